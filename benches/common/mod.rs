@@ -0,0 +1,36 @@
+//! Timing shared by the benchmarks.
+
+use std::time::{Duration, Instant};
+
+use nova_tw::language::{generate_parsed_ast, AstInterpreter, STACK_SIZE};
+
+const RUNS: usize = 5;
+
+/// the fastest of several runs of `source`, parsing is not included
+fn time(source: &str) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let statements = generate_parsed_ast(source, "bench.nova").unwrap();
+            let mut interpreter = AstInterpreter::new();
+
+            let start = Instant::now();
+            interpreter.interpret(statements).unwrap();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+/// times each named source on a thread with the interpreter's stack, `report` prints the result
+pub fn run<const N: usize>(benchmarks: [(&'static str, String); N], report: fn(&str, Duration)) {
+    std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || {
+            for (name, source) in &benchmarks {
+                report(name, time(source));
+            }
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+}
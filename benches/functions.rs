@@ -0,0 +1,81 @@
+//! Times defining closures and calling methods whose bodies are large, the cost of both should
+//! not depend on the size of the function body.
+//!
+//! Run with `cargo bench --bench functions`.
+
+mod common;
+
+const ITERATIONS: usize = 20000;
+const BODY_LINES: usize = 200;
+
+/// statements padding out a function body, none of them run
+fn body() -> String {
+    let mut body = String::from("        if step < 0\n");
+    for line in 0..BODY_LINES {
+        body.push_str(&format!("            let value_{} = step * {} + {}\n", line, line, line));
+    }
+    body.push_str("        end\n");
+    body
+}
+
+/// a function defining a closure with a large body on every call
+fn closures() -> String {
+    format!(
+        "fn make_counter(start)
+    fn counter(step)
+{}        return start + step
+    end
+    return counter
+end
+
+let i = 0
+while i < {}
+    let counter = make_counter(i)
+    i = i + 1
+end
+",
+        body(),
+        ITERATIONS
+    )
+}
+
+/// an instance whose large method is bound on every call
+fn methods() -> String {
+    format!(
+        "class Counter
+    fn init()
+        this.count = 0
+    end
+
+    fn add(step)
+{}        this.count = this.count + step
+    end
+end
+
+let counter = Counter()
+let i = 0
+while i < {}
+    counter.add(1)
+    i = i + 1
+end
+",
+        body(),
+        ITERATIONS
+    )
+}
+
+fn main() {
+    common::run(
+        [("closures", closures()), ("methods", methods())],
+        |name, elapsed| {
+            println!(
+                "{:<10} {} calls, {} line bodies: {:?} ({:?} per call)",
+                name,
+                ITERATIONS,
+                BODY_LINES,
+                elapsed,
+                elapsed / ITERATIONS as u32
+            )
+        },
+    );
+}
@@ -0,0 +1,94 @@
+//! Times method calls and field reads on instances of a class several superclasses below the
+//! one defining the method, every call has to find the method somewhere along the chain.
+//!
+//! Run with `cargo bench --bench methods`.
+
+mod common;
+
+const ITERATIONS: usize = 100000;
+const DEPTH: usize = 8;
+
+/// a chain of `DEPTH` classes below `Base`, the last one called `Leaf`
+fn classes() -> String {
+    let mut classes = String::from(
+        "class Base
+    fn add(step)
+        this.count = this.count + step
+    end
+end
+",
+    );
+
+    let mut superclass = String::from("Base");
+    for level in 0..DEPTH {
+        let name = if level + 1 == DEPTH {
+            String::from("Leaf")
+        } else {
+            format!("Level{}", level)
+        };
+        classes.push_str(&format!(
+            "
+class {} : {}
+    fn level_{}()
+        return {}
+    end
+end
+",
+            name, superclass, level, level
+        ));
+        superclass = name;
+    }
+
+    classes
+}
+
+/// calls a method inherited from the top of the chain
+fn inherited() -> String {
+    format!(
+        "{}
+let leaf = Leaf()
+leaf.count = 0
+let i = 0
+while i < {}
+    leaf.add(1)
+    i = i + 1
+end
+",
+        classes(),
+        ITERATIONS
+    )
+}
+
+/// reads a field, which is found before any method is looked up
+fn fields() -> String {
+    format!(
+        "{}
+let leaf = Leaf()
+leaf.count = 0
+let total = 0
+let i = 0
+while i < {}
+    total = total + leaf.count
+    i = i + 1
+end
+",
+        classes(),
+        ITERATIONS
+    )
+}
+
+fn main() {
+    common::run(
+        [("inherited", inherited()), ("fields", fields())],
+        |name, elapsed| {
+            println!(
+                "{:<10} {} lookups, {} superclasses: {:?} ({:?} per lookup)",
+                name,
+                ITERATIONS,
+                DEPTH,
+                elapsed,
+                elapsed / ITERATIONS as u32
+            )
+        },
+    );
+}
@@ -2,23 +2,45 @@ mod abstract_syntax_tree;
 pub mod errors;
 mod scanner;
 
-pub use abstract_syntax_tree::{interpreter::AstInterpreter, parser::AstParser};
+pub use abstract_syntax_tree::{
+    heap::HeapStats,
+    interpreter::{AstInterpreter, DEFAULT_MAX_STACK_MEMORY, STACK_SIZE},
+    module_loader::{FileSystemLoader, MemoryLoader, ModuleLoader},
+    optimizer::optimize,
+    package::{
+        Dependency, LockedPackage, Lockfile, Manifest, Package, PackageLoader, Resolution, LOCK_FILE,
+        MANIFEST_FILE,
+    },
+    parser::AstParser,
+};
 pub use scanner::token::debug_print_tokens;
 pub use scanner::Scanner;
 
-pub use scanner::object::{Callable, NativeCall, Object};
+pub use scanner::object::{Callable, NativeCall, Object, TupleObject};
+pub use scanner::symbol::Symbol;
+pub use scanner::value::Value;
 pub use scanner::token::{Token, TokenType};
 
 pub use abstract_syntax_tree::{expression::*, statement::*, visitor::*};
 
+/// parses and runs `source` on a thread with enough native stack, see `STACK_SIZE`
 pub fn interpret(source: &str, filename: &str) -> Result<(), errors::Error> {
-    let statements = generate_parsed_ast(source, filename)?;
-    //println!("Expression: {:?}", expression);
+    std::thread::scope(|scope| {
+        let run = || {
+            let statements = generate_parsed_ast(source, filename)?;
 
-    let mut interpreter = AstInterpreter::new();
-    //println!("{:?}", &statements);
-    interpreter.interpret(statements)?;
-    Ok(())
+            let mut interpreter = AstInterpreter::new();
+            interpreter.interpret(statements)?;
+            interpreter.run_event_loop()
+        };
+
+        std::thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn_scoped(scope, run)
+            .map_err(|err| errors::Error::Runtime(err.to_string()))?
+            .join()
+            .unwrap_or_else(|_| Err(errors::Error::Runtime("interpreter thread panicked".to_string())))
+    })
 }
 
 pub fn generate_parsed_ast(source: &str, filename: &str) -> Result<Vec<Statement>, errors::Error> {
@@ -1,6 +1,15 @@
+pub mod ast_cache;
+pub mod control_flow;
+pub mod coroutine;
 pub mod environment;
+pub mod event_loop;
 pub mod expression;
+pub mod heap;
+pub mod inline_cache;
 pub mod interpreter;
+pub mod module_loader;
+pub mod optimizer;
+pub mod package;
 pub mod parser;
 pub mod statement;
 pub mod visitor;
@@ -0,0 +1,1071 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use super::{
+    expression::{
+        await_expression::Await, binary::Binary, call::Call, grouping::Grouping, literal::Literal,
+        tuple::Tuple, unary::Unary, variable::Variable, Expression,
+    },
+    inline_cache::InlineCache,
+    statement::{
+        assignment::{Assign, DestructuringAssign, Get, Set},
+        class::ClassStatement,
+        declaration::{TupleDeclaration, VariableDeclaration},
+        enum_statement::{EnumStatement, VariantDeclaration},
+        function::{FunctionStatement, Parameter},
+        match_statement::{MatchArm, MatchStatement, Pattern},
+        Block, ForLoop, IfStatement, Import, ImportBinding, Include, Statement, WhileLoop,
+    },
+};
+use crate::language::scanner::{
+    object::Object,
+    symbol::Symbol,
+    token::{Token, TokenType},
+};
+
+/// the directory next to a source file holding the parsed versions of the files in it
+pub const CACHE_DIRECTORY: &str = ".nova_cache";
+
+const MAGIC: &[u8] = b"NOVAAST\0";
+/// bumped whenever the syntax tree, its encoding or the syntax the parser accepts changes
+const FORMAT_VERSION: u32 = 3;
+/// statements and expressions nested deeper than this are not saved, encoding and decoding
+/// them recurses
+const MAX_DEPTH: usize = 128;
+
+/// the statements parsed from `source` on an earlier run, if they were saved for the same path,
+/// contents and interpreter version
+pub fn load(path: &str, source: &str) -> Option<Vec<Statement>> {
+    let bytes = fs::read(cache_path(path)?).ok()?;
+    let mut decoder = Decoder::new(&bytes);
+
+    let header_matches = decoder.take(MAGIC.len())? == MAGIC
+        && u32::decode(&mut decoder)? == FORMAT_VERSION
+        && String::decode(&mut decoder)? == env!("CARGO_PKG_VERSION")
+        && String::decode(&mut decoder)? == path
+        && u64::decode(&mut decoder)? == content_hash(source);
+    if !header_matches {
+        return None;
+    }
+
+    let statements = Vec::decode(&mut decoder)?;
+    decoder.is_finished().then_some(statements)
+}
+
+/// saves the statements parsed from `source` for later runs, a cache that can not be written
+/// only means the file is parsed again next time
+pub fn store(path: &str, source: &str, statements: &[Statement]) {
+    let Some(cache_path) = cache_path(path) else {
+        return;
+    };
+
+    let mut encoder = Encoder::default();
+    encoder.bytes.extend_from_slice(MAGIC);
+    FORMAT_VERSION.encode(&mut encoder);
+    env!("CARGO_PKG_VERSION").to_string().encode(&mut encoder);
+    path.to_string().encode(&mut encoder);
+    content_hash(source).encode(&mut encoder);
+    statements.len().encode(&mut encoder);
+    statements.iter().for_each(|statement| statement.encode(&mut encoder));
+
+    if encoder.unsupported {
+        return;
+    }
+
+    if let Some(directory) = cache_path.parent() {
+        let _ = fs::create_dir_all(directory);
+    }
+    // written aside and renamed so a reader never sees half a file
+    let partial = cache_path.with_extension("partial");
+    if fs::write(&partial, &encoder.bytes).is_ok() {
+        let _ = fs::rename(&partial, &cache_path);
+    }
+}
+
+/// `dir/.nova_cache/name.ast` for `dir/name`
+fn cache_path(path: &str) -> Option<PathBuf> {
+    let path = Path::new(path);
+    let mut file_name = path.file_name()?.to_os_string();
+    file_name.push(".ast");
+
+    let directory = path.parent().unwrap_or(Path::new(""));
+    Some(directory.join(CACHE_DIRECTORY).join(file_name))
+}
+
+/// 64 bit FNV-1a, stable across builds unlike the standard library hashers
+fn content_hash(source: &str) -> u64 {
+    source.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[derive(Default)]
+struct Encoder {
+    bytes: Vec<u8>,
+    /// set when the tree holds something the format can not represent
+    unsupported: bool,
+    /// statements and expressions enclosing the one being encoded
+    depth: usize,
+}
+
+impl Encoder {
+    /// encodes a statement or an expression, marking the tree unsupported once it is too deep
+    fn nested(&mut self, encode: impl FnOnce(&mut Self)) {
+        if self.depth >= MAX_DEPTH {
+            self.unsupported = true;
+            return;
+        }
+
+        self.depth += 1;
+        encode(self);
+        self.depth -= 1;
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    /// statements and expressions enclosing the one being decoded
+    depth: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            position: 0,
+            depth: 0,
+        }
+    }
+
+    /// decodes a statement or an expression, failing once the tree is deeper than any the
+    /// encoder saves
+    fn nested<T>(&mut self, decode: impl FnOnce(&mut Self) -> Option<T>) -> Option<T> {
+        if self.depth >= MAX_DEPTH {
+            return None;
+        }
+
+        self.depth += 1;
+        let value = decode(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn take(&mut self, length: usize) -> Option<&'a [u8]> {
+        let end = self.position.checked_add(length)?;
+        let bytes = self.bytes.get(self.position..end)?;
+        self.position = end;
+        Some(bytes)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.position == self.bytes.len()
+    }
+}
+
+/// A part of the syntax tree that can be written to and read back from the cache.
+///
+/// Decoding returns none for malformed input, the cache is then ignored.
+trait CacheFormat: Sized {
+    fn encode(&self, encoder: &mut Encoder);
+    fn decode(decoder: &mut Decoder) -> Option<Self>;
+}
+
+impl CacheFormat for u8 {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.bytes.push(*self);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(decoder.take(1)?[0])
+    }
+}
+
+impl CacheFormat for u32 {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.bytes.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(Self::from_le_bytes(decoder.take(4)?.try_into().ok()?))
+    }
+}
+
+impl CacheFormat for u64 {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.bytes.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(Self::from_le_bytes(decoder.take(8)?.try_into().ok()?))
+    }
+}
+
+impl CacheFormat for usize {
+    fn encode(&self, encoder: &mut Encoder) {
+        (*self as u64).encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        u64::decode(decoder)?.try_into().ok()
+    }
+}
+
+impl CacheFormat for f64 {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.to_bits().encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(Self::from_bits(u64::decode(decoder)?))
+    }
+}
+
+impl CacheFormat for bool {
+    fn encode(&self, encoder: &mut Encoder) {
+        (*self as u8).encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        match u8::decode(decoder)? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl CacheFormat for String {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.len().encode(encoder);
+        encoder.bytes.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        let length = usize::decode(decoder)?;
+        let bytes = decoder.take(length)?;
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+impl<T: CacheFormat> CacheFormat for Option<T> {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.is_some().encode(encoder);
+        if let Some(value) = self {
+            value.encode(encoder);
+        }
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        match bool::decode(decoder)? {
+            true => Some(Some(T::decode(decoder)?)),
+            false => Some(None),
+        }
+    }
+}
+
+impl<T: CacheFormat> CacheFormat for Vec<T> {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.len().encode(encoder);
+        self.iter().for_each(|value| value.encode(encoder));
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        let length = usize::decode(decoder)?;
+        // every value takes at least a byte, a corrupt length can not reserve more than the file
+        let mut values = Vec::with_capacity(length.min(decoder.bytes.len()));
+        for _ in 0..length {
+            values.push(T::decode(decoder)?);
+        }
+        Some(values)
+    }
+}
+
+impl<T: CacheFormat> CacheFormat for Arc<T> {
+    fn encode(&self, encoder: &mut Encoder) {
+        (**self).encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(Arc::new(T::decode(decoder)?))
+    }
+}
+
+impl<T: CacheFormat> CacheFormat for Arc<[T]> {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.len().encode(encoder);
+        self.iter().for_each(|value| value.encode(encoder));
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Vec::decode(decoder).map(Vec::into)
+    }
+}
+
+impl<A: CacheFormat, B: CacheFormat> CacheFormat for (A, B) {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.0.encode(encoder);
+        self.1.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some((A::decode(decoder)?, B::decode(decoder)?))
+    }
+}
+
+impl<A: CacheFormat, B: CacheFormat, C: CacheFormat> CacheFormat for (A, B, C) {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.0.encode(encoder);
+        self.1.encode(encoder);
+        self.2.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some((A::decode(decoder)?, B::decode(decoder)?, C::decode(decoder)?))
+    }
+}
+
+/// symbols are numbered per run, the name is stored and interned again
+impl CacheFormat for Symbol {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.as_str().to_string().encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(Symbol::intern(&String::decode(decoder)?))
+    }
+}
+
+impl CacheFormat for TokenType {
+    fn encode(&self, encoder: &mut Encoder) {
+        (*self as u8).encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        TokenType::ALL.get(u8::decode(decoder)? as usize).copied()
+    }
+}
+
+/// only the objects a parser produces, literals and the values held by tokens
+impl CacheFormat for Object {
+    fn encode(&self, encoder: &mut Encoder) {
+        match self {
+            Object::None => 0u8.encode(encoder),
+            Object::Bool(boolean) => {
+                1u8.encode(encoder);
+                boolean.encode(encoder);
+            }
+            Object::Number(number) => {
+                2u8.encode(encoder);
+                number.encode(encoder);
+            }
+            Object::String(string) => {
+                3u8.encode(encoder);
+                string.encode(encoder);
+            }
+            _ => encoder.unsupported = true,
+        }
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        match u8::decode(decoder)? {
+            0 => Some(Object::None),
+            1 => Some(Object::Bool(bool::decode(decoder)?)),
+            2 => Some(Object::Number(f64::decode(decoder)?)),
+            3 => Some(Object::String(String::decode(decoder)?)),
+            _ => None,
+        }
+    }
+}
+
+impl CacheFormat for Token {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.token_type.encode(encoder);
+        self.object.encode(encoder);
+        self.line.encode(encoder);
+        self.symbol.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(Token {
+            token_type: TokenType::decode(decoder)?,
+            object: Object::decode(decoder)?,
+            line: usize::decode(decoder)?,
+            symbol: Option::decode(decoder)?,
+        })
+    }
+}
+
+impl CacheFormat for Statement {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.nested(|encoder| match self {
+                Statement::None => 0u8.encode(encoder),
+                Statement::If(if_statement) => {
+                    1u8.encode(encoder);
+                    if_statement.encode(encoder);
+                }
+                Statement::WhileLoop(while_loop) => {
+                    2u8.encode(encoder);
+                    while_loop.encode(encoder);
+                }
+                Statement::ForLoop(for_loop) => {
+                    3u8.encode(encoder);
+                    for_loop.encode(encoder);
+                }
+                Statement::Block(block) => {
+                    4u8.encode(encoder);
+                    block.encode(encoder);
+                }
+                Statement::FunctionStatement(function) => {
+                    5u8.encode(encoder);
+                    function.encode(encoder);
+                }
+                Statement::ReturnStatement(value) => {
+                    6u8.encode(encoder);
+                    value.encode(encoder);
+                }
+                Statement::Yield(value) => {
+                    7u8.encode(encoder);
+                    value.encode(encoder);
+                }
+                Statement::Break(keyword) => {
+                    8u8.encode(encoder);
+                    keyword.encode(encoder);
+                }
+                Statement::Continue(keyword) => {
+                    9u8.encode(encoder);
+                    keyword.encode(encoder);
+                }
+                Statement::VariableDeclaration(declaration) => {
+                    10u8.encode(encoder);
+                    declaration.encode(encoder);
+                }
+                Statement::TupleDeclaration(declaration) => {
+                    11u8.encode(encoder);
+                    declaration.encode(encoder);
+                }
+                Statement::ExpressionStatement(expression) => {
+                    12u8.encode(encoder);
+                    expression.encode(encoder);
+                }
+                Statement::ClassStatement(class_statement) => {
+                    13u8.encode(encoder);
+                    class_statement.encode(encoder);
+                }
+                Statement::EnumStatement(enum_statement) => {
+                    14u8.encode(encoder);
+                    enum_statement.encode(encoder);
+                }
+                Statement::Include(include) => {
+                    15u8.encode(encoder);
+                    include.encode(encoder);
+                }
+                Statement::Match(match_statement) => {
+                    16u8.encode(encoder);
+                    match_statement.encode(encoder);
+                }
+                Statement::Import(import) => {
+                    17u8.encode(encoder);
+                    import.encode(encoder);
+                }
+        });
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        decoder.nested(|decoder| {
+            let statement = match u8::decode(decoder)? {
+                0 => Statement::None,
+                1 => Statement::If(Arc::decode(decoder)?),
+                2 => Statement::WhileLoop(Arc::decode(decoder)?),
+                3 => Statement::ForLoop(Arc::decode(decoder)?),
+                4 => Statement::Block(Block::decode(decoder)?),
+                5 => Statement::FunctionStatement(Arc::decode(decoder)?),
+                6 => Statement::ReturnStatement(Option::decode(decoder)?),
+                7 => Statement::Yield(Option::decode(decoder)?),
+                8 => Statement::Break(Token::decode(decoder)?),
+                9 => Statement::Continue(Token::decode(decoder)?),
+                10 => Statement::VariableDeclaration(VariableDeclaration::decode(decoder)?),
+                11 => Statement::TupleDeclaration(Arc::decode(decoder)?),
+                12 => Statement::ExpressionStatement(<(Expression, usize, String)>::decode(decoder)?),
+                13 => Statement::ClassStatement(ClassStatement::decode(decoder)?),
+                14 => Statement::EnumStatement(EnumStatement::decode(decoder)?),
+                15 => Statement::Include(Include::decode(decoder)?),
+                16 => Statement::Match(Arc::decode(decoder)?),
+                17 => Statement::Import(Import::decode(decoder)?),
+                _ => return None,
+            };
+            Some(statement)
+        })
+    }
+}
+
+impl CacheFormat for Block {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.statements.encode(encoder);
+        self.line.encode(encoder);
+        self.filename.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(Block {
+            statements: Arc::decode(decoder)?,
+            line: usize::decode(decoder)?,
+            filename: String::decode(decoder)?,
+        })
+    }
+}
+
+impl CacheFormat for IfStatement {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.condition.encode(encoder);
+        self.then_branch.encode(encoder);
+        self.else_branch.encode(encoder);
+        self.line.encode(encoder);
+        self.filename.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(IfStatement {
+            condition: Expression::decode(decoder)?,
+            then_branch: Statement::decode(decoder)?,
+            else_branch: Option::decode(decoder)?,
+            line: usize::decode(decoder)?,
+            filename: String::decode(decoder)?,
+        })
+    }
+}
+
+impl CacheFormat for WhileLoop {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.condition.encode(encoder);
+        self.body.encode(encoder);
+        self.line.encode(encoder);
+        self.filename.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(WhileLoop {
+            condition: Expression::decode(decoder)?,
+            body: Statement::decode(decoder)?,
+            line: usize::decode(decoder)?,
+            filename: String::decode(decoder)?,
+        })
+    }
+}
+
+impl CacheFormat for ForLoop {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.names.encode(encoder);
+        self.destructure.encode(encoder);
+        self.iterable.encode(encoder);
+        self.body.encode(encoder);
+        self.line.encode(encoder);
+        self.filename.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(ForLoop {
+            names: Vec::decode(decoder)?,
+            destructure: bool::decode(decoder)?,
+            iterable: Expression::decode(decoder)?,
+            body: Statement::decode(decoder)?,
+            line: usize::decode(decoder)?,
+            filename: String::decode(decoder)?,
+        })
+    }
+}
+
+impl CacheFormat for FunctionStatement {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.name.encode(encoder);
+        self.parameters.encode(encoder);
+        self.body.encode(encoder);
+        self.generator.encode(encoder);
+        self.asynchronous.encode(encoder);
+        self.line.encode(encoder);
+        self.filename.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(FunctionStatement {
+            name: Token::decode(decoder)?,
+            parameters: Vec::decode(decoder)?,
+            body: Block::decode(decoder)?,
+            generator: bool::decode(decoder)?,
+            asynchronous: bool::decode(decoder)?,
+            line: usize::decode(decoder)?,
+            filename: String::decode(decoder)?,
+        })
+    }
+}
+
+impl CacheFormat for Parameter {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.name.encode(encoder);
+        self.default.encode(encoder);
+        self.variadic.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(Parameter {
+            name: Token::decode(decoder)?,
+            default: Option::decode(decoder)?,
+            variadic: bool::decode(decoder)?,
+        })
+    }
+}
+
+impl CacheFormat for VariableDeclaration {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.name.encode(encoder);
+        self.initializer.encode(encoder);
+        self.constant.encode(encoder);
+        self.line.encode(encoder);
+        self.filename.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(VariableDeclaration {
+            name: Token::decode(decoder)?,
+            initializer: Option::decode(decoder)?,
+            constant: bool::decode(decoder)?,
+            line: usize::decode(decoder)?,
+            filename: String::decode(decoder)?,
+        })
+    }
+}
+
+impl CacheFormat for TupleDeclaration {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.names.encode(encoder);
+        self.initializer.encode(encoder);
+        self.constant.encode(encoder);
+        self.line.encode(encoder);
+        self.filename.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(TupleDeclaration {
+            names: Vec::decode(decoder)?,
+            initializer: Expression::decode(decoder)?,
+            constant: bool::decode(decoder)?,
+            line: usize::decode(decoder)?,
+            filename: String::decode(decoder)?,
+        })
+    }
+}
+
+impl CacheFormat for ClassStatement {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.name.encode(encoder);
+        self.superclass.encode(encoder);
+        self.methods.encode(encoder);
+        self.line.encode(encoder);
+        self.filename.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(ClassStatement {
+            name: Token::decode(decoder)?,
+            superclass: Option::decode(decoder)?,
+            methods: Vec::decode(decoder)?,
+            line: usize::decode(decoder)?,
+            filename: String::decode(decoder)?,
+        })
+    }
+}
+
+impl CacheFormat for EnumStatement {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.name.encode(encoder);
+        self.variants.encode(encoder);
+        self.line.encode(encoder);
+        self.filename.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(EnumStatement {
+            name: Token::decode(decoder)?,
+            variants: Vec::decode(decoder)?,
+            line: usize::decode(decoder)?,
+            filename: String::decode(decoder)?,
+        })
+    }
+}
+
+impl CacheFormat for VariantDeclaration {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.name.encode(encoder);
+        self.fields.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(VariantDeclaration {
+            name: Token::decode(decoder)?,
+            fields: Option::decode(decoder)?,
+        })
+    }
+}
+
+impl CacheFormat for Include {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.files.encode(encoder);
+        self.line.encode(encoder);
+        self.filename.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(Include {
+            files: Vec::decode(decoder)?,
+            line: usize::decode(decoder)?,
+            filename: String::decode(decoder)?,
+        })
+    }
+}
+
+impl CacheFormat for Import {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.path.encode(encoder);
+        self.binding.encode(encoder);
+        self.line.encode(encoder);
+        self.filename.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(Import {
+            path: String::decode(decoder)?,
+            binding: ImportBinding::decode(decoder)?,
+            line: usize::decode(decoder)?,
+            filename: String::decode(decoder)?,
+        })
+    }
+}
+
+impl CacheFormat for ImportBinding {
+    fn encode(&self, encoder: &mut Encoder) {
+        match self {
+            ImportBinding::Module(name) => {
+                0u8.encode(encoder);
+                name.encode(encoder);
+            }
+            ImportBinding::Names(names) => {
+                1u8.encode(encoder);
+                names.encode(encoder);
+            }
+        }
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        match u8::decode(decoder)? {
+            0 => Some(ImportBinding::Module(Token::decode(decoder)?)),
+            1 => Some(ImportBinding::Names(Vec::decode(decoder)?)),
+            _ => None,
+        }
+    }
+}
+
+impl CacheFormat for MatchStatement {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.value.encode(encoder);
+        self.arms.encode(encoder);
+        self.line.encode(encoder);
+        self.filename.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(MatchStatement {
+            value: Expression::decode(decoder)?,
+            arms: Vec::decode(decoder)?,
+            line: usize::decode(decoder)?,
+            filename: String::decode(decoder)?,
+        })
+    }
+}
+
+impl CacheFormat for MatchArm {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.pattern.encode(encoder);
+        self.guard.encode(encoder);
+        self.body.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(MatchArm {
+            pattern: Pattern::decode(decoder)?,
+            guard: Option::decode(decoder)?,
+            body: Block::decode(decoder)?,
+        })
+    }
+}
+
+impl CacheFormat for Pattern {
+    fn encode(&self, encoder: &mut Encoder) {
+        match self {
+            Pattern::Wildcard => 0u8.encode(encoder),
+            Pattern::Literal(object) => {
+                1u8.encode(encoder);
+                object.encode(encoder);
+            }
+            Pattern::Range(low, high) => {
+                2u8.encode(encoder);
+                low.encode(encoder);
+                high.encode(encoder);
+            }
+            Pattern::Binding(name) => {
+                3u8.encode(encoder);
+                name.encode(encoder);
+            }
+            Pattern::Tuple(patterns) => {
+                4u8.encode(encoder);
+                patterns.encode(encoder);
+            }
+            Pattern::Instance { class, fields } => {
+                5u8.encode(encoder);
+                class.encode(encoder);
+                fields.encode(encoder);
+            }
+            Pattern::Variant {
+                enum_name,
+                variant,
+                fields,
+            } => {
+                6u8.encode(encoder);
+                enum_name.encode(encoder);
+                variant.encode(encoder);
+                fields.encode(encoder);
+            }
+        }
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        let pattern = match u8::decode(decoder)? {
+            0 => Pattern::Wildcard,
+            1 => Pattern::Literal(Object::decode(decoder)?),
+            2 => Pattern::Range(Object::decode(decoder)?, Object::decode(decoder)?),
+            3 => Pattern::Binding(Token::decode(decoder)?),
+            4 => Pattern::Tuple(Vec::decode(decoder)?),
+            5 => Pattern::Instance {
+                class: Token::decode(decoder)?,
+                fields: Vec::decode(decoder)?,
+            },
+            6 => Pattern::Variant {
+                enum_name: Token::decode(decoder)?,
+                variant: Token::decode(decoder)?,
+                fields: Option::decode(decoder)?,
+            },
+            _ => return None,
+        };
+        Some(pattern)
+    }
+}
+
+impl CacheFormat for Expression {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.nested(|encoder| match self {
+                Expression::Binary(binary) => {
+                    0u8.encode(encoder);
+                    binary.left.encode(encoder);
+                    binary.right.encode(encoder);
+                    binary.operator.encode(encoder);
+                }
+                Expression::Unary(unary) => {
+                    1u8.encode(encoder);
+                    unary.operator.encode(encoder);
+                    unary.right.encode(encoder);
+                }
+                Expression::Literal(literal) => {
+                    2u8.encode(encoder);
+                    literal.object.encode(encoder);
+                }
+                Expression::Grouping(grouping) => {
+                    3u8.encode(encoder);
+                    grouping.expression.encode(encoder);
+                }
+                Expression::Call(call) => {
+                    4u8.encode(encoder);
+                    call.callee.encode(encoder);
+                    call.paren.encode(encoder);
+                    call.arguments.encode(encoder);
+                    call.keyword_arguments.encode(encoder);
+                }
+                Expression::Variable(variable) => {
+                    5u8.encode(encoder);
+                    variable.name.encode(encoder);
+                }
+                Expression::Assign(assign) => {
+                    6u8.encode(encoder);
+                    assign.name.encode(encoder);
+                    assign.value.encode(encoder);
+                    assign.line.encode(encoder);
+                    assign.filename.encode(encoder);
+                }
+                Expression::Get(get) => {
+                    7u8.encode(encoder);
+                    get.object.encode(encoder);
+                    get.name.encode(encoder);
+                    get.arguments.encode(encoder);
+                    get.keyword_arguments.encode(encoder);
+                    get.line.encode(encoder);
+                    get.filename.encode(encoder);
+                }
+                Expression::Set(set) => {
+                    8u8.encode(encoder);
+                    set.object.encode(encoder);
+                    set.name.encode(encoder);
+                    set.value.encode(encoder);
+                    set.line.encode(encoder);
+                    set.filename.encode(encoder);
+                }
+                Expression::Tuple(tuple) => {
+                    9u8.encode(encoder);
+                    tuple.elements.encode(encoder);
+                }
+                Expression::DestructuringAssign(assign) => {
+                    10u8.encode(encoder);
+                    assign.targets.encode(encoder);
+                    assign.value.encode(encoder);
+                    assign.line.encode(encoder);
+                    assign.filename.encode(encoder);
+                }
+                Expression::Await(await_expression) => {
+                    11u8.encode(encoder);
+                    await_expression.keyword.encode(encoder);
+                    await_expression.value.encode(encoder);
+                }
+        });
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        decoder.nested(|decoder| {
+            let expression = match u8::decode(decoder)? {
+                0 => Expression::Binary(Arc::new(Binary {
+                    left: Expression::decode(decoder)?,
+                    right: Expression::decode(decoder)?,
+                    operator: Token::decode(decoder)?,
+                })),
+                1 => Expression::Unary(Arc::new(Unary {
+                    operator: Token::decode(decoder)?,
+                    right: Expression::decode(decoder)?,
+                })),
+                2 => Expression::Literal(Arc::new(Literal::new(Object::decode(decoder)?))),
+                3 => Expression::Grouping(Arc::new(Grouping::new(Expression::decode(decoder)?))),
+                4 => Expression::Call(Arc::new(Call {
+                    callee: Expression::decode(decoder)?,
+                    paren: Token::decode(decoder)?,
+                    arguments: Vec::decode(decoder)?,
+                    keyword_arguments: Vec::decode(decoder)?,
+                })),
+                5 => Expression::Variable(Arc::new(Variable::new(Token::decode(decoder)?))),
+                6 => Expression::Assign(Arc::new(Assign {
+                    name: Token::decode(decoder)?,
+                    value: Expression::decode(decoder)?,
+                    line: usize::decode(decoder)?,
+                    filename: String::decode(decoder)?,
+                })),
+                7 => Expression::Get(Arc::new(Get {
+                    object: Expression::decode(decoder)?,
+                    name: Token::decode(decoder)?,
+                    arguments: Option::decode(decoder)?,
+                    keyword_arguments: Vec::decode(decoder)?,
+                    cache: InlineCache::default(),
+                    line: usize::decode(decoder)?,
+                    filename: String::decode(decoder)?,
+                })),
+                8 => Expression::Set(Arc::new(Set {
+                    object: Expression::decode(decoder)?,
+                    name: Token::decode(decoder)?,
+                    value: Expression::decode(decoder)?,
+                    line: usize::decode(decoder)?,
+                    filename: String::decode(decoder)?,
+                })),
+                9 => Expression::Tuple(Arc::new(Tuple::new(Vec::decode(decoder)?))),
+                10 => Expression::DestructuringAssign(Arc::new(DestructuringAssign {
+                    targets: Vec::decode(decoder)?,
+                    value: Expression::decode(decoder)?,
+                    line: usize::decode(decoder)?,
+                    filename: String::decode(decoder)?,
+                })),
+                11 => Expression::Await(Arc::new(Await {
+                    keyword: Token::decode(decoder)?,
+                    value: Expression::decode(decoder)?,
+                })),
+                _ => return None,
+            };
+            Some(expression)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+
+    use super::{CacheFormat, Decoder, Encoder, FORMAT_VERSION};
+    use crate::language::{generate_parsed_ast, Expression, Statement, TokenType};
+
+    /// the order token types are saved in by `FORMAT_VERSION`
+    const TOKEN_TYPES: &str = "Number String Plus Minus Star Slash Percent Colon LeftParen RightParen \
+        Identifier Dot DotDot Ellipsis Comma None Eof Caret Fn Async Await Class Enum Let Const Block \
+        Delete If Else For In While NewLine End Return Yield Break Continue True False Include Import \
+        From As Match Case And Or Not Equal EqualEqual ColonEqual NotEqual GreaterEqual LessEqual \
+        Greater Less";
+
+    #[test]
+    fn test_ast_cache_round_trip() {
+        let source = "fn area(shape, scale = 2, ...rest)\nmatch shape\ncase Shape.Circle(r) if r > 1\nreturn r ^ 2 * scale\ncase (a, 1..3)\nreturn -a\nend\nend\nclass Point : Base\nfn init(x)\nthis.x = x\nend\nend\nlet (q, r) = divmod(7, 2)\nfor (k, v) in pairs\nprintln(k, v.name(key: true))\nend\n";
+        let statements = generate_parsed_ast(source, "shapes.nova").unwrap();
+
+        let mut encoder = Encoder::default();
+        statements.encode(&mut encoder);
+        assert!(!encoder.unsupported);
+
+        let decoded = Vec::<Statement>::decode(&mut Decoder::new(&encoder.bytes)).unwrap();
+        assert_eq!(format!("{:?}", decoded), format!("{:?}", statements));
+
+        // a truncated file is rejected rather than misread
+        let truncated = &encoder.bytes[..encoder.bytes.len() - 1];
+        assert!(Vec::<Statement>::decode(&mut Decoder::new(truncated)).is_none());
+    }
+
+    #[test]
+    fn test_ast_cache_token_types() {
+        for (index, token_type) in TokenType::ALL.iter().enumerate() {
+            assert_eq!(*token_type as usize, index);
+        }
+
+        // token types are saved by position, adding, removing or reordering one needs a new
+        // FORMAT_VERSION and this list updated with it
+        let token_types: Vec<String> = TokenType::ALL.iter().map(|token_type| format!("{:?}", token_type)).collect();
+        assert_eq!((FORMAT_VERSION, token_types.join(" ")), (3, TOKEN_TYPES.to_string()));
+    }
+
+    #[test]
+    fn test_ast_cache_deep_trees() {
+        let statements = generate_parsed_ast(&format!("let x = 1{}\n", "+1".repeat(2000)), "").unwrap();
+        let mut literal = Encoder::default();
+        Expression::decode(&mut Decoder::new(&[2, 0])).unwrap().encode(&mut literal);
+
+        // encoding or decoding either tree by recursion would overflow this stack, the syntax
+        // tree is shared with the thread and dropped here
+        let shared = statements.clone();
+        let run = thread::Builder::new()
+            .stack_size(2 * 1024 * 1024)
+            .spawn(move || {
+                let mut encoder = Encoder::default();
+                shared.encode(&mut encoder);
+                assert!(encoder.unsupported);
+
+                // a hundred thousand groupings around a literal
+                let mut bytes = vec![3u8; 100_000];
+                bytes.extend_from_slice(&literal.bytes);
+                assert!(Expression::decode(&mut Decoder::new(&bytes)).is_none());
+            })
+            .unwrap();
+
+        run.join().unwrap();
+        drop(statements);
+    }
+}
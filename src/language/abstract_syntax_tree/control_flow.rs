@@ -0,0 +1,30 @@
+use crate::language::scanner::{object::WrappedObject, symbol::Symbol};
+
+/// How a statement finished.
+///
+/// Jumps are ordinary results rather than errors, a statement hands them to the enclosing
+/// statements until the loop or function they leave takes them.
+#[derive(Debug, Clone)]
+pub enum ControlFlow {
+    /// carries on with the next statement
+    Normal,
+    /// leaves the enclosing function with a value
+    Return(WrappedObject),
+    /// leaves the enclosing loop
+    Break,
+    /// skips to the next iteration of the enclosing loop
+    Continue,
+    /// leaves the enclosing function with the result of a call, made once the function is gone
+    TailCall(Box<TailCall>),
+}
+
+/// A call in tail position, `return f(...)`.
+///
+/// The callee and arguments are evaluated by the returning function, the call itself is made by
+/// whoever called that function so tail recursion does not grow the host stack.
+#[derive(Debug, Clone)]
+pub struct TailCall {
+    pub callee: WrappedObject,
+    pub arguments: Vec<WrappedObject>,
+    pub keyword_arguments: Vec<(Symbol, WrappedObject)>,
+}
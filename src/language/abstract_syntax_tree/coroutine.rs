@@ -0,0 +1,354 @@
+use std::sync::{Arc, RwLock};
+
+use super::{
+    control_flow::ControlFlow,
+    environment::Environment,
+    heap::Tracer,
+    interpreter::AstInterpreter,
+    statement::{ForLoop, Statement, WhileLoop},
+};
+use crate::language::{
+    errors,
+    scanner::object::{mark_awaited, WrappedObject},
+    Object,
+};
+
+/// A position inside a suspended function body.
+///
+/// Statements that contain a `yield`, or an `await` in an async function, are unpacked into
+/// frames so their progress can be kept between resumptions, everything else is executed by the
+/// interpreter as usual.
+#[derive(Debug)]
+enum Frame {
+    Block {
+        statements: Arc<[Statement]>,
+        index: usize,
+        environment: Arc<RwLock<Environment>>,
+    },
+    While {
+        while_loop: Arc<WhileLoop>,
+        environment: Arc<RwLock<Environment>>,
+    },
+    For {
+        for_loop: Arc<ForLoop>,
+        iterator: WrappedObject,
+        environment: Arc<RwLock<Environment>>,
+    },
+}
+
+/// The execution state of a function body that can be suspended at `yield` and resumed later
+///
+/// The body of an async function is a coroutine as well, it suspends at statements awaiting a
+/// task and is resumed by the event loop once that task has finished.
+#[derive(Debug)]
+pub struct Coroutine {
+    frames: Vec<Frame>,
+    asynchronous: bool,
+    /// a statement parked at its `await` along with its scope and the awaited value
+    pending: Option<(Statement, Arc<RwLock<Environment>>, WrappedObject)>,
+    returned: Option<WrappedObject>,
+}
+
+impl Coroutine {
+    pub fn new(statements: Arc<[Statement]>, environment: Arc<RwLock<Environment>>) -> Self {
+        Self {
+            frames: vec![Frame::Block {
+                statements,
+                index: 0,
+                environment,
+            }],
+            asynchronous: false,
+            pending: None,
+            returned: None,
+        }
+    }
+
+    pub fn asynchronous(statements: Arc<[Statement]>, environment: Arc<RwLock<Environment>>) -> Self {
+        Self {
+            asynchronous: true,
+            ..Self::new(statements, environment)
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frames.is_empty() && self.pending.is_none()
+    }
+
+    /// reports the environments and values held by the suspended body to the collector
+    pub fn trace(&self, tracer: &mut Tracer) {
+        for frame in &self.frames {
+            match frame {
+                Frame::Block { environment, .. } | Frame::While { environment, .. } => {
+                    tracer.visit(environment)
+                }
+                Frame::For {
+                    iterator,
+                    environment,
+                    ..
+                } => {
+                    iterator.trace(tracer);
+                    tracer.visit(environment);
+                }
+            }
+        }
+
+        if let Some((_, environment, awaited)) = &self.pending {
+            tracer.visit(environment);
+            awaited.trace(tracer);
+        }
+
+        if let Some(returned) = &self.returned {
+            returned.trace(tracer);
+        }
+    }
+
+    /// drops everything held by an unreachable coroutine
+    pub fn release(&mut self) {
+        self.frames.clear();
+        self.pending = None;
+        self.returned = None;
+    }
+
+    /// the value the coroutine is suspended on, if it is waiting at an `await`
+    pub fn awaiting(&self) -> Option<&WrappedObject> {
+        self.pending.as_ref().map(|(_, _, awaited)| awaited)
+    }
+
+    /// the value of the `return` that finished the body, none if it ran off the end
+    pub fn take_returned(&mut self) -> WrappedObject {
+        self.returned.take().unwrap_or_else(|| Object::None.wrap())
+    }
+
+    /// runs until the next `yield`, returning its value, or `None` once the body has finished
+    pub fn resume(
+        &mut self,
+        interpreter: &mut AstInterpreter,
+    ) -> Result<Option<WrappedObject>, errors::Error> {
+        let result = self.run(interpreter);
+        let suspended = match &result {
+            Ok(value) => value.is_some() || self.pending.is_some(),
+            Err(_) => false,
+        };
+
+        if !suspended {
+            self.frames.clear();
+            self.pending = None;
+        }
+
+        result
+    }
+
+    fn run(
+        &mut self,
+        interpreter: &mut AstInterpreter,
+    ) -> Result<Option<WrappedObject>, errors::Error> {
+        if let Some((statement, environment, awaited)) = self.pending.take() {
+            let value = interpreter.await_value(&awaited)?;
+            let result = interpreter.execute_awaited(&statement, environment, value);
+            self.complete(interpreter, result)?;
+        }
+
+        while let Some(frame) = self.frames.last_mut() {
+            match frame {
+                Frame::Block {
+                    statements,
+                    index,
+                    environment,
+                } => {
+                    let Some(statement) = statements.get(*index).cloned() else {
+                        self.frames.pop();
+                        continue;
+                    };
+                    *index += 1;
+
+                    let environment = Arc::clone(environment);
+                    if let Some(value) = self.step(interpreter, &statement, environment)? {
+                        return Ok(Some(value));
+                    }
+
+                    if self.pending.is_some() {
+                        return Ok(None);
+                    }
+                }
+
+                Frame::While {
+                    while_loop,
+                    environment,
+                } => {
+                    let environment = Arc::clone(environment);
+                    let condition =
+                        interpreter.evaluate_in(&while_loop.condition, Arc::clone(&environment))?;
+
+                    if !condition.read().unwrap().is_truthy() {
+                        self.frames.pop();
+                        continue;
+                    }
+
+                    let body = while_loop.body.clone();
+                    self.enter(&body, environment);
+                }
+
+                Frame::For {
+                    for_loop,
+                    iterator,
+                    environment,
+                } => {
+                    let (for_loop, iterator) = (for_loop.clone(), iterator.clone());
+                    let environment = Arc::clone(environment);
+
+                    let Some(item) = interpreter.next_item(&iterator)? else {
+                        self.frames.pop();
+                        continue;
+                    };
+
+                    let environment = interpreter.loop_environment(&for_loop, item, environment)?;
+                    self.enter(&for_loop.body, environment);
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// executes a single statement, unpacking it into frames when it contains a `yield`
+    fn step(
+        &mut self,
+        interpreter: &mut AstInterpreter,
+        statement: &Statement,
+        environment: Arc<RwLock<Environment>>,
+    ) -> Result<Option<WrappedObject>, errors::Error> {
+        if self.asynchronous {
+            if let Some(awaited) = statement.awaited_expression() {
+                let awaited = interpreter.evaluate_in(awaited, Arc::clone(&environment))?;
+                mark_awaited(&awaited);
+                self.pending = Some((statement.clone(), environment, awaited));
+                return Ok(None);
+            }
+        }
+
+        let suspends = match self.asynchronous {
+            true => statement.contains_await(),
+            false => statement.contains_yield(),
+        };
+        if !suspends {
+            let result = interpreter.execute_in(statement, environment);
+            return self.complete(interpreter, result);
+        }
+
+        match statement {
+            Statement::Yield(value) => {
+                let value = match value {
+                    Some((expression, _, _)) => interpreter.evaluate_in(expression, environment)?,
+                    None => Object::None.wrap(),
+                };
+
+                Ok(Some(value))
+            }
+
+            Statement::Block(_) => {
+                self.enter(statement, environment);
+                Ok(None)
+            }
+
+            Statement::If(if_statement) => {
+                let condition =
+                    interpreter.evaluate_in(&if_statement.condition, Arc::clone(&environment))?;
+
+                if condition.read().unwrap().is_truthy() {
+                    self.enter(&if_statement.then_branch, environment);
+                } else if let Some(else_branch) = &if_statement.else_branch {
+                    self.enter(else_branch, environment);
+                }
+                Ok(None)
+            }
+
+            Statement::WhileLoop(while_loop) => {
+                self.frames.push(Frame::While {
+                    while_loop: while_loop.clone(),
+                    environment,
+                });
+                Ok(None)
+            }
+
+            Statement::ForLoop(for_loop) => {
+                let iterable =
+                    interpreter.evaluate_in(&for_loop.iterable, Arc::clone(&environment))?;
+                let iterator = interpreter.get_iterator(iterable)?;
+
+                self.frames.push(Frame::For {
+                    for_loop: for_loop.clone(),
+                    iterator,
+                    environment,
+                });
+                Ok(None)
+            }
+
+            Statement::Match(match_statement) => {
+                let previous = interpreter.replace_environment(environment);
+                let selected = interpreter.select_arm(match_statement);
+                interpreter.replace_environment(previous);
+
+                let (arm, environment) = selected?;
+                self.frames.push(Frame::Block {
+                    statements: arm.body.statements.clone(),
+                    index: 0,
+                    environment,
+                });
+                Ok(None)
+            }
+
+            _ => Err(errors::Error::Runtime(
+                "Cannot yield from this statement".to_string(),
+            )),
+        }
+    }
+
+    /// finishes the coroutine when a statement returned and leaves the frames a loop jump skips
+    fn complete(
+        &mut self,
+        interpreter: &mut AstInterpreter,
+        result: Result<ControlFlow, errors::Error>,
+    ) -> Result<Option<WrappedObject>, errors::Error> {
+        match result? {
+            ControlFlow::Normal => {}
+            ControlFlow::Return(value) => {
+                self.frames.clear();
+                self.returned = Some(value);
+            }
+            ControlFlow::TailCall(call) => {
+                self.frames.clear();
+                let value = interpreter.execute_call(call.callee, call.arguments, call.keyword_arguments)?;
+                self.returned = Some(value);
+            }
+            ControlFlow::Break => {
+                self.unwind_to_loop();
+                self.frames.pop();
+            }
+            ControlFlow::Continue => self.unwind_to_loop(),
+        }
+
+        Ok(None)
+    }
+
+    /// pops frames until the innermost loop is on top
+    fn unwind_to_loop(&mut self) {
+        while let Some(frame) = self.frames.last() {
+            if matches!(frame, Frame::While { .. } | Frame::For { .. }) {
+                return;
+            }
+            self.frames.pop();
+        }
+    }
+
+    /// pushes a frame running the statements of a block in a new scope
+    fn enter(&mut self, statement: &Statement, environment: Arc<RwLock<Environment>>) {
+        if let Statement::Block(block) = statement {
+            let environment = Environment::with_parent(environment);
+            self.frames.push(Frame::Block {
+                statements: block.statements.clone(),
+                index: 0,
+                environment: environment.wrap(),
+            });
+        }
+    }
+}
@@ -1,18 +1,23 @@
 use std::borrow::BorrowMut;
-use std::cell::RefCell;
 use std::fmt::Display;
-use std::mem;
-use std::rc::Rc;
 use std::sync::RwLock;
 use std::{collections::HashMap as Map, sync::Arc};
 
-use crate::language::{errors, scanner::object::Object};
+use super::heap::{self, Trace, Tracer};
+use crate::language::{
+    errors,
+    scanner::{
+        object::{Object, WrappedObject},
+        symbol::Symbol,
+    },
+};
 
 #[derive(Debug)]
 pub struct Environment {
     parent: Option<Arc<RwLock<Environment>>>,
-    values: Map<String, Arc<RwLock<Object>>>,
-    constants: Rc<RefCell<Map<String, Object>>>
+    values: Map<Symbol, WrappedObject>,
+    /// constants declared in this scope, inner scopes may shadow them
+    constants: Map<Symbol, WrappedObject>,
 }
 
 impl Default for Environment {
@@ -26,48 +31,74 @@ impl Environment {
         Self {
             parent: None,
             values: Map::new(),
-            constants: Rc::new(RefCell::new(Map::new()))
+            constants: Map::new(),
         }
     }
 
     pub fn with_parent(parent: Arc<RwLock<Environment>>) -> Self {
-        let binding = parent.read().unwrap();
-        let constants = Rc::clone(&(*binding).constants);
-        mem::drop(binding);
-
         Self {
             parent: Some(parent),
             values: Map::new(),
-            constants
+            constants: Map::new(),
         }
     }
 
-    pub fn declare_value(&mut self, name: &str, value: Arc<RwLock<Object>>) {
-        self.values.insert(name.to_string(), value);
+    /// moves the environment onto the heap
+    pub fn wrap(self) -> Arc<RwLock<Environment>> {
+        let environment = Arc::new(RwLock::new(self));
+        heap::track(&environment);
+        environment
+    }
+
+    pub fn declare_value(
+        &mut self,
+        name: impl Into<Symbol>,
+        value: WrappedObject,
+    ) -> Result<(), errors::Error> {
+        let name = name.into();
+        if self.constants.contains_key(&name) {
+            return Err(errors::Error::Runtime(format!(
+                "Cannot redeclare constant {} in the same scope",
+                name
+            )));
+        }
+
+        self.values.insert(name, value);
+        Ok(())
     }
 
-    pub fn declare_constant(&mut self, name: &str, value: Object) -> Result<(), errors::Error>{
-        if self.constants.borrow().contains_key(name) {
-            return Err(errors::Error::Runtime(format!("Cannot declare an existing constant {}", name)));
+    pub fn declare_constant(
+        &mut self,
+        name: impl Into<Symbol>,
+        value: WrappedObject,
+    ) -> Result<(), errors::Error> {
+        let name = name.into();
+        if self.constants.contains_key(&name) || self.values.contains_key(&name) {
+            return Err(errors::Error::Runtime(format!(
+                "Cannot declare an existing name {} as a constant in the same scope",
+                name
+            )));
         }
 
-        let constants = self.constants.as_ref();
-        constants.borrow_mut().insert(name.to_string(), value);
+        self.constants.insert(name, value);
         Ok(())
     }
 
     pub fn set_value(
         &mut self,
-        name: &str,
-        value: Arc<RwLock<Object>>,
+        name: impl Into<Symbol>,
+        value: WrappedObject,
     ) -> Result<(), errors::Error> {
-        
-        if self.constants.borrow().contains_key(name) {
-            return Err(errors::Error::Runtime(format!("Cannot assign to constant value: {}", name)));
+        let name = name.into();
+        if self.constants.contains_key(&name) {
+            return Err(errors::Error::Runtime(format!(
+                "Cannot assign to constant value: {}",
+                name
+            )));
         }
 
-        if self.values.contains_key(name) {
-            self.declare_value(name, value);
+        if let Some(existing) = self.values.get_mut(&name) {
+            *existing = value;
             return Ok(());
         }
 
@@ -88,13 +119,14 @@ impl Environment {
         ))
     }
 
-    pub fn get_value(&self, name: &str) -> Arc<RwLock<Object>> {
-        if let Some(value) = self.constants.borrow().get(name) {
-            return value.clone().wrap()
+    pub fn get_value(&self, name: impl Into<Symbol>) -> WrappedObject {
+        let name = name.into();
+        if let Some(value) = self.constants.get(&name) {
+            return value.clone();
         }
 
-        if let Some(value) = self.values.get(name) {
-            return Arc::clone(value);
+        if let Some(value) = self.values.get(&name) {
+            return value.clone();
         }
 
         if let Some(parent) = &self.parent {
@@ -106,13 +138,20 @@ impl Environment {
             }
         }
 
-        Arc::new(RwLock::new(Object::None))
+        Object::None.wrap()
+    }
+
+    /// a value declared in this scope itself, not in any of its parents
+    pub fn get_local(&self, name: impl Into<Symbol>) -> Option<WrappedObject> {
+        let name = name.into();
+        self.constants.get(&name).or_else(|| self.values.get(&name)).cloned()
     }
 
     #[allow(dead_code)]
-    pub fn delete_value(&mut self, name: &str) {
-        if self.values.contains_key(name) {
-            self.values.borrow_mut().remove(name);
+    pub fn delete_value(&mut self, name: impl Into<Symbol>) {
+        let name = name.into();
+        if self.values.contains_key(&name) {
+            self.values.borrow_mut().remove(&name);
             return;
         }
 
@@ -127,6 +166,30 @@ impl Environment {
     }
 }
 
+impl Trace for RwLock<Environment> {
+    fn trace(&self, tracer: &mut Tracer) {
+        let Ok(environment) = self.try_read() else {
+            return;
+        };
+
+        if let Some(parent) = &environment.parent {
+            tracer.visit(parent);
+        }
+
+        for value in environment.constants.values().chain(environment.values.values()) {
+            value.trace(tracer);
+        }
+    }
+
+    fn release(&self) {
+        if let Ok(mut environment) = self.try_write() {
+            environment.parent = None;
+            environment.values.clear();
+            environment.constants.clear();
+        }
+    }
+}
+
 impl Display for Environment {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut parent_env = String::new();
@@ -138,7 +201,7 @@ impl Display for Environment {
 
         let mut values = String::new();
 
-        for (name, value) in &self.values {
+        for (name, value) in self.constants.iter().chain(&self.values) {
             if let Ok(value) = value.read() {
                 values.push_str(&format!(" [id: {} => value : {}]", name, (*value)));
                 values.push('\n');
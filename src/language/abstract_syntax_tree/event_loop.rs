@@ -0,0 +1,109 @@
+use std::time::{Duration, Instant};
+
+use crate::language::{
+    scanner::object::{task_outcome, WrappedObject},
+    Object,
+};
+
+/// work to do once a timer is due
+#[derive(Debug)]
+pub enum TimerAction {
+    /// finishes a task created by `sleep`
+    Wake(WrappedObject),
+    /// calls a function for `set_timeout`, finishing the task with its result
+    Call {
+        callee: WrappedObject,
+        task: WrappedObject,
+    },
+}
+
+#[derive(Debug)]
+struct Timer {
+    deadline: Instant,
+    action: TimerAction,
+}
+
+/// The tasks and timers scheduled by a program.
+///
+/// Everything runs on the interpreter's own thread, tasks take turns at their `await`s and the
+/// interpreter only sleeps when every task is waiting on a timer.
+#[derive(Debug, Default)]
+pub struct EventLoop {
+    tasks: Vec<WrappedObject>,
+    timers: Vec<Timer>,
+    /// tasks that failed, reported once the loop runs out of work unless something awaited them
+    failed: Vec<WrappedObject>,
+}
+
+impl EventLoop {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn spawn(&mut self, task: WrappedObject) {
+        self.tasks.push(task);
+    }
+
+    pub fn schedule(&mut self, delay: Duration, action: TimerAction) {
+        self.timers.push(Timer {
+            deadline: Instant::now() + delay,
+            action,
+        });
+    }
+
+    pub fn tasks(&self) -> Vec<WrappedObject> {
+        self.tasks.clone()
+    }
+
+    /// forgets tasks that have finished, keeping the failed ones to report
+    pub fn remove_finished(&mut self) {
+        let mut failed = Vec::new();
+        self.tasks.retain(|task| match task_outcome(task) {
+            None => true,
+            Some(Ok(_)) => false,
+            Some(Err(_)) => {
+                failed.push(task.clone());
+                false
+            }
+        });
+
+        self.failed.extend(failed);
+    }
+
+    pub fn record_failure(&mut self, task: WrappedObject) {
+        self.failed.push(task);
+    }
+
+    /// the errors of failed tasks nothing has awaited, forgetting every failed task
+    pub fn take_unawaited_failures(&mut self) -> Vec<String> {
+        self.failed
+            .drain(..)
+            .filter_map(|task| match &*task.read().unwrap() {
+                Object::Task(task) => task
+                    .unawaited_failure()
+                    .map(|message| format!("{} failed without being awaited: {}", task, message)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// when the earliest timer is due
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.timers.iter().map(|timer| timer.deadline).min()
+    }
+
+    /// takes the earliest timer if it is due
+    pub fn next_timer(&mut self) -> Option<TimerAction> {
+        let (index, timer) = self
+            .timers
+            .iter()
+            .enumerate()
+            .min_by_key(|(index, timer)| (timer.deadline, *index))?;
+
+        if timer.deadline > Instant::now() {
+            return None;
+        }
+
+        Some(self.timers.remove(index).action)
+    }
+}
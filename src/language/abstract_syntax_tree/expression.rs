@@ -1,34 +1,43 @@
+pub mod await_expression;
 pub mod binary;
 pub mod call;
 pub mod grouping;
 pub mod literal;
+pub mod tuple;
 pub mod unary;
 pub mod variable;
 
+use std::sync::Arc;
+
+use await_expression::Await;
 use binary::Binary;
 use grouping::Grouping;
 use literal::Literal;
+use tuple::Tuple;
 use unary::Unary;
 
 use self::{call::Call, variable::Variable};
 
 use super::{
-    statement::assignment::{Assign, Get, Set},
+    statement::assignment::{Assign, DestructuringAssign, Get, Set},
     visitor::ExpressionVisitor,
 };
 
 #[derive(Debug, Clone)]
 pub enum Expression {
     /// left operator right
-    Binary(Box<Binary>),
-    Unary(Box<Unary>),
-    Literal(Literal),
-    Grouping(Box<Grouping>),
-    Call(Box<Call>),
-    Variable(Box<Variable>),
-    Assign(Box<Assign>),
-    Get(Box<Get>),
-    Set(Box<Set>),
+    Binary(Arc<Binary>),
+    Unary(Arc<Unary>),
+    Literal(Arc<Literal>),
+    Grouping(Arc<Grouping>),
+    Call(Arc<Call>),
+    Variable(Arc<Variable>),
+    Assign(Arc<Assign>),
+    Get(Arc<Get>),
+    Set(Arc<Set>),
+    Tuple(Arc<Tuple>),
+    DestructuringAssign(Arc<DestructuringAssign>),
+    Await(Arc<Await>),
 }
 
 impl Expression {
@@ -41,8 +50,11 @@ impl Expression {
             Self::Call(math_function) => math_function.accept(visitor),
             Self::Variable(variable_expression) => variable_expression.accept(visitor),
             Self::Assign(assign) => assign.accept(visitor),
-            Self::Get(get) => get.accept(visitor),
+            Self::Get(get) => visitor.visit_get(get),
             Self::Set(set) => set.accept(visitor),
+            Self::Tuple(tuple) => tuple.accept(visitor),
+            Self::DestructuringAssign(assign) => visitor.visit_destructuring_assign(assign),
+            Self::Await(await_expression) => await_expression.accept(visitor),
         }
     }
 }
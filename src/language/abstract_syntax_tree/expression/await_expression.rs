@@ -0,0 +1,20 @@
+use crate::language::{abstract_syntax_tree::visitor::ExpressionVisitor, scanner::token::Token};
+
+use super::Expression;
+
+/// `await value`, waits for a task to finish and evaluates to its result
+#[derive(Debug, Clone)]
+pub struct Await {
+    pub keyword: Token,
+    pub value: Expression,
+}
+
+impl Await {
+    pub fn accept<T>(&self, visitor: &mut impl ExpressionVisitor<Output = T>) -> T {
+        visitor.visit_await(self)
+    }
+
+    pub fn new(value: Expression, keyword: Token) -> Self {
+        Self { keyword, value }
+    }
+}
@@ -1,19 +1,29 @@
 use super::Expression;
 use crate::language::{abstract_syntax_tree::visitor::ExpressionVisitor, scanner::token::Token};
 
+/// an argument passed by parameter name, `port: 8080`
+pub type KeywordArgument = (Token, Expression);
+
 #[derive(Debug, Clone)]
 pub struct Call {
     pub callee: Expression,
     pub paren: Token,
     pub arguments: Vec<Expression>,
+    pub keyword_arguments: Vec<KeywordArgument>,
 }
 
 impl Call {
-    pub fn new(callee: Expression, paren: Token, arguments: Vec<Expression>) -> Self {
+    pub fn new(
+        callee: Expression,
+        paren: Token,
+        arguments: Vec<Expression>,
+        keyword_arguments: Vec<KeywordArgument>,
+    ) -> Self {
         Self {
             callee,
             paren,
             arguments,
+            keyword_arguments,
         }
     }
     pub fn accept<T>(&self, visitor: &mut impl ExpressionVisitor<Output = T>) -> T {
@@ -0,0 +1,17 @@
+use super::Expression;
+use crate::language::abstract_syntax_tree::visitor::ExpressionVisitor;
+
+#[derive(Debug, Clone)]
+pub struct Tuple {
+    pub elements: Vec<Expression>,
+}
+
+impl Tuple {
+    pub fn accept<T>(&self, visitor: &mut impl ExpressionVisitor<Output = T>) -> T {
+        visitor.visit_tuple(self)
+    }
+
+    pub fn new(elements: Vec<Expression>) -> Self {
+        Self { elements }
+    }
+}
@@ -0,0 +1,387 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex, Weak,
+    },
+    time::Duration,
+};
+
+/// collections run once this many values were tracked since the last one
+const MIN_THRESHOLD: usize = 10_000;
+/// how long a collection waits for the other threads to stop before it is skipped
+const STOP_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// A value the collector can walk through.
+pub trait Trace: Send + Sync {
+    /// reports every shared reference held by the value, a value locked by running code reports
+    /// nothing and so keeps everything it references alive
+    fn trace(&self, tracer: &mut Tracer);
+
+    /// drops the references held by an unreachable value, breaking the cycles it is part of
+    fn release(&self);
+}
+
+/// numbers describing the heap, handed to the host after a collection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HeapStats {
+    /// collections run so far
+    pub collections: usize,
+    /// collections given up because the other threads did not stop within `STOP_TIMEOUT`
+    pub skipped: usize,
+    /// tracked objects and environments alive after the last collection
+    pub live: usize,
+    /// unreachable values released by the last collection
+    pub freed: usize,
+    /// unreachable values released by every collection
+    pub total_freed: usize,
+}
+
+/// The objects and environments allocated by the current thread.
+///
+/// Values are reference counted, the heap keeps weak handles to everything that can hold
+/// references so cycles, which reference counting alone never frees, can be found and released.
+struct Heap {
+    tracked: Vec<Weak<dyn Trace>>,
+    allocations: usize,
+    threshold: usize,
+}
+
+thread_local! {
+    static HEAP: RefCell<Heap> = const {
+        RefCell::new(Heap {
+            tracked: Vec::new(),
+            allocations: 0,
+            threshold: MIN_THRESHOLD,
+        })
+    };
+}
+
+/// registers a newly allocated value with the heap of the current thread
+pub fn track<T: Trace + 'static>(node: &Arc<T>) {
+    let node: Weak<dyn Trace> = Arc::downgrade(node) as Weak<dyn Trace>;
+
+    HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+        heap.tracked.push(node);
+        heap.allocations += 1;
+    });
+}
+
+/// true once enough values were allocated since the last collection
+pub fn should_collect() -> bool {
+    HEAP.with(|heap| {
+        let heap = heap.borrow();
+        heap.allocations >= heap.threshold
+    })
+}
+
+#[derive(Default)]
+struct Threads {
+    /// threads that may be using shared values right now
+    active: usize,
+    /// set while a thread is collecting or waiting for the others to stop
+    collecting: bool,
+}
+
+/// The threads running one program.
+///
+/// They share values, so a thread collects only while every other one is stopped: running
+/// threads stop at their next statement once a collection is requested, and threads blocked in
+/// `recv()`, `lock()`, `join()` or a timer stay stopped until it is done. A thread hands the
+/// values it tracked over to the group whenever it stops.
+pub struct ThreadGroup {
+    threads: Mutex<Threads>,
+    changed: Condvar,
+    /// read at every statement, so running threads notice a collection without taking the lock
+    requested: AtomicBool,
+    handed_over: Mutex<Vec<Weak<dyn Trace>>>,
+    stats: Mutex<HeapStats>,
+}
+
+impl Default for ThreadGroup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ThreadGroup {
+    /// a group of the thread creating it
+    pub fn new() -> Self {
+        Self {
+            threads: Mutex::new(Threads {
+                active: 1,
+                collecting: false,
+            }),
+            changed: Condvar::new(),
+            requested: AtomicBool::new(false),
+            handed_over: Mutex::new(Vec::new()),
+            stats: Mutex::new(HeapStats::default()),
+        }
+    }
+
+    /// counts a thread about to be spawned as active, called by the spawning thread
+    pub fn spawn(&self) {
+        self.threads.lock().unwrap().active += 1;
+    }
+
+    /// called by a thread once it stops using shared values, for good or until it calls `enter`
+    pub fn leave(&self) {
+        let tracked = HEAP.with(|heap| std::mem::take(&mut heap.borrow_mut().tracked));
+        self.handed_over.lock().unwrap().extend(tracked);
+
+        self.threads.lock().unwrap().active -= 1;
+        self.changed.notify_all();
+    }
+
+    /// called by a thread that left before it uses shared values again, waits for a collection
+    pub fn enter(&self) {
+        let threads = self.threads.lock().unwrap();
+        let mut threads = self
+            .changed
+            .wait_while(threads, |threads| threads.collecting)
+            .unwrap();
+        threads.active += 1;
+    }
+
+    /// runs `wait`, which must not touch shared values, with the thread counted as stopped
+    pub fn blocking<T>(&self, wait: impl FnOnce() -> T) -> T {
+        self.leave();
+        let result = wait();
+        self.enter();
+        result
+    }
+
+    /// stops the thread while another one collects
+    pub fn safepoint(&self) {
+        if self.requested.load(Ordering::Relaxed) {
+            self.blocking(|| ());
+        }
+    }
+
+    pub fn stats(&self) -> HeapStats {
+        *self.stats.lock().unwrap()
+    }
+
+    /// waits for every other thread to stop, false when another thread collects instead or they
+    /// do not stop in time
+    fn stop_others(&self) -> bool {
+        let mut threads = self.threads.lock().unwrap();
+        if threads.collecting {
+            drop(threads);
+            self.blocking(|| ());
+            return false;
+        }
+
+        threads.collecting = true;
+        threads.active -= 1;
+        self.requested.store(true, Ordering::Relaxed);
+
+        let (threads, waited) = self
+            .changed
+            .wait_timeout_while(threads, STOP_TIMEOUT, |threads| threads.active > 0)
+            .unwrap();
+        if waited.timed_out() {
+            drop(threads);
+            self.stats.lock().unwrap().skipped += 1;
+            self.resume_others();
+            return false;
+        }
+
+        true
+    }
+
+    fn resume_others(&self) {
+        let mut threads = self.threads.lock().unwrap();
+        threads.collecting = false;
+        threads.active += 1;
+        self.requested.store(false, Ordering::Relaxed);
+        self.changed.notify_all();
+    }
+}
+
+/// Releases the reference cycles nothing outside them refers to, among the values tracked by
+/// every thread of the group, once the others stopped.
+///
+/// The heap only holds weak handles, so it cannot list the roots: the environments of the
+/// interpreters, their call stacks and values held by native code. It finds them by trial
+/// deletion instead. The references the tracked values hold to each other are subtracted from
+/// their reference counts, a value with references left over is held from outside and is a
+/// root. Values reachable from a root are kept, the rest are only referenced from cycles among
+/// themselves and are released.
+pub fn collect(group: &ThreadGroup) -> HeapStats {
+    if !group.stop_others() {
+        // try again once as many values were allocated again
+        HEAP.with(|heap| heap.borrow_mut().allocations = 0);
+        return group.stats();
+    }
+
+    let mut tracked = HEAP.with(|heap| std::mem::take(&mut heap.borrow_mut().tracked));
+    tracked.append(&mut group.handed_over.lock().unwrap());
+
+    let mut tracer = Tracer::default();
+    for node in &tracked {
+        if let Some(node) = node.upgrade() {
+            // the upgrade itself is one of the references
+            let references = Arc::strong_count(&node) - 1;
+            tracer.add(node, references);
+        }
+    }
+
+    tracer.count_internal_references();
+    tracer.mark_reachable();
+    let freed = tracer.release_unreachable();
+    drop(tracer);
+
+    tracked.retain(|node| node.strong_count() > 0);
+
+    let live = HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+        heap.tracked.append(&mut tracked);
+        heap.allocations = 0;
+        heap.threshold = MIN_THRESHOLD.max(heap.tracked.len() * 2);
+        heap.tracked.len()
+    });
+
+    let mut stats = group.stats.lock().unwrap();
+    stats.collections += 1;
+    stats.live = live;
+    stats.freed = freed;
+    stats.total_freed += freed;
+    let stats = *stats;
+
+    group.resume_others();
+    stats
+}
+
+struct Entry {
+    node: Arc<dyn Trace>,
+    /// references from outside the traced values, the value is a root while this is above zero
+    references: usize,
+    reachable: bool,
+}
+
+/// Walks the references between values during a collection.
+#[derive(Default)]
+pub struct Tracer {
+    entries: Vec<Entry>,
+    indices: HashMap<usize, usize>,
+    pending: Vec<usize>,
+    marking: bool,
+}
+
+impl Tracer {
+    /// reports a reference to a shared value
+    pub fn visit<T: Trace + 'static>(&mut self, node: &Arc<T>) {
+        let key = Arc::as_ptr(node) as *const () as usize;
+
+        if self.marking {
+            if let Some(&index) = self.indices.get(&key) {
+                if !self.entries[index].reachable {
+                    self.entries[index].reachable = true;
+                    self.pending.push(index);
+                }
+            }
+            return;
+        }
+
+        let index = match self.indices.get(&key) {
+            Some(&index) => index,
+            None => {
+                let references = Arc::strong_count(node);
+                self.add(Arc::clone(node) as Arc<dyn Trace>, references)
+            }
+        };
+
+        let entry = &mut self.entries[index];
+        entry.references = entry.references.saturating_sub(1);
+    }
+
+    fn add(&mut self, node: Arc<dyn Trace>, references: usize) -> usize {
+        let key = Arc::as_ptr(&node) as *const () as usize;
+        if let Some(&index) = self.indices.get(&key) {
+            return index;
+        }
+
+        let index = self.entries.len();
+        self.entries.push(Entry {
+            node,
+            references,
+            reachable: false,
+        });
+        self.indices.insert(key, index);
+        self.pending.push(index);
+        index
+    }
+
+    /// subtracts the references values hold to each other, leaving the outside references
+    fn count_internal_references(&mut self) {
+        while let Some(index) = self.pending.pop() {
+            let node = Arc::clone(&self.entries[index].node);
+            node.trace(self);
+        }
+    }
+
+    fn mark_reachable(&mut self) {
+        self.marking = true;
+
+        for (index, entry) in self.entries.iter_mut().enumerate() {
+            if entry.references > 0 {
+                entry.reachable = true;
+                self.pending.push(index);
+            }
+        }
+
+        while let Some(index) = self.pending.pop() {
+            let node = Arc::clone(&self.entries[index].node);
+            node.trace(self);
+        }
+    }
+
+    fn release_unreachable(&self) -> usize {
+        let unreachable = self.entries.iter().filter(|entry| !entry.reachable);
+
+        let mut freed = 0;
+        for entry in unreachable {
+            entry.node.release();
+            freed += 1;
+        }
+
+        freed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{collect, ThreadGroup};
+    use crate::language::{generate_parsed_ast, AstInterpreter};
+
+    #[test]
+    fn test_heap_collects_cycles() {
+        let source = "class Node\nend\nlet index = 0\nwhile index < 50\nlet a = Node()\nlet b = Node()\na.other = b\nb.other = a\nindex = index + 1\nend\nlet kept = Node()\nkept.value = 1\nkept.this = kept\n";
+        let statements = generate_parsed_ast(source, "").unwrap();
+
+        let mut interpreter = AstInterpreter::new();
+        interpreter.interpret(statements).unwrap();
+
+        let stats = interpreter.collect_garbage();
+        assert_eq!(stats.collections, 1);
+        assert!(stats.freed >= 100);
+
+        let source = "let this = kept.this\nlet value = this.value + 1\n";
+        let statements = generate_parsed_ast(source, "").unwrap();
+        assert!(interpreter.interpret(statements).is_ok());
+    }
+
+    #[test]
+    fn test_heap_skips_collection_when_threads_do_not_stop() {
+        // a thread that was spawned but never reaches a safepoint
+        let group = ThreadGroup::new();
+        group.spawn();
+
+        let stats = collect(&group);
+        assert_eq!((stats.collections, stats.skipped), (0, 1));
+        assert_eq!(group.stats().skipped, 1);
+    }
+}
@@ -0,0 +1,52 @@
+use std::sync::{Arc, RwLock, Weak};
+
+use crate::language::{
+    scanner::object::{ClassId, WrappedObject},
+    Object, Value,
+};
+
+/// The method a property access found the last time it ran.
+///
+/// Every `object.name` expression keeps one, so a call site seeing instances of the same class
+/// again skips walking the superclass chain. Entries are keyed on the class id: a class does not
+/// change once created and running its statement again creates a new class with a new id, so an
+/// entry for a redefined class no longer matches and is replaced on the next lookup.
+///
+/// The method is held weakly, syntax trees live as long as the functions containing them and
+/// must not keep classes or the scopes their methods close over alive.
+#[derive(Default)]
+pub struct InlineCache {
+    entry: RwLock<Option<(ClassId, Weak<RwLock<Object>>)>>,
+}
+
+impl InlineCache {
+    /// the cached method for instances of `class`, not yet bound to an instance
+    pub fn get(&self, class: ClassId) -> Option<WrappedObject> {
+        match &*self.entry.read().unwrap() {
+            Some((cached_class, method)) if *cached_class == class => {
+                method.upgrade().map(Value::Heap)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn set(&self, class: ClassId, method: &WrappedObject) {
+        if let Value::Heap(method) = method {
+            *self.entry.write().unwrap() = Some((class, Arc::downgrade(method)));
+        }
+    }
+}
+
+/// the cached method is left out of syntax tree dumps
+impl std::fmt::Debug for InlineCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InlineCache").finish_non_exhaustive()
+    }
+}
+
+/// a copied expression starts out with an empty cache
+impl Clone for InlineCache {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
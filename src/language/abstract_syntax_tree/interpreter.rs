@@ -1,28 +1,124 @@
 use std::{
     collections::HashMap,
+    fmt::Display,
     io::{self, Write},
-    sync::{Arc, RwLock}, fs,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    thread,
+    time::{Duration, Instant},
 };
 
 use super::{
-    environment::Environment, expression::Expression, parser, statement::{Block, Statement}, visitor::{ExpressionVisitor, StatementVisitor}
+    ast_cache,
+    control_flow::ControlFlow,
+    environment::Environment,
+    event_loop::{EventLoop, TimerAction},
+    heap::{self, HeapStats, ThreadGroup},
+    inline_cache::InlineCache,
+    module_loader::{FileSystemLoader, ModuleLoader},
+    optimizer,
+    expression::Expression,
+    parser,
+    statement::{
+        assignment::{DestructuringAssign, Get},
+        declaration::TupleDeclaration,
+        function::FunctionStatement,
+        match_statement::{MatchArm, MatchStatement, Pattern},
+        Block, ForLoop, IfStatement, Import, ImportBinding, Statement, WhileLoop,
+    },
+    visitor::{ExpressionVisitor, StatementVisitor},
 };
 use crate::language::{
     errors,
     scanner::{
         object::{
-            Callable, ClassObject, DefinedCall, InstanceIDCreator, NativeCall, Object,
-            WrappedObject,
+            mark_awaited, task_outcome, Callable, ChannelObject, ClassObject, DefinedCall, EnumObject,
+            InstanceIDCreator, IteratorObject, ModuleObject, MutexObject, NativeCall, Object, TaskObject,
+            TaskState, ThreadHandle, TupleObject, WrappedObject,
         },
-        token::TokenType, self,
+        symbol::Symbol,
+        token::TokenType,
+        value::Value,
+        self,
     },
 };
 
+mod machine;
+
+use machine::Task;
+
+/// bytes the interpreter's stacks may take unless configured with `set_max_stack_memory`, room
+/// for a little over 250000 nested calls
+pub const DEFAULT_MAX_STACK_MEMORY: usize = 32 * 1024 * 1024;
+
+/// native stack given to threads running the interpreter, calls of defined functions do not
+/// use it but parsing deeply nested code and native functions calling back into the
+/// interpreter do
+pub const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+/// helpers written in Nova, run in the global scope before the first program
+const PRELUDE: &str = include_str!("../prelude.nova");
+
+// the interpreter and the objects it shares with spawned threads must stay thread safe
+const _: fn() = || {
+    fn assert_thread_safe<T: Send + Sync>() {}
+    assert_thread_safe::<AstInterpreter>();
+    assert_thread_safe::<WrappedObject>();
+};
+
 /// A simple abstract syntax tree interpreter
 pub struct AstInterpreter {
     environment: Arc<RwLock<Environment>>,
+    /// the scope holding the native functions, parent of the scope of every module
+    globals: Arc<RwLock<Environment>>,
+    /// imported modules by canonical path, a file runs once however often it is imported
+    modules: HashMap<PathBuf, WrappedObject>,
+    /// files being included or imported, innermost last, as resolved by the module loader
+    loading: Vec<PathBuf>,
+    /// finds and reads included and imported files
+    loader: Arc<dyn ModuleLoader>,
     pub id_maker: InstanceIDCreator,
     pub interactive: bool,
+    pub event_loop: EventLoop,
+    /// result handed to the next `await` when a suspended statement is resumed
+    awaited: Option<WrappedObject>,
+    /// the threads sharing values with this one, each interpreter counts as one of them until
+    /// it is dropped
+    threads: Arc<ThreadGroup>,
+    /// the defined functions currently being called, innermost last
+    call_stack: Vec<CallFrame>,
+    /// work left to do, innermost last, see `machine::Task`
+    tasks: Vec<Task>,
+    /// operands and results of the expressions being evaluated
+    values: Vec<WrappedObject>,
+    /// height of the task stack the innermost run started at, jumps do not unwind below it
+    base: usize,
+    /// a jump that unwound to `base`, handed to whoever started the run
+    escaped: Option<ControlFlow>,
+    nested_runs: usize,
+    /// expressions being visited inside each other on the native stack
+    inline_depth: usize,
+    max_stack_memory: usize,
+    /// optimize included files before running them
+    optimize: bool,
+    /// reuse the parsed versions of included files saved by earlier runs, see `ast_cache`
+    ast_cache: bool,
+    /// the prelude is yet to run, it does before the first program
+    prelude: bool,
+}
+
+/// a function call in progress, reported in tracebacks
+#[derive(Debug, Clone, PartialEq)]
+struct CallFrame {
+    function: Symbol,
+    filename: Symbol,
+    line: usize,
+}
+
+impl Display for CallFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}, line {}, in {}", self.filename, self.line, self.function)
+    }
 }
 
 impl Default for AstInterpreter {
@@ -31,17 +127,136 @@ impl Default for AstInterpreter {
     }
 }
 
+impl Drop for AstInterpreter {
+    fn drop(&mut self) {
+        self.threads.leave();
+    }
+}
+
 impl AstInterpreter {
     pub fn new() -> Self {
         let mut global_env = Environment::new();
         AstInterpreter::load_native_functions(&mut global_env);
 
-        let global = Arc::new(RwLock::new(global_env));
+        let global = global_env.wrap();
         Self {
-            environment: Arc::new(RwLock::new(Environment::with_parent(global))),
+            environment: Environment::with_parent(Arc::clone(&global)).wrap(),
+            globals: global,
+            modules: HashMap::new(),
+            loading: Vec::new(),
+            loader: Arc::new(FileSystemLoader::new()),
             id_maker: InstanceIDCreator::new(),
             interactive: false,
+            event_loop: EventLoop::new(),
+            awaited: None,
+            threads: Arc::new(ThreadGroup::new()),
+            call_stack: Vec::new(),
+            tasks: Vec::new(),
+            values: Vec::new(),
+            base: 0,
+            escaped: None,
+            nested_runs: 0,
+            inline_depth: 0,
+            max_stack_memory: DEFAULT_MAX_STACK_MEMORY,
+            optimize: false,
+            ast_cache: true,
+            prelude: true,
+        }
+    }
+
+    /// an interpreter for a spawned thread, sharing instance ids with this one, the thread counts
+    /// as running from now on
+    pub fn for_thread(&self) -> Self {
+        let mut interpreter = Self::new();
+        interpreter.id_maker = self.id_maker.clone();
+        self.threads.spawn();
+        interpreter.threads = Arc::clone(&self.threads);
+        // the prelude and natives, seen by the modules the thread imports as well
+        interpreter.globals = Arc::clone(&self.globals);
+        interpreter.environment = Environment::with_parent(Arc::clone(&self.globals)).wrap();
+        interpreter.modules = self.modules.clone();
+        interpreter.loader = Arc::clone(&self.loader);
+        interpreter.max_stack_memory = self.max_stack_memory;
+        interpreter.optimize = self.optimize;
+        interpreter.ast_cache = self.ast_cache;
+        interpreter.prelude = false;
+        interpreter
+    }
+
+    /// limits the bytes taken by the stacks of calls in progress and the work they have left,
+    /// recursing deeper is a runtime error
+    pub fn set_max_stack_memory(&mut self, bytes: usize) {
+        self.max_stack_memory = bytes;
+    }
+
+    pub fn max_stack_memory(&self) -> usize {
+        self.max_stack_memory
+    }
+
+    /// whether the standard prelude runs before the first program, on by default
+    pub fn set_prelude(&mut self, prelude: bool) {
+        self.prelude = prelude;
+    }
+
+    /// replaces where included and imported files come from, the filesystem by default
+    pub fn set_module_loader(&mut self, loader: impl ModuleLoader + 'static) {
+        self.loader = Arc::new(loader);
+    }
+
+    /// runs included files through the optimizer, see `optimizer::optimize`
+    pub fn set_optimize(&mut self, optimize: bool) {
+        self.optimize = optimize;
+    }
+
+    /// whether included files are parsed once and saved for later runs, on by default
+    pub fn set_ast_cache(&mut self, ast_cache: bool) {
+        self.ast_cache = ast_cache;
+    }
+
+    /// records a call to `function`, failing once the stacks have reached their memory limit
+    fn enter_call(&mut self, function: &FunctionStatement) -> Result<(), errors::Error> {
+        if self.stack_memory() >= self.max_stack_memory {
+            return Err(self.recursion_error());
+        }
+
+        self.call_stack.push(CallFrame {
+            function: function.name.name(),
+            filename: Symbol::intern(&function.filename),
+            line: function.line,
+        });
+        Ok(())
+    }
+
+    fn leave_call(&mut self) {
+        self.call_stack.pop();
+    }
+
+    fn recursion_error(&self) -> errors::Error {
+        errors::Error::Runtime(format!(
+            "maximum recursion depth exceeded ({} calls)\n{}",
+            self.call_stack.len(),
+            self.traceback()
+        ))
+    }
+
+    /// the calls in progress, most recent last, with repeated calls folded into one line
+    pub fn traceback(&self) -> String {
+        let mut lines = vec!["traceback (most recent call last):".to_string()];
+        let mut frames = self.call_stack.iter().peekable();
+
+        while let Some(frame) = frames.next() {
+            let mut repeated = 0;
+            while frames.next_if(|next| next == &frame).is_some() {
+                repeated += 1;
+            }
+
+            lines.push(format!("  {}", frame));
+            if repeated > 0 {
+                lines.push(format!("  [previous line repeated {} more times]", repeated));
+            }
         }
+
+        lines.join("\n")
     }
 
     pub fn interactive() -> Self {
@@ -71,7 +286,9 @@ impl AstInterpreter {
             println,
         )));
 
-        environment.declare_constant("println", println_object).unwrap();
+        environment
+            .declare_constant("println", println_object.wrap())
+            .unwrap();
 
         // print function
         let print = |_interpreter: &mut AstInterpreter,
@@ -93,7 +310,9 @@ impl AstInterpreter {
             print,
         )));
 
-        environment.declare_constant("print", print_object).unwrap();
+        environment
+            .declare_constant("print", print_object.wrap())
+            .unwrap();
 
         // exit function
         let exit = |_interpreter: &mut AstInterpreter,
@@ -116,13 +335,15 @@ impl AstInterpreter {
             exit,
         )));
 
-        environment.declare_constant("exit", exit_object).unwrap();
+        environment
+            .declare_constant("exit", exit_object.wrap())
+            .unwrap();
 
         // time function
         let time = |_interpreter: &mut AstInterpreter,
                     arguments: &Vec<WrappedObject>|
          -> Result<WrappedObject, errors::Error> {
-            let argument = Arc::clone(&arguments[0]);
+            let argument = arguments[0].clone();
 
             let binding = argument.read().unwrap();
             if let Object::String(option) = &*binding {
@@ -177,333 +398,897 @@ impl AstInterpreter {
             time,
         )));
 
-        environment.declare_constant("time", time_object).unwrap();
-    }
+        environment
+            .declare_constant("time", time_object.wrap())
+            .unwrap();
 
-    pub fn interpret(&mut self, statements: Vec<Statement>) -> Result<(), errors::Error> {
-        for statement in statements {
-            self.execute(&statement)?;
-        }
+        // iter function
+        let iter = |interpreter: &mut AstInterpreter,
+                    arguments: &Vec<WrappedObject>|
+         -> Result<WrappedObject, errors::Error> {
+            interpreter.get_iterator(arguments[0].clone())
+        };
 
-        Ok(())
-    }
+        let iter_object = Object::Callable(Callable::NativeCall(NativeCall::new(
+            "iter".to_string(),
+            1,
+            iter,
+        )));
 
-    pub fn generate_error(&mut self, message: String) -> Result<(), errors::Error> {
-        return Err(errors::Error::Runtime(message));
-    }
+        environment
+            .declare_constant("iter", iter_object.wrap())
+            .unwrap();
 
-    /// load file contents into interpreter
-    pub fn load_file(&mut self, name: &str) -> Result<(), errors::Error> {
-        let contents = fs::read_to_string(name);
-        if let Err(err) = contents {
-            return Err(errors::Error::Runtime(format!("Error importing file: {}/n{}", name, err)));
-        }
-        let source = contents.unwrap();
-        let tokens = scanner::Scanner::new().scan_tokens_with_filename(&source, name);
+        environment.declare_constant("done", Object::Done.wrap()).unwrap();
 
-        if let Err(err) = tokens {
-            return Err(errors::Error::Runtime(format!("Error importing file: {}/n{}", name, err)));
-        }
-        let tokens = tokens.unwrap();
-        let parser = parser::AstParser::new(tokens);
-        let statements = parser.parse_ast();
+        // next function, returns done once the iterator is exhausted
+        let next = |interpreter: &mut AstInterpreter,
+                    arguments: &Vec<WrappedObject>|
+         -> Result<WrappedObject, errors::Error> {
+            let item = interpreter.next_item(&arguments[0])?;
+            Ok(item.unwrap_or_else(|| Object::Done.wrap()))
+        };
 
-        if let Err(err) = statements {
-            return Err(errors::Error::Runtime(format!("Error importing file: {}/n{}", name, err)));
-        }
-        let statements = statements.unwrap();
+        let next_object = Object::Callable(Callable::NativeCall(NativeCall::new(
+            "next".to_string(),
+            1,
+            next,
+        )));
 
-        let result = self.interpret(statements);
+        environment
+            .declare_constant("next", next_object.wrap())
+            .unwrap();
 
-        if let Err(err) = result {
-            return Err(errors::Error::Runtime(format!("Error importing file: {}/n{}", name, err)));
-        }
+        // tuple function, collects an iterable into a tuple
+        let tuple = |interpreter: &mut AstInterpreter,
+                     arguments: &Vec<WrappedObject>|
+         -> Result<WrappedObject, errors::Error> {
+            let elements = interpreter.collect_items(arguments[0].clone())?;
+            Ok(Object::Tuple(TupleObject::new(elements)).wrap())
+        };
 
-        Ok(())
-    }
+        let tuple_object = Object::Callable(Callable::NativeCall(NativeCall::new(
+            "tuple".to_string(),
+            1,
+            tuple,
+        )));
 
-    fn execute(&mut self, statement: &Statement) -> Result<(), errors::Error> {
-        statement.accept(self)
-    }
+        environment
+            .declare_constant("tuple", tuple_object.wrap())
+            .unwrap();
 
-    pub fn execute_block(
-        &mut self,
-        block: &Block,
-        new_environment: Arc<RwLock<Environment>>,
-    ) -> Result<(), errors::Error> {
-        // create new environment
-        let mut result = Ok(());
-        let previous_environment = Arc::clone(&self.environment);
+        // sleep function, returns a task finishing after the given milliseconds
+        let sleep = |interpreter: &mut AstInterpreter,
+                     arguments: &Vec<WrappedObject>|
+         -> Result<WrappedObject, errors::Error> {
+            let delay = Self::milliseconds(&arguments[0])?;
+            let task = Object::Task(TaskObject::new("sleep".to_string(), TaskState::Waiting)).wrap();
+
+            interpreter
+                .event_loop
+                .schedule(delay, TimerAction::Wake(task.clone()));
+            Ok(task)
+        };
 
-        self.environment = new_environment;
+        let sleep_object = Object::Callable(Callable::NativeCall(NativeCall::new(
+            "sleep".to_string(),
+            1,
+            sleep,
+        )));
 
-        for statement in &block.statements {
-            result = self.execute(statement);
-            if result.is_err() {
-                break;
+        environment
+            .declare_constant("sleep", sleep_object.wrap())
+            .unwrap();
+
+        // set_timeout function, calls a function after the given milliseconds
+        let set_timeout = |interpreter: &mut AstInterpreter,
+                           arguments: &Vec<WrappedObject>|
+         -> Result<WrappedObject, errors::Error> {
+            let callee = arguments[0].clone();
+            if !matches!(&*callee.read().unwrap(), Object::Callable(_)) {
+                return Err(errors::Error::Runtime(
+                    "set_timeout expects a function".to_string(),
+                ));
             }
-        }
 
-        self.environment = previous_environment;
+            let delay = Self::milliseconds(&arguments[1])?;
+            let task = Object::Task(TaskObject::new("timeout".to_string(), TaskState::Waiting)).wrap();
 
-        result
-    }
+            let action = TimerAction::Call {
+                callee,
+                task: task.clone(),
+            };
+            interpreter.event_loop.schedule(delay, action);
+            Ok(task)
+        };
 
-    fn evaluate(&mut self, expression: &Expression) -> Result<WrappedObject, errors::Error> {
-        expression.accept::<Result<WrappedObject, errors::Error>>(self)
-    }
+        let set_timeout_object = Object::Callable(Callable::NativeCall(NativeCall::new(
+            "set_timeout".to_string(),
+            2,
+            set_timeout,
+        )));
 
-    fn execute_call(
-        &mut self,
-        callee: WrappedObject,
-        arguments: Vec<WrappedObject>,
-    ) -> Result<WrappedObject, errors::Error> {
-        let callee_binding = callee.read().unwrap();
-        if let Object::Callable(callable) = &(*callee_binding) {
-            if callable.arity() != arguments.len() as i8 && callable.arity() != -1 {
-                return Err(errors::Error::intepret_error(&format!("unmatching function arguments. {} arguments required", callable.arity())));
+        environment
+            .declare_constant("set_timeout", set_timeout_object.wrap())
+            .unwrap();
+
+        // gather function, a task finishing with a tuple of results once all tasks have finished
+        let gather = |interpreter: &mut AstInterpreter,
+                      arguments: &Vec<WrappedObject>|
+         -> Result<WrappedObject, errors::Error> {
+            arguments.iter().for_each(mark_awaited);
+            let state = TaskState::Gathering(arguments.clone());
+            let task = Object::Task(TaskObject::new("gather".to_string(), state)).wrap();
+
+            // kept by the loop so a failure is reported when nothing awaits the gathered tasks
+            interpreter.event_loop.spawn(task.clone());
+            Ok(task)
+        };
+
+        let gather_object = Object::Callable(Callable::NativeCall(NativeCall::new(
+            "gather".to_string(),
+            -1,
+            gather,
+        )));
+
+        environment
+            .declare_constant("gather", gather_object.wrap())
+            .unwrap();
+
+        // spawn function, calls a function with the remaining arguments on a new thread
+        let spawn = |interpreter: &mut AstInterpreter,
+                     arguments: &Vec<WrappedObject>|
+         -> Result<WrappedObject, errors::Error> {
+            let Some((callee, arguments)) = arguments.split_first() else {
+                return Err(errors::Error::Runtime("spawn expects a function".to_string()));
+            };
+
+            if !matches!(&*callee.read().unwrap(), Object::Callable(_)) {
+                return Err(errors::Error::Runtime("spawn expects a function".to_string()));
             }
 
-            return callable.call(self, &arguments);
-        }
+            let (callee, arguments) = (callee.clone(), arguments.to_vec());
+            let mut thread_interpreter = interpreter.for_thread();
 
-        Err(errors::Error::intepret_error("undefined function"))
-    }
+            // the thread leaves its group when `thread_interpreter` is dropped
+            let spawned = thread::Builder::new().stack_size(STACK_SIZE).spawn(move || {
+                thread_interpreter
+                    .execute_call(callee, arguments, Vec::new())
+                    .and_then(|result| thread_interpreter.await_value(&result))
+                    .and_then(|result| thread_interpreter.run_event_loop().map(|_| result))
+            });
 
-    pub fn print_environment(&self) {
-        let environment = Arc::clone(&self.environment);
-        let env_reader = environment.read();
+            match spawned {
+                Ok(handle) => Ok(Object::Thread(ThreadHandle::new(handle)).wrap()),
+                Err(err) => Err(errors::Error::Runtime(format!("Cannot spawn thread: {}", err))),
+            }
+        };
 
-        if let Ok(env_reader) = env_reader {
-            println!("=== Environment ===");
-            println!("{}", (*env_reader));
-            println!("===================");
+        let spawn_object = Object::Callable(Callable::NativeCall(NativeCall::new(
+            "spawn".to_string(),
+            -1,
+            spawn,
+        )));
+
+        environment
+            .declare_constant("spawn", spawn_object.wrap())
+            .unwrap();
+
+        // channel function
+        let channel = |_interpreter: &mut AstInterpreter,
+                       _arguments: &Vec<WrappedObject>|
+         -> Result<WrappedObject, errors::Error> {
+            Ok(Object::Channel(ChannelObject::new()).wrap())
+        };
+
+        let channel_object = Object::Callable(Callable::NativeCall(NativeCall::new(
+            "channel".to_string(),
+            0,
+            channel,
+        )));
+
+        environment
+            .declare_constant("channel", channel_object.wrap())
+            .unwrap();
+
+        // mutex function
+        let mutex = |_interpreter: &mut AstInterpreter,
+                     arguments: &Vec<WrappedObject>|
+         -> Result<WrappedObject, errors::Error> {
+            Ok(Object::Mutex(MutexObject::new(arguments[0].clone())).wrap())
+        };
+
+        let mutex_object = Object::Callable(Callable::NativeCall(NativeCall::new(
+            "mutex".to_string(),
+            1,
+            mutex,
+        )));
+
+        environment
+            .declare_constant("mutex", mutex_object.wrap())
+            .unwrap();
+
+        // collect_garbage function
+        let collect_garbage = |interpreter: &mut AstInterpreter,
+                               _arguments: &Vec<WrappedObject>|
+         -> Result<WrappedObject, errors::Error> {
+            let stats = interpreter.collect_garbage();
+            Ok(interpreter.heap_stats_object(stats))
+        };
+
+        let collect_garbage_object = Object::Callable(Callable::NativeCall(NativeCall::new(
+            "collect_garbage".to_string(),
+            0,
+            collect_garbage,
+        )));
+
+        environment
+            .declare_constant("collect_garbage", collect_garbage_object.wrap())
+            .unwrap();
+
+        // heap_stats function
+        let heap_stats = |interpreter: &mut AstInterpreter,
+                          _arguments: &Vec<WrappedObject>|
+         -> Result<WrappedObject, errors::Error> {
+            let stats = interpreter.heap_stats();
+            Ok(interpreter.heap_stats_object(stats))
+        };
+
+        let heap_stats_object = Object::Callable(Callable::NativeCall(NativeCall::new(
+            "heap_stats".to_string(),
+            0,
+            heap_stats,
+        )));
+
+        environment
+            .declare_constant("heap_stats", heap_stats_object.wrap())
+            .unwrap();
+    }
+
+    /// a `HeapStats` instance with one field per number of `stats`, for Nova code
+    fn heap_stats_object(&mut self, stats: HeapStats) -> WrappedObject {
+        let class = ClassObject::new("HeapStats".to_string(), None, HashMap::new());
+        let (instance, _) = class.instantiate(self);
+
+        let fields = [
+            ("collections", stats.collections),
+            ("skipped", stats.skipped),
+            ("live", stats.live),
+            ("freed", stats.freed),
+            ("total_freed", stats.total_freed),
+        ];
+        if let Object::Instance(object) = &mut *instance.write().unwrap() {
+            for (name, value) in fields {
+                object.set(Symbol::intern(name), Object::Number(value as f64).wrap());
+            }
         }
+        instance
     }
-}
 
-impl StatementVisitor for AstInterpreter {
-    type Output = Result<(), errors::Error>;
+    /// reads a delay in milliseconds
+    fn milliseconds(value: &WrappedObject) -> Result<Duration, errors::Error> {
+        match &*value.read().unwrap() {
+            Object::Number(milliseconds) => Ok(Duration::from_secs_f64(milliseconds.max(0.0) / 1000.0)),
+            object => Err(errors::Error::Runtime(format!(
+                "Expected a delay in milliseconds, found: {}",
+                object
+            ))),
+        }
+    }
 
-    fn visit_if(&mut self, if_statement: &super::statement::IfStatement) -> Self::Output {
-        let condition = self.evaluate(&if_statement.condition)?;
+    pub fn interpret(&mut self, statements: Vec<Statement>) -> Result<(), errors::Error> {
+        if std::mem::take(&mut self.prelude) {
+            self.load_prelude()?;
+        }
 
-        let condition = condition.read();
-        if let Ok(condition) = condition {
-            if condition.is_truthy() {
-                self.execute(&if_statement.then_branch)?;
-            } else if let Some(else_branch) = &if_statement.else_branch {
-                self.execute(else_branch)?;
+        for statement in statements {
+            if let ControlFlow::Return(_) | ControlFlow::TailCall(_) = self.execute(&statement)? {
+                return Err(errors::Error::Runtime(
+                    "Cannot return outside a function".to_string(),
+                ));
             }
-        } else {
-            let _unused = condition.unwrap();
         }
 
         Ok(())
     }
 
-    fn visit_while(&mut self, while_loop: &super::statement::WhileLoop) -> Self::Output {
-        while self
-            .evaluate(&while_loop.condition)?
-            .read()
-            .unwrap()
-            .is_truthy()
-        {
-            self.execute(&while_loop.body)?;
+    /// runs the prelude in the global scope, where every module sees it
+    fn load_prelude(&mut self) -> Result<(), errors::Error> {
+        let mut statements = Self::parse_file("prelude", PRELUDE)?;
+        if self.optimize {
+            statements = optimizer::optimize(statements);
         }
 
-        Ok(())
+        let previous_environment = self.replace_environment(Arc::clone(&self.globals));
+        let result = self.interpret(statements);
+        self.environment = previous_environment;
+
+        result
     }
 
-    fn visit_block(&mut self, block: &super::statement::Block) -> Self::Output {
-        let previous_environment = Arc::clone(&self.environment);
-        let new_environment = Arc::new(RwLock::new(Environment::with_parent(previous_environment)));
+    pub fn generate_error(&mut self, message: String) -> Result<(), errors::Error> {
+        Err(errors::Error::Runtime(message))
+    }
 
-        self.execute_block(block, new_environment)
+    /// load file contents into interpreter, the file is found by the module loader as if it was
+    /// included by code not read from a file
+    pub fn load_file(&mut self, name: &str) -> Result<(), errors::Error> {
+        self.include_file(name, "")
     }
 
-    fn visit_function_statement(
-        &mut self,
-        function_statement: &super::statement::function::FunctionStatement,
-    ) -> Self::Output {
-        let function = function_statement.clone();
-        let function = Callable::DefinedCall(DefinedCall::new(
-            Box::new(function),
-            Arc::clone(&self.environment),
-            false,
-        ));
-        let function = Arc::new(RwLock::new(Object::Callable(function)));
+    /// runs a file named by an `include` in the file `from`, in the current scope
+    fn include_file(&mut self, name: &str, from: &str) -> Result<(), errors::Error> {
+        let file = self.loader.resolve(Path::new(name), Path::new(from))?;
+        self.enter_file(&file, "include")?;
 
-        self.environment.write().unwrap().declare_value(
-            function_statement.name.object.to_string().as_str(),
-            function,
-        );
+        let result = self.read_file(&file).and_then(|statements| self.interpret(statements));
+        self.loading.pop();
+
+        if let Err(err) = result {
+            return Err(errors::Error::Runtime(format!("Error importing file: {}\n{}", name, err)));
+        }
 
         Ok(())
     }
 
-    fn visit_return(&mut self, return_statement: &Option<(Expression, usize, String)>) -> Self::Output {
-        let mut object = Object::None.wrap();
-
-        if let Some(expression) = return_statement {
-            let expression = &expression.0;
-            
-            object = self.evaluate(expression)?;
+    /// the module named by an `import` in the file `from`, running the file in a scope of its
+    /// own the first time it is imported
+    fn import_module(&mut self, path: &str, from: &str) -> Result<WrappedObject, errors::Error> {
+        let mut name = PathBuf::from(path);
+        if name.extension().is_none() {
+            name.set_extension("nova");
         }
 
-        Err(errors::Error::Return(object))
-    }
+        let file = self.loader.resolve(&name, Path::new(from))?;
+        if let Some(module) = self.modules.get(&file) {
+            return Ok(module.clone());
+        }
 
-    fn visit_var_declaration(
-        &mut self,
-        var_declaration: &super::statement::declaration::VariableDeclaration,
-    ) -> Self::Output {
-        let mut value = Arc::new(RwLock::new(Object::None));
+        self.enter_file(&file, "import")?;
+        let environment = Environment::with_parent(Arc::clone(&self.globals)).wrap();
+        let previous_environment = self.replace_environment(Arc::clone(&environment));
+        let result = self.read_file(&file).and_then(|statements| self.interpret(statements));
+        self.environment = previous_environment;
+        self.loading.pop();
 
-        if let Some(initializer) = &var_declaration.initializer {
-            let initializer = self.evaluate(initializer)?;
-            if initializer.read().unwrap().prefers_copy() {
-                value = Arc::new(RwLock::new(initializer.read().unwrap().clone()));
-            } else {
-                value = initializer;
-            }
+        if let Err(err) = result {
+            return Err(errors::Error::Runtime(format!("Error importing module: {}\n{}", path, err)));
         }
 
-        let env_writer = self.environment.write();
-        if let Ok(mut env_writer) = env_writer {
-            (*env_writer).declare_value(var_declaration.name.object.to_string().as_str(), value)
-        } else {
-            return Err(errors::Error::Runtime(env_writer.unwrap_err().to_string()));
+        let name = Path::new(path)
+            .file_stem()
+            .map_or_else(|| path.to_string(), |stem| stem.to_string_lossy().into_owned());
+        let module = Object::Module(ModuleObject::new(name, environment)).wrap();
+        self.modules.insert(file, module.clone());
+
+        Ok(module)
+    }
+
+    /// marks a resolved file as being loaded until it is popped off `loading`, failing with the
+    /// chain of files leading back to it if it already is
+    fn enter_file(&mut self, file: &Path, statement: &str) -> Result<(), errors::Error> {
+        if let Some(start) = self.loading.iter().position(|loading| loading == file) {
+            let chain: Vec<String> = self.loading[start..]
+                .iter()
+                .map(|loading| loading.display().to_string())
+                .chain([file.display().to_string()])
+                .collect();
+            return Err(errors::Error::Runtime(format!(
+                "Circular {}: {}",
+                statement,
+                chain.join(" -> ")
+            )));
         }
 
+        self.loading.push(file.to_path_buf());
         Ok(())
     }
 
-    fn visit_expression_statement(&mut self, expression_statement: &(Expression, usize, String)) -> Self::Output {
-        let object = self.evaluate(&expression_statement.0)?;
-        if self.interactive {
-            let binding = object.read();
-
-            if let Ok(value) = binding {
-                let value = &*value;
-                if !value.is_none() {
-                    println!("{}", value);
+    /// the statements of a file, parsed or taken from the cache and optimized as configured
+    fn read_file(&self, file: &Path) -> Result<Vec<Statement>, errors::Error> {
+        let name = file.to_string_lossy();
+        let source = self.loader.load(file)?;
+
+        let ast_cache = self.ast_cache && self.loader.on_disk();
+        let cached = ast_cache.then(|| ast_cache::load(&name, &source)).flatten();
+        let mut statements = match cached {
+            Some(statements) => statements,
+            None => {
+                let statements = Self::parse_file(&name, &source)?;
+                if ast_cache {
+                    ast_cache::store(&name, &source, &statements);
                 }
+                statements
             }
+        };
+        if self.optimize {
+            statements = optimizer::optimize(statements);
         }
-        Ok(())
-    }
 
-    fn visit_none(&mut self) -> Self::Output {
-        Err(errors::Error::Interpret(
-            "Cannot execute a nil statement".to_string(),
-        ))
+        Ok(statements)
     }
 
-    fn visit_class_statement(
-        &mut self,
-        class_statement: &crate::language::class::ClassStatement,
-    ) -> Self::Output {
-        let class_name = class_statement.name.object.to_string();
-        let mut class_superclass = None;
-        self.environment
-            .write()
-            .unwrap()
-            .declare_value(&class_name, Object::None.wrap());
+    fn parse_file(name: &str, source: &str) -> Result<Vec<Statement>, errors::Error> {
+        let tokens = scanner::Scanner::new().scan_tokens_with_filename(source, name);
 
-        let mut class_environment = Arc::clone(&self.environment);
+        if let Err(err) = tokens {
+            return Err(errors::Error::Runtime(format!("Error importing file: {}\n{}", name, err)));
+        }
+        let tokens = tokens.unwrap();
+        let parser = parser::AstParser::new(tokens);
+        let statements = parser.parse_ast();
 
-        if let Some(superclass_expr) = &class_statement.superclass {
-            let superclass = self.evaluate(superclass_expr)?;
-            let binding = superclass.read();
+        if let Err(err) = statements {
+            return Err(errors::Error::Runtime(format!("Error importing file: {}\n{}", name, err)));
+        }
+        Ok(statements.unwrap())
+    }
 
-            if !binding.unwrap().is_class() {
-                return Err(errors::Error::Runtime(format!(
-                    "{}: superclass must be a class",
-                    class_statement.name.object
-                )));
+    /// runs scheduled tasks and timers until there is nothing left to do
+    pub fn run_event_loop(&mut self) -> Result<(), errors::Error> {
+        loop {
+            if self.run_ready_tasks()? {
+                continue;
             }
 
-            let mut new_environment = Environment::with_parent(Arc::clone(&class_environment));
-            new_environment.declare_value("super", Arc::clone(&superclass));
-            class_environment = Arc::new(RwLock::new(new_environment));
-            class_superclass = Some(superclass)
+            match self.wait_for_timer() {
+                Some(action) => self.fire_timer(action)?,
+                None => break,
+            }
         }
 
-        let mut methods = HashMap::new();
-        for method in &class_statement.methods {
-            let initializer = method.name.object.to_string().as_str() == "init";
+        let failures = self.event_loop.take_unawaited_failures();
+        if failures.is_empty() {
+            return Ok(());
+        }
 
-            let function = DefinedCall::new(
-                Box::new(method.clone()),
-                Arc::clone(&class_environment),
-                initializer,
-            );
-            methods.insert(
-                method.name.object.to_string(),
-                Object::Callable(Callable::DefinedCall(function)).wrap(),
-            );
+        Err(errors::Error::Runtime(failures.join("\n")))
+    }
+
+    /// drives the event loop until `awaited` has finished, returning its result
+    pub fn await_value(&mut self, awaited: &WrappedObject) -> Result<WrappedObject, errors::Error> {
+        mark_awaited(awaited);
+        loop {
+            if let Some(outcome) = task_outcome(awaited) {
+                return outcome.map_err(errors::Error::Runtime);
+            }
+
+            if self.run_ready_tasks()? {
+                continue;
+            }
+
+            let Some(action) = self.wait_for_timer() else {
+                return Err(errors::Error::Runtime(format!(
+                    "{} can never finish, nothing it waits on is scheduled",
+                    awaited.read().unwrap()
+                )));
+            };
+            self.fire_timer(action)?;
         }
+    }
 
-        let class = ClassObject::new(class_name.clone(), class_superclass, methods);
-        let class = Object::Callable(Callable::Class(class)).wrap();
+    /// executes a suspended statement, its `await` evaluating to `value`
+    pub fn execute_awaited(
+        &mut self,
+        statement: &Statement,
+        environment: Arc<RwLock<Environment>>,
+        value: WrappedObject,
+    ) -> Result<ControlFlow, errors::Error> {
+        self.awaited = Some(value);
+        let result = self.execute_in(statement, environment);
+        self.awaited = None;
 
-        let env_binding = self.environment.write();
-        env_binding.unwrap().set_value(&class_name, class)?;
+        result
+    }
 
-        Ok(())
+    /// gives every task that can make progress one step and fires due timers,
+    /// returns whether anything ran
+    fn run_ready_tasks(&mut self) -> Result<bool, errors::Error> {
+        let mut progressed = false;
+        for task in self.event_loop.tasks() {
+            progressed |= self.step_task(&task)?;
+        }
+
+        while let Some(action) = self.event_loop.next_timer() {
+            self.fire_timer(action)?;
+            progressed = true;
+        }
+
+        self.event_loop.remove_finished();
+        Ok(progressed)
     }
 
-    fn visit_include(&mut self, include: &crate::language::Include) -> Self::Output {
-        for file_expression in &include.files {
-            let object = self.evaluate(file_expression)?;
-            let binding = object.read().unwrap();
+    /// resumes a task unless it is already running or still waiting on another task
+    fn step_task(&mut self, task: &WrappedObject) -> Result<bool, errors::Error> {
+        let task = match &*task.read().unwrap() {
+            Object::Task(task) => task.clone(),
+            _ => return Ok(false),
+        };
 
-            if let Object::String(name) = &*binding {
-                self.load_file(name)?;
-                continue;
+        let Ok(mut state) = task.state.try_lock() else {
+            return Ok(false);
+        };
+        let TaskState::Running(coroutine) = &mut *state else {
+            return Ok(false);
+        };
+
+        if coroutine
+            .awaiting()
+            .is_some_and(|awaited| task_outcome(awaited).is_none())
+        {
+            return Ok(false);
+        }
+
+        match coroutine.resume(self) {
+            Err(errors::Error::Exit(code)) => return Err(errors::Error::Exit(code)),
+            Err(err) => *state = TaskState::Failed(err.to_string()),
+            Ok(_) if coroutine.is_finished() => {
+                let value = coroutine.take_returned();
+                *state = TaskState::Done(value);
             }
-            return Err(errors::Error::Runtime(format!("invalid argument for include: ({})", &*binding)));
+            Ok(_) => {}
+        }
+
+        Ok(true)
+    }
+
+    /// sleeps until the earliest timer is due and takes it, `None` when there are no timers
+    fn wait_for_timer(&mut self) -> Option<TimerAction> {
+        let deadline = self.event_loop.next_deadline()?;
+        let now = Instant::now();
+        if deadline > now {
+            self.threads.blocking(|| thread::sleep(deadline - now));
         }
 
+        self.event_loop.next_timer()
+    }
+
+    /// runs `wait` with this thread counted as stopped, so other threads can collect garbage
+    /// meanwhile, `wait` must not touch any value
+    pub fn blocking<T>(&self, wait: impl FnOnce() -> T) -> T {
+        self.threads.blocking(wait)
+    }
+
+    fn fire_timer(&mut self, action: TimerAction) -> Result<(), errors::Error> {
+        let (task, result) = match action {
+            TimerAction::Wake(task) => (task, Ok(Object::None.wrap())),
+            TimerAction::Call { callee, task } => {
+                match self.execute_call(callee, Vec::new(), Vec::new()) {
+                    Err(errors::Error::Exit(code)) => return Err(errors::Error::Exit(code)),
+                    result => (task, result.map_err(|err| err.to_string())),
+                }
+            }
+        };
+
+        let failed = result.is_err();
+        if let Object::Task(task) = &*task.read().unwrap() {
+            task.finish(result);
+        }
+
+        if failed {
+            self.event_loop.record_failure(task);
+        }
         Ok(())
     }
-}
 
-impl ExpressionVisitor for AstInterpreter {
-    type Output = Result<WrappedObject, errors::Error>;
+    /// releases unreachable values, the other threads of the program stop meanwhile
+    pub fn collect_garbage(&mut self) -> HeapStats {
+        heap::collect(&self.threads)
+    }
 
-    fn visit_binary(&mut self, binary: &super::expression::binary::Binary) -> Self::Output {
-        let left_binding = self.evaluate(&binary.left)?;
-        let right_binding = self.evaluate(&binary.right)?;
+    pub fn heap_stats(&self) -> HeapStats {
+        self.threads.stats()
+    }
+
+    fn execute(&mut self, statement: &Statement) -> Result<ControlFlow, errors::Error> {
+        self.run(|interpreter| interpreter.schedule_statement(statement))
+    }
+
+    /// schedules a statement, first stopping while another thread collects garbage or
+    /// collecting it when the heap has grown enough
+    fn schedule_statement(&mut self, statement: &Statement) -> Result<(), errors::Error> {
+        self.threads.safepoint();
+        if heap::should_collect() {
+            self.collect_garbage();
+        }
+
+        statement.accept(self)
+    }
+
+    fn evaluate(&mut self, expression: &Expression) -> Result<WrappedObject, errors::Error> {
+        self.run_value(|interpreter| expression.accept(interpreter))
+    }
+
+    /// evaluates an expression with `environment` as the current scope
+    pub fn evaluate_in(
+        &mut self,
+        expression: &Expression,
+        environment: Arc<RwLock<Environment>>,
+    ) -> Result<WrappedObject, errors::Error> {
+        let previous_environment = self.replace_environment(environment);
+        let result = self.evaluate(expression);
+        self.environment = previous_environment;
+
+        result
+    }
+
+    /// executes a statement with `environment` as the current scope
+    pub fn execute_in(
+        &mut self,
+        statement: &Statement,
+        environment: Arc<RwLock<Environment>>,
+    ) -> Result<ControlFlow, errors::Error> {
+        let previous_environment = self.replace_environment(environment);
+        let result = self.execute(statement);
+        self.environment = previous_environment;
+
+        result
+    }
+
+    /// makes `environment` the current scope, returning the previous one
+    pub fn replace_environment(
+        &mut self,
+        environment: Arc<RwLock<Environment>>,
+    ) -> Arc<RwLock<Environment>> {
+        std::mem::replace(&mut self.environment, environment)
+    }
+
+    /// calls `callee`, the bodies of defined functions run on the interpreter's own stack
+    pub fn execute_call(
+        &mut self,
+        callee: WrappedObject,
+        arguments: Vec<WrappedObject>,
+        keyword_arguments: Vec<(Symbol, WrappedObject)>,
+    ) -> Result<WrappedObject, errors::Error> {
+        self.run_value(|interpreter| interpreter.begin_call(callee, arguments, keyword_arguments))
+    }
+
+    pub fn call_function(
+        &mut self,
+        function: &DefinedCall,
+        arguments: &[WrappedObject],
+        keyword_arguments: &[(Symbol, WrappedObject)],
+    ) -> Result<WrappedObject, errors::Error> {
+        self.run_value(|interpreter| {
+            interpreter.begin_function(function, arguments, keyword_arguments, None)
+        })
+    }
+
+    /// creates an instance of `class`, running its initializer
+    pub fn call_class(
+        &mut self,
+        class: &ClassObject,
+        arguments: &[WrappedObject],
+        keyword_arguments: &[(Symbol, WrappedObject)],
+    ) -> Result<WrappedObject, errors::Error> {
+        self.run_value(|interpreter| interpreter.begin_instance(class, arguments, keyword_arguments))
+    }
+
+    /// splits an iterable into exactly `count` values for destructuring
+    fn unpack(&mut self, value: WrappedObject, count: usize) -> Result<Vec<WrappedObject>, errors::Error> {
+        let elements = match &*value.read().unwrap() {
+            Object::Tuple(tuple) => Some(tuple.elements.clone()),
+            _ => None,
+        };
+
+        let elements = match elements {
+            Some(elements) => elements,
+            None => {
+                // stop one past `count` so an endless iterator cannot hang destructuring
+                let iterator = self.get_iterator(value)?;
+                let mut elements = Vec::new();
+                while elements.len() <= count {
+                    match self.next_item(&iterator)? {
+                        Some(item) => elements.push(item),
+                        None => break,
+                    }
+                }
+                elements
+            }
+        };
+
+        if elements.len() != count {
+            return Err(errors::Error::Runtime(format!(
+                "Cannot unpack {} values into {} targets",
+                elements.len(),
+                count
+            )));
+        }
+
+        Ok(elements)
+    }
+
+    /// returns an iterator for `value` following the iteration protocol: strings and tuples
+    /// iterate natively, instances provide `iter()` or are iterators themselves with `next()`
+    pub fn get_iterator(&mut self, value: WrappedObject) -> Result<WrappedObject, errors::Error> {
+        let iterator = match &*value.read().unwrap() {
+            Object::String(string) => {
+                let characters = string
+                    .chars()
+                    .map(|character| Object::String(character.to_string()).wrap())
+                    .collect();
+                Some(IteratorObject::new("string".to_string(), characters))
+            }
+            Object::Tuple(tuple) => {
+                Some(IteratorObject::new("tuple".to_string(), tuple.elements.clone()))
+            }
+            Object::Generator(_) | Object::Iterator(_) => return Ok(value.clone()),
+            Object::Instance(instance) if instance.has_property(Symbol::intern("iter")) => None,
+            Object::Instance(instance) if instance.has_property(Symbol::intern("next")) => {
+                return Ok(value.clone())
+            }
+            object => {
+                return Err(errors::Error::Runtime(format!(
+                    "{} is not iterable",
+                    object
+                )))
+            }
+        };
+
+        if let Some(iterator) = iterator {
+            return Ok(Object::Iterator(iterator).wrap());
+        }
+
+        let iter = self.get_property(&value, Symbol::intern("iter"))?;
+        let iterator = self.execute_call(iter, Vec::new(), Vec::new())?;
+
+        match &*iterator.read().unwrap() {
+            Object::Generator(_) | Object::Iterator(_) => {}
+            Object::Instance(instance) if instance.has_property(Symbol::intern("next")) => {}
+            object => {
+                return Err(errors::Error::Runtime(format!(
+                    "iter() must return an iterator object, not {}",
+                    object
+                )))
+            }
+        }
+
+        Ok(iterator)
+    }
+
+    /// advances an iterator, a `done` from `next()` means it is exhausted
+    pub fn next_item(&mut self, iterator: &WrappedObject) -> Result<Option<WrappedObject>, errors::Error> {
+        let generator = match &*iterator.read().unwrap() {
+            Object::Iterator(iterator) => return Ok(iterator.next_item()),
+            Object::Generator(generator) => Some(generator.clone()),
+            _ => None,
+        };
+
+        // a generator yielding none has not finished, it says when it has
+        if let Some(generator) = generator {
+            return generator.resume(self);
+        }
+
+        let next = self.get_property(iterator, Symbol::intern("next"))?;
+        let item = self.execute_call(next, Vec::new(), Vec::new())?;
+
+        if item.read().unwrap().is_done() {
+            return Ok(None);
+        }
+
+        Ok(Some(item))
+    }
+
+    /// drains an iterable into a list of its items
+    pub fn collect_items(&mut self, iterable: WrappedObject) -> Result<Vec<WrappedObject>, errors::Error> {
+        let iterator = self.get_iterator(iterable)?;
+        let mut items = Vec::new();
+
+        while let Some(item) = self.next_item(&iterator)? {
+            items.push(item);
+        }
+
+        Ok(items)
+    }
+
+    /// creates the scope of a single for loop iteration holding the loop variables
+    pub fn loop_environment(
+        &mut self,
+        for_loop: &ForLoop,
+        item: WrappedObject,
+        parent: Arc<RwLock<Environment>>,
+    ) -> Result<Arc<RwLock<Environment>>, errors::Error> {
+        let values = if for_loop.destructure {
+            self.unpack(item, for_loop.names.len())?
+        } else {
+            vec![item]
+        };
+
+        let mut environment = Environment::with_parent(parent);
+        for (name, value) in for_loop.names.iter().zip(values) {
+            environment.declare_value(name.name(), Self::copy_if_preferred(value))?;
+        }
+
+        Ok(environment.wrap())
+    }
+
+    /// looks up a property of an instance or any other object exposing properties
+    pub fn get_property(&self, object: &WrappedObject, name: Symbol) -> Result<WrappedObject, errors::Error> {
+        match &*object.read().unwrap() {
+            Object::Instance(instance) => instance.get(name, object),
+            Object::Enum(enum_object) => enum_object.get(name.as_str()),
+            Object::Variant(value) => value.get(name.as_str()),
+            Object::Generator(generator) => generator.get(name.as_str(), object),
+            Object::Iterator(iterator) => iterator.get(name.as_str(), object),
+            Object::Thread(thread) => thread.get(name.as_str(), object),
+            Object::Channel(channel) => channel.get(name.as_str(), object),
+            Object::Mutex(mutex) => mutex.get(name.as_str(), object),
+            Object::Module(module) => module.get(name),
+            _ => Err(errors::Error::Runtime(
+                "Only Instances have properties".to_string(),
+            )),
+        }
+    }
+
+    /// `get_property` for a property access expression, methods of instances are found
+    /// through the expression's cache
+    fn get_property_cached(
+        &self,
+        object: &WrappedObject,
+        name: Symbol,
+        cache: &InlineCache,
+    ) -> Result<WrappedObject, errors::Error> {
+        if let Object::Instance(instance) = &*object.read().unwrap() {
+            return instance.get_cached(name, object, cache);
+        }
+
+        self.get_property(object, name)
+    }
+
+    /// applies a binary operator to two numbers, none for operators numbers do not define
+    pub(crate) fn number_operation(operator: &TokenType, left: f64, right: f64) -> Option<WrappedObject> {
+        let result = match operator {
+            TokenType::Plus => Value::Number(left + right),
+            TokenType::Minus => Value::Number(left - right),
+            TokenType::Slash => Value::Number(left / right),
+            TokenType::Star => Value::Number(left * right),
+            TokenType::Caret => Value::Number(left.powf(right)),
+            TokenType::Percent => Value::Number(left % right),
+            TokenType::EqualEqual => Value::Bool(left == right),
+            TokenType::Greater => Value::Bool(left > right),
+            TokenType::GreaterEqual => Value::Bool(left >= right),
+            TokenType::Less => Value::Bool(left < right),
+            TokenType::LessEqual => Value::Bool(left <= right),
+            _ => return None,
+        };
+
+        Some(result)
+    }
+
+    /// applies a binary operator to two values
+    fn binary_operation(
+        operator: TokenType,
+        left_binding: &WrappedObject,
+        right_binding: &WrappedObject,
+    ) -> Result<WrappedObject, errors::Error> {
+        // numbers are stored inline, combine them without reading through objects
+        if let (Some(left), Some(right)) = (left_binding.as_number(), right_binding.as_number()) {
+            if let Some(result) = Self::number_operation(&operator, left, right) {
+                return Ok(result);
+            }
+        }
 
         let left = left_binding.read().unwrap();
         let right = right_binding.read().unwrap();
 
-        match binary.operator.token_type {
+        match operator {
             TokenType::Plus => {
                 // add numbers
                 if let (Object::Number(left), Object::Number(right)) = ((&*left), &(*right)) {
-                    return Ok(Arc::new(RwLock::new(Object::Number(left + right))));
+                    return Ok(Object::Number(left + right).wrap());
                 }
 
                 // concanate strings
                 if let Object::String(left) = &*left {
                     let right = (*right).to_string();
 
-                    return Ok(Arc::new(RwLock::new(Object::String(format!(
-                        "{}{}",
-                        left, right
-                    )))));
+                    return Ok(Object::String(format!("{}{}", left, right)).wrap());
                 }
 
                 if let Object::String(right) = &*right {
                     let left = (*left).to_string();
 
-                    return Ok(Arc::new(RwLock::new(Object::String(format!(
-                        "{}{}",
-                        left, right
-                    )))));
+                    return Ok(Object::String(format!("{}{}", left, right)).wrap());
                 }
 
                 Err(errors::Error::intepret_error(
@@ -571,16 +1356,16 @@ impl ExpressionVisitor for AstInterpreter {
 
             _ => Err(errors::Error::intepret_error(&format!(
                 "Undefined binary operation: {:?}",
-                binary.operator.token_type
+                operator
             ))),
         }
     }
 
-    fn visit_unary(&mut self, unary: &super::expression::unary::Unary) -> Self::Output {
-        let binding = self.evaluate(&unary.right)?;
+    /// applies a unary operator to a value
+    fn unary_operation(operator: TokenType, binding: &WrappedObject) -> Result<WrappedObject, errors::Error> {
         let right = binding.read().unwrap();
 
-        match unary.operator.token_type {
+        match operator {
             TokenType::Minus => {
                 if let Object::Number(right) = right.to_owned() {
                     return Ok(Object::Number(-right).wrap());
@@ -591,94 +1376,1224 @@ impl ExpressionVisitor for AstInterpreter {
 
             _ => Err(errors::Error::intepret_error(&format!(
                 "Undefined Unary Operation : {:?}",
-                unary.operator.token_type
+                operator
             ))),
         }
     }
 
-    fn visit_grouping(&mut self, grouping: &super::expression::grouping::Grouping) -> Self::Output {
-        self.evaluate(&grouping.expression)
+    /// assigns the elements of `value` to the targets of a destructuring assignment
+    fn destructure(&mut self, assign: &DestructuringAssign, value: WrappedObject) -> Result<(), errors::Error> {
+        let values = self.unpack(value, assign.targets.len())?;
+
+        for (target, value) in assign.targets.iter().zip(values) {
+            match target {
+                Expression::Variable(variable) => {
+                    let name = variable.name.name();
+                    self.environment.write().unwrap().set_value(name, value)?;
+                }
+
+                Expression::Get(get) => {
+                    let object = self.evaluate(&get.object)?;
+                    let mut binding = object.write().unwrap();
+
+                    if let Object::Instance(instance) = &mut *binding {
+                        instance.set(get.name.name(), value);
+                        continue;
+                    }
+
+                    return Err(errors::Error::Runtime(
+                        "Only instances have fields".to_string(),
+                    ));
+                }
+
+                _ => {
+                    return Err(errors::Error::Runtime(
+                        "Invalid assignment target".to_string(),
+                    ))
+                }
+            }
+        }
+
+        Ok(())
     }
 
-    fn visit_literal(&mut self, literal: &super::expression::literal::Literal) -> Self::Output {
-        Ok(literal.object.clone().wrap())
+    /// copies values that prefer copying so the new binding does not alias the old one
+    fn copy_if_preferred(value: WrappedObject) -> WrappedObject {
+        if !value.is_heap() {
+            return value;
+        }
+
+        let copy = {
+            let binding = value.read().unwrap();
+            binding.prefers_copy().then(|| binding.clone())
+        };
+
+        match copy {
+            Some(copy) => copy.wrap(),
+            None => value,
+        }
     }
 
-    fn visit_call(&mut self, call: &super::expression::call::Call) -> Self::Output {
-        let callee = self.evaluate(&call.callee)?;
+    /// finds the first arm of a match statement accepting its value, along with an
+    /// environment holding the names bound by the pattern
+    pub fn select_arm<'a>(
+        &mut self,
+        match_statement: &'a MatchStatement,
+    ) -> Result<(&'a MatchArm, Arc<RwLock<Environment>>), errors::Error> {
+        let value = self.evaluate(&match_statement.value)?;
+        self.choose_arm(match_statement, value)
+    }
+
+    /// `select_arm` for an already evaluated value
+    fn choose_arm<'a>(
+        &mut self,
+        match_statement: &'a MatchStatement,
+        value: WrappedObject,
+    ) -> Result<(&'a MatchArm, Arc<RwLock<Environment>>), errors::Error> {
+        for arm in &match_statement.arms {
+            let mut bindings = Vec::new();
+            if !self.match_pattern(&arm.pattern, &value, &mut bindings)? {
+                continue;
+            }
 
-        let mut arguments = Vec::new();
+            let mut environment = Environment::with_parent(Arc::clone(&self.environment));
+            for (name, value) in bindings {
+                environment.declare_value(name, value)?;
+            }
+            let environment = environment.wrap();
+
+            if let Some(guard) = &arm.guard {
+                let guard = self.evaluate_in(guard, Arc::clone(&environment))?;
+                if !guard.read().unwrap().is_truthy() {
+                    continue;
+                }
+            }
 
-        for argument in &call.arguments {
-            arguments.push(self.evaluate(argument)?);
+            return Ok((arm, environment));
         }
 
-        self.execute_call(callee, arguments)
+        Err(errors::Error::Runtime(format!(
+            "[line: {}] No case matched value: {}",
+            match_statement.line,
+            value.read().unwrap()
+        )))
     }
 
-    fn visit_variable(&mut self, variable: &super::expression::variable::Variable) -> Self::Output {
-        let env_reader = self.environment.read();
+    /// checks `value` against `pattern`, collecting the names it binds
+    fn match_pattern(
+        &self,
+        pattern: &Pattern,
+        value: &WrappedObject,
+        bindings: &mut Vec<(Symbol, WrappedObject)>,
+    ) -> Result<bool, errors::Error> {
+        let binding = value.read().unwrap();
+
+        match (pattern, &*binding) {
+            (Pattern::Wildcard, _) => Ok(true),
+            (Pattern::Literal(literal), object) => Ok(literal == object),
+            (Pattern::Range(low, high), object) => Ok(object >= low && object < high),
+            (Pattern::Binding(name), _) => {
+                bindings.push((name.name(), value.clone()));
+                Ok(true)
+            }
+            (Pattern::Tuple(patterns), Object::Tuple(tuple)) => {
+                if patterns.len() != tuple.len() {
+                    return Ok(false);
+                }
+
+                for (pattern, element) in patterns.iter().zip(&tuple.elements) {
+                    if !self.match_pattern(pattern, element, bindings)? {
+                        return Ok(false);
+                    }
+                }
+
+                Ok(true)
+            }
+            (Pattern::Instance { class, fields }, Object::Instance(instance)) => {
+                let class_object = self.environment.read().unwrap().get_value(class.name());
+                let class = match &*class_object.read().unwrap() {
+                    Object::Callable(Callable::Class(class_object)) => class_object.id(),
+                    _ => {
+                        return Err(errors::Error::Runtime(format!(
+                            "{} in pattern is not a class",
+                            class.object
+                        )))
+                    }
+                };
+
+                if !instance.is_instance_of(class) {
+                    return Ok(false);
+                }
+
+                for (name, pattern) in fields {
+                    let Ok(field) = instance.get(name.name(), value) else {
+                        return Ok(false);
+                    };
+
+                    if !self.match_pattern(pattern, &field, bindings)? {
+                        return Ok(false);
+                    }
+                }
+
+                Ok(true)
+            }
+            (
+                Pattern::Variant {
+                    enum_name,
+                    variant,
+                    fields,
+                },
+                Object::Variant(value),
+            ) => {
+                let enum_object = self.environment.read().unwrap().get_value(enum_name.name());
+                let enum_id = match &*enum_object.read().unwrap() {
+                    Object::Enum(enum_object) => enum_object.id(),
+                    _ => {
+                        return Err(errors::Error::Runtime(format!(
+                            "{} in pattern is not an enum",
+                            enum_name.object
+                        )))
+                    }
+                };
+
+                if value.enum_id != enum_id || value.variant != variant.object.to_string() {
+                    return Ok(false);
+                }
+
+                let Some(patterns) = fields else {
+                    return Ok(true);
+                };
+
+                if patterns.len() != value.fields.len() {
+                    return Err(errors::Error::Runtime(format!(
+                        "Pattern for {}.{} expects {} fields but the variant has {}",
+                        value.enum_name,
+                        value.variant,
+                        patterns.len(),
+                        value.fields.len()
+                    )));
+                }
+
+                for (pattern, (_, field)) in patterns.iter().zip(&value.fields) {
+                    if !self.match_pattern(pattern, field, bindings)? {
+                        return Ok(false);
+                    }
+                }
+
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    pub fn print_environment(&self) {
+        let environment = Arc::clone(&self.environment);
+        let env_reader = environment.read();
+
         if let Ok(env_reader) = env_reader {
-            let object = env_reader.get_value(variable.name.object.to_string().as_str());
-            return Ok(object);
+            println!("=== Environment ===");
+            println!("{}", (*env_reader));
+            println!("===================");
         }
+    }
+}
 
-        Err(errors::Error::Runtime("Error retrieving value".to_string()))
+impl StatementVisitor for AstInterpreter {
+    type Output = Result<(), errors::Error>;
+
+    fn visit_if(&mut self, if_statement: &Arc<IfStatement>) -> Self::Output {
+        let height = self.tasks.len();
+        if_statement.condition.accept(self)?;
+        self.then(height, Task::If(Arc::clone(if_statement)))
     }
 
-    fn visit_assign(&mut self, assign: &super::statement::assignment::Assign) -> Self::Output {
-        let value = self.evaluate(&assign.value)?;
+    fn visit_while(&mut self, while_loop: &Arc<WhileLoop>) -> Self::Output {
+        self.then(self.tasks.len(), Task::While(Arc::clone(while_loop)))
+    }
 
-        let env_writer = self.environment.write();
-        if let Ok(mut env_writer) = env_writer {
-            //let value = Arc::new(RwLock::new(value));
-            (*env_writer).set_value(assign.name.object.to_string().as_str(), value)?;
-        } else {
-            let err = env_writer.unwrap_err();
-            return Err(errors::Error::Runtime(err.to_string()));
+    fn visit_for(&mut self, for_loop: &Arc<ForLoop>) -> Self::Output {
+        let height = self.tasks.len();
+        for_loop.iterable.accept(self)?;
+        self.then(height, Task::For(Arc::clone(for_loop)))
+    }
+
+    fn visit_block(&mut self, block: &Block) -> Self::Output {
+        let new_environment = Environment::with_parent(Arc::clone(&self.environment)).wrap();
+        self.enter_block(&block.statements, new_environment);
+
+        Ok(())
+    }
+
+    fn visit_function_statement(
+        &mut self,
+        function_statement: &Arc<FunctionStatement>,
+    ) -> Self::Output {
+        let function = Callable::DefinedCall(DefinedCall::new(
+            Arc::clone(function_statement),
+            Arc::clone(&self.environment),
+            false,
+        ));
+        let function = Object::Callable(function).wrap();
+
+        self.environment.write().unwrap().declare_value(
+            function_statement.name.name(),
+            function,
+        )?;
+
+        Ok(())
+    }
+
+    fn visit_return(&mut self, return_statement: &Option<(Expression, usize, String)>) -> Self::Output {
+        let Some((expression, _, _)) = return_statement else {
+            return self.jump(ControlFlow::Return(Object::None.wrap()));
+        };
+        let height = self.tasks.len();
+
+        // the call is made once this function has left the stack, see `machine::Task::TailCall`
+        match expression {
+            Expression::Call(call) => {
+                self.schedule_call(&call.callee, &call.arguments, &call.keyword_arguments)?;
+                return self.then(
+                    height,
+                    Task::TailCall {
+                        arguments: call.arguments.len(),
+                        keywords: Self::keyword_names(&call.keyword_arguments),
+                    },
+                );
+            }
+            Expression::Get(get) if get.arguments.is_some() => {
+                self.schedule_expression(&get.object)?;
+                return self.then(height, Task::TailProperty(Arc::clone(get)));
+            }
+            _ => {}
+        }
+
+        expression.accept(self)?;
+        self.then(height, Task::Return)
+    }
+
+    fn visit_var_declaration(
+        &mut self,
+        var_declaration: &super::statement::declaration::VariableDeclaration,
+    ) -> Self::Output {
+        let declare = Task::Declare {
+            name: var_declaration.name.name(),
+            constant: var_declaration.constant,
+        };
+        let height = self.tasks.len();
+
+        match &var_declaration.initializer {
+            Some(initializer) => initializer.accept(self)?,
+            None => self.values.push(Object::None.wrap()),
         }
 
-        Ok(Object::None.wrap())
+        self.then(height, declare)
     }
 
-    fn visit_get(&mut self, get: &super::statement::assignment::Get) -> Self::Output {
-        let object = self.evaluate(&get.object)?;
-        let binding = object.read();
-        if let Object::Instance(instance) = &*binding.unwrap() {
-            let object = instance.get(&get.name)?;
-            if let Some(argument_expresssions) = &get.arguments {
-                let mut arguments = Vec::new();
+    fn visit_tuple_declaration(&mut self, tuple_declaration: &Arc<TupleDeclaration>) -> Self::Output {
+        let height = self.tasks.len();
+        tuple_declaration.initializer.accept(self)?;
+        self.then(height, Task::DeclareTuple(Arc::clone(tuple_declaration)))
+    }
 
-                for argument_expression in argument_expresssions {
-                    arguments.push(self.evaluate(argument_expression)?);
-                }
+    fn visit_expression_statement(&mut self, expression_statement: &(Expression, usize, String)) -> Self::Output {
+        let height = self.tasks.len();
+        expression_statement.0.accept(self)?;
+        self.then(height, Task::Discard)
+    }
 
-                return self.execute_call(object, arguments);
+    fn visit_none(&mut self) -> Self::Output {
+        Err(errors::Error::Interpret(
+            "Cannot execute a nil statement".to_string(),
+        ))
+    }
+
+    fn visit_class_statement(
+        &mut self,
+        class_statement: &crate::language::class::ClassStatement,
+    ) -> Self::Output {
+        let class_name = class_statement.name.object.to_string();
+        let mut class_superclass = None;
+        self.environment
+            .write()
+            .unwrap()
+            .declare_value(&class_name, Object::None.wrap())?;
+
+        let mut class_environment = Arc::clone(&self.environment);
+
+        if let Some(superclass_expr) = &class_statement.superclass {
+            let superclass = self.evaluate(superclass_expr)?;
+            let binding = superclass.read();
+
+            if !binding.unwrap().is_class() {
+                return Err(errors::Error::Runtime(format!(
+                    "{}: superclass must be a class",
+                    class_statement.name.object
+                )));
             }
 
-            return Ok(object);
+            let mut new_environment = Environment::with_parent(Arc::clone(&class_environment));
+            new_environment.declare_value("super", superclass.clone())?;
+            class_environment = new_environment.wrap();
+            class_superclass = Some(superclass)
         }
 
+        let mut methods = HashMap::new();
+        for method in &class_statement.methods {
+            let initializer = method.name.name().as_str() == "init";
+
+            let function = DefinedCall::new(
+                Arc::clone(method),
+                Arc::clone(&class_environment),
+                initializer,
+            );
+            methods.insert(
+                method.name.name(),
+                Object::Callable(Callable::DefinedCall(function)).wrap(),
+            );
+        }
+
+        let class = ClassObject::new(class_name.clone(), class_superclass, methods);
+        let class = Object::Callable(Callable::Class(class)).wrap();
+
+        let env_binding = self.environment.write();
+        env_binding.unwrap().set_value(&class_name, class)?;
+
+        Ok(())
+    }
+
+    fn visit_enum_statement(
+        &mut self,
+        enum_statement: &crate::language::enum_statement::EnumStatement,
+    ) -> Self::Output {
+        let name = enum_statement.name.object.to_string();
+        let variants = enum_statement
+            .variants
+            .iter()
+            .map(|variant| {
+                let fields = variant.fields.as_ref().map(|fields| {
+                    fields
+                        .iter()
+                        .map(|field| field.object.to_string())
+                        .collect()
+                });
+                (variant.name.object.to_string(), fields)
+            })
+            .collect();
+
+        let enum_object = Object::Enum(EnumObject::new(name.clone(), variants)).wrap();
+        self.environment
+            .write()
+            .unwrap()
+            .declare_value(&name, enum_object)?;
+
+        Ok(())
+    }
+
+    fn visit_match(&mut self, match_statement: &Arc<MatchStatement>) -> Self::Output {
+        let height = self.tasks.len();
+        match_statement.value.accept(self)?;
+        self.then(height, Task::Match(Arc::clone(match_statement)))
+    }
+
+    fn visit_yield(&mut self, _yield_statement: &Option<(Expression, usize, String)>) -> Self::Output {
         Err(errors::Error::Runtime(
-            "Only Instances have properties".to_string(),
+            "Cannot yield outside a generator".to_string(),
         ))
     }
 
+    fn visit_break(&mut self, _keyword: &crate::language::Token) -> Self::Output {
+        self.jump(ControlFlow::Break)
+    }
+
+    fn visit_continue(&mut self, _keyword: &crate::language::Token) -> Self::Output {
+        self.jump(ControlFlow::Continue)
+    }
+
+    fn visit_include(&mut self, include: &crate::language::Include) -> Self::Output {
+        for file_expression in &include.files {
+            let object = self.evaluate(file_expression)?;
+            let binding = object.read().unwrap();
+
+            if let Object::String(name) = &*binding {
+                self.include_file(name, &include.filename)?;
+                continue;
+            }
+            return Err(errors::Error::Runtime(format!("invalid argument for include: ({})", &*binding)));
+        }
+
+        Ok(())
+    }
+
+    fn visit_import(&mut self, import: &Import) -> Self::Output {
+        let module = self.import_module(&import.path, &import.filename)?;
+
+        let declarations = match &import.binding {
+            ImportBinding::Module(name) => vec![(name.name(), module)],
+            ImportBinding::Names(names) => names
+                .iter()
+                .map(|name| Ok((name.name(), self.get_property(&module, name.name())?)))
+                .collect::<Result<_, errors::Error>>()?,
+        };
+
+        let mut environment = self.environment.write().unwrap();
+        for (name, value) in declarations {
+            environment.declare_value(name, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ExpressionVisitor for AstInterpreter {
+    type Output = Result<(), errors::Error>;
+
+    fn visit_binary(&mut self, binary: &super::expression::binary::Binary) -> Self::Output {
+        // the common `i + 1` needs neither tasks nor the value stack
+        if let (Some(left), Some(right)) = (self.immediate(&binary.left), self.immediate(&binary.right)) {
+            let value = Self::binary_operation(binary.operator.token_type, &left?, &right?)?;
+            self.values.push(value);
+            return Ok(());
+        }
+
+        let height = self.tasks.len();
+        self.schedule_in_order([&binary.left, &binary.right].into_iter())?;
+        self.then(height, Task::Binary(binary.operator.token_type))
+    }
+
+    fn visit_unary(&mut self, unary: &super::expression::unary::Unary) -> Self::Output {
+        let height = self.tasks.len();
+        self.schedule_expression(&unary.right)?;
+        self.then(height, Task::Unary(unary.operator.token_type))
+    }
+
+    fn visit_grouping(&mut self, grouping: &super::expression::grouping::Grouping) -> Self::Output {
+        self.schedule_expression(&grouping.expression)
+    }
+
+    fn visit_literal(&mut self, literal: &super::expression::literal::Literal) -> Self::Output {
+        self.values.push(literal.object.clone().wrap());
+        Ok(())
+    }
+
+    fn visit_call(&mut self, call: &super::expression::call::Call) -> Self::Output {
+        let height = self.tasks.len();
+        self.schedule_call(&call.callee, &call.arguments, &call.keyword_arguments)?;
+        self.then(
+            height,
+            Task::Call {
+                arguments: call.arguments.len(),
+                keywords: Self::keyword_names(&call.keyword_arguments),
+            },
+        )
+    }
+
+    fn visit_variable(&mut self, variable: &super::expression::variable::Variable) -> Self::Output {
+        let env_reader = self.environment.read();
+        if let Ok(env_reader) = env_reader {
+            let object = env_reader.get_value(variable.name.name());
+            drop(env_reader);
+            self.values.push(object);
+            return Ok(());
+        }
+
+        Err(errors::Error::Runtime("Error retrieving value".to_string()))
+    }
+
+    fn visit_assign(&mut self, assign: &super::statement::assignment::Assign) -> Self::Output {
+        let height = self.tasks.len();
+        self.schedule_expression(&assign.value)?;
+        self.then(height, Task::Assign(assign.name.name()))
+    }
+
+    fn visit_get(&mut self, get: &Arc<Get>) -> Self::Output {
+        let height = self.tasks.len();
+        self.schedule_expression(&get.object)?;
+        self.then(height, Task::Property(Arc::clone(get)))
+    }
+
     fn visit_set(&mut self, set: &super::statement::assignment::Set) -> Self::Output {
-        let object = self.evaluate(&set.object)?;
-        let binding = object.write();
-        let name = set.name.clone();
+        // evaluate before locking, the value may read the same object
+        let height = self.tasks.len();
+        self.schedule_in_order([&set.object, &set.value].into_iter())?;
+        self.then(height, Task::Set(set.name.name()))
+    }
+
+    fn visit_await(&mut self, await_expression: &super::expression::await_expression::Await) -> Self::Output {
+        if let Some(value) = self.awaited.take() {
+            self.values.push(value);
+            return Ok(());
+        }
+
+        let height = self.tasks.len();
+        self.schedule_expression(&await_expression.value)?;
+        self.then(height, Task::Await)
+    }
 
-        let writable_binding = &mut *binding.unwrap();
+    fn visit_tuple(&mut self, tuple: &super::expression::tuple::Tuple) -> Self::Output {
+        let height = self.tasks.len();
+        self.schedule_in_order(tuple.elements.iter())?;
+        self.then(height, Task::Tuple(tuple.elements.len()))
+    }
 
-        if let Object::Instance(ref mut instance) = writable_binding {
-            let value = self.evaluate(&set.value)?;
-            instance.set(name, Arc::clone(&value));
-            return Ok(value);
+    fn visit_destructuring_assign(&mut self, assign: &Arc<DestructuringAssign>) -> Self::Output {
+        let height = self.tasks.len();
+        self.schedule_expression(&assign.value)?;
+        self.then(height, Task::Destructure(Arc::clone(assign)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::PathBuf};
+
+    use super::AstInterpreter;
+    use crate::language::{
+        errors, generate_parsed_ast, scanner::symbol::Symbol, FileSystemLoader, MemoryLoader,
+        Object, Resolution,
+    };
+
+    fn run(source: &str) -> Result<AstInterpreter, errors::Error> {
+        let mut interpreter = AstInterpreter::new();
+        interpreter.interpret(generate_parsed_ast(source, "")?)?;
+        Ok(interpreter)
+    }
+
+    /// the message of the error running `source` fails with
+    fn error(source: &str) -> String {
+        match run(source) {
+            Ok(_) => panic!("expected an error running {}", source),
+            Err(err) => err.to_string(),
         }
+    }
 
-        Err(errors::Error::Runtime(
-            "Only instances have fields".to_string(),
+    /// a fresh directory holding `files`, given by their paths inside it and their sources
+    fn directory(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("nova_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+
+        for (path, source) in files {
+            let path = directory.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, source).unwrap();
+        }
+        fs::canonicalize(directory).unwrap()
+    }
+
+    /// the printed form of a variable of the program run by `interpreter`
+    fn value(interpreter: &AstInterpreter, name: &str) -> String {
+        let value = interpreter.environment.read().unwrap().get_value(Symbol::intern(name));
+        let value = value.read().unwrap().to_string();
+        value
+    }
+
+    #[test]
+    fn test_enums() {
+        let shape = "enum Shape\nEmpty\nCircle(radius)\nRect(width, height)\nend\n";
+        let interpreter = run(&format!(
+            "{}let nothing = Shape.Empty\n\
+            let circle = Shape.Circle(2)\n\
+            let rect = Shape.Rect(height: 4, width: 3)\n\
+            let radius = circle.radius\n\
+            let area = rect.width * rect.height\n\
+            let same = circle == Shape.Circle(2)\n\
+            let different = circle == Shape.Circle(3)\n\
+            let empty = nothing == Shape.Empty\n",
+            shape
         ))
+        .unwrap();
+
+        assert_eq!(value(&interpreter, "nothing"), "Shape.Empty");
+        assert_eq!(value(&interpreter, "circle"), "Shape.Circle(radius: 2)");
+        assert_eq!(value(&interpreter, "rect"), "Shape.Rect(width: 3, height: 4)");
+        assert_eq!(value(&interpreter, "radius"), "2");
+        assert_eq!(value(&interpreter, "area"), "12");
+        assert_eq!(value(&interpreter, "same"), "true");
+        assert_eq!(value(&interpreter, "different"), "false");
+        assert_eq!(value(&interpreter, "empty"), "true");
+
+        assert!(error(&format!("{}let c = Shape.Circle(2)\nc.size\n", shape)).contains("Undefined property size on Shape.Circle"));
+        assert!(error(&format!("{}Shape.Circle(1, 2)\n", shape)).contains("Shape.Circle takes 1 fields but 2 were given"));
+        assert!(error(&format!("{}Shape.Square\n", shape)).contains("Shape has no variant Square"));
+    }
+
+    #[test]
+    fn test_match() {
+        let interpreter = run("enum Shape\nCircle(radius)\nRect(width, height)\nend\n\
+            class Point\nend\n\
+            fn describe(value)\n\
+            match value\n\
+            case none\nreturn \"nothing\"\n\
+            case 0\nreturn \"zero\"\n\
+            case 1..10\nreturn \"small\"\n\
+            case \"hello\"\nreturn \"greeting\"\n\
+            case (a, b)\nreturn a + b\n\
+            case Shape.Circle(r)\nreturn r\n\
+            case Shape.Rect(w, h) if w == h\nreturn \"square\"\n\
+            case Shape.Rect\nreturn \"rectangle\"\n\
+            case Point(x, y: 0)\nreturn x\n\
+            case n if n >= 100\nreturn n * 2\n\
+            case _\nreturn \"other\"\n\
+            end\n\
+            end\n\
+            let point = Point()\n\
+            point.x = 5\n\
+            point.y = 0\n\
+            let results = (describe(none), describe(0), describe(4), describe(10), describe(\"hello\"), describe((1, 2)))\n\
+            let shapes = (describe(Shape.Circle(3)), describe(Shape.Rect(2, 2)), describe(Shape.Rect(2, 5)))\n\
+            let others = (describe(point), describe(250), describe(50))\n")
+        .unwrap();
+
+        assert_eq!(value(&interpreter, "results"), "(nothing, zero, small, other, greeting, 3)");
+        assert_eq!(value(&interpreter, "shapes"), "(3, square, rectangle)");
+        assert_eq!(value(&interpreter, "others"), "(5, 500, other)");
+
+        assert!(error("match 3\ncase 1\nprintln(1)\nend\n").contains("No case matched value: 3"));
+    }
+
+    #[test]
+    fn test_match_same_names_from_other_modules() {
+        let mut loader = MemoryLoader::new();
+        loader.insert("other.nova", "enum Shape\nCircle(radius)\nend\nclass Point\nend\nlet circle = Shape.Circle(1)\nlet point = Point()\n");
+
+        let mut interpreter = AstInterpreter::new();
+        interpreter.set_module_loader(loader);
+        let statements = generate_parsed_ast(
+            "import \"other\" as other\n\
+            enum Shape\nCircle(radius)\nend\n\
+            class Point\nend\n\
+            fn describe(value)\n\
+            match value\n\
+            case Shape.Circle(r)\nreturn \"circle\"\n\
+            case Point()\nreturn \"point\"\n\
+            case _\nreturn \"other\"\n\
+            end\n\
+            end\n\
+            let own = (describe(Shape.Circle(1)), describe(Point()))\n\
+            let imported = (describe(other.circle), describe(other.point))\n\
+            let equal = Shape.Circle(1) == other.circle\n",
+            "",
+        )
+        .unwrap();
+        interpreter.interpret(statements).unwrap();
+
+        assert_eq!(value(&interpreter, "own"), "(circle, point)");
+        assert_eq!(value(&interpreter, "imported"), "(other, other)");
+        assert_eq!(value(&interpreter, "equal"), "false");
+
+        assert!(error("enum Kind\nOne\nend\nlet Shape = 1\nmatch Kind.One\ncase Shape.One\nprintln(1)\nend\n").contains("Shape in pattern is not an enum"));
+    }
+
+    #[test]
+    fn test_threads_channels_and_mutexes() {
+        let interpreter = run("fn sum_range(start, stop)\n\
+            let total = 0\n\
+            while start < stop\n\
+            total = total + start\n\
+            start = start + 1\n\
+            end\n\
+            return total\n\
+            end\n\
+            let first = spawn(sum_range, 0, 5000)\n\
+            let second = spawn(sum_range, 5000, 10000)\n\
+            let sum = first.join() + second.join()\n\
+            fn produce(messages, count)\n\
+            let index = 0\n\
+            while index < count\n\
+            messages.send(index * 10)\n\
+            index = index + 1\n\
+            end\n\
+            messages.close()\n\
+            end\n\
+            let messages = channel()\n\
+            let producer = spawn(produce, messages, 3)\n\
+            let received = (messages.recv(), messages.recv(), messages.recv(), messages.recv())\n\
+            producer.join()\n\
+            fn increment(value)\n\
+            return value + 1\n\
+            end\n\
+            fn count_up(counter, times)\n\
+            while times > 0\n\
+            counter.update(increment)\n\
+            times = times - 1\n\
+            end\n\
+            end\n\
+            let counter = mutex(0)\n\
+            let workers = (spawn(count_up, counter, 300), spawn(count_up, counter, 300), spawn(count_up, counter, 300))\n\
+            for worker in workers\n\
+            worker.join()\n\
+            end\n\
+            let counted = counter.get()\n\
+            counter.lock()\n\
+            counter.set(counted * 2)\n\
+            counter.unlock()\n\
+            let doubled = counter.get()\n")
+        .unwrap();
+
+        assert_eq!(value(&interpreter, "sum"), "49995000");
+        assert_eq!(value(&interpreter, "received"), "(0, 10, 20, none)");
+        assert_eq!(value(&interpreter, "counted"), "900");
+        assert_eq!(value(&interpreter, "doubled"), "1800");
+
+        let failed = error("fn broken()\nreturn 1 - \"a\"\nend\nlet thread = spawn(broken)\nthread.join()\n");
+        assert!(failed.contains("Cannot subtract non numbers"), "{}", failed);
+    }
+
+    #[test]
+    fn test_immediate_values() {
+        let interpreter = run("class Point\nend\n\
+            let number = 1.5 * 4 - 2 ^ 2\n\
+            let flag = number > 1 and true\n\
+            let nothing = none\n\
+            let copy = number\n\
+            copy = copy + 1\n\
+            let point = Point()\n\
+            point.x = number\n\
+            let x = point.x\n\
+            x = x * 10\n\
+            let alias = point\n\
+            alias.x = alias.x + 5\n\
+            let text = \"a\" + \"b\"\n")
+        .unwrap();
+
+        assert_eq!(value(&interpreter, "number"), "2");
+        assert_eq!(value(&interpreter, "flag"), "true");
+        assert_eq!(value(&interpreter, "nothing"), "none");
+        assert_eq!(value(&interpreter, "copy"), "3");
+        assert_eq!(value(&interpreter, "x"), "20");
+        assert_eq!(value(&interpreter, "point"), value(&interpreter, "alias"));
+
+        let environment = interpreter.environment.read().unwrap();
+        let stored = |name: &str| environment.get_value(Symbol::intern(name));
+        for name in ["number", "flag", "nothing", "copy", "x"] {
+            assert!(!stored(name).is_heap(), "{} is on the heap", name);
+        }
+        assert!(stored("text").is_heap());
+        assert!(stored("point").ptr_eq(&stored("alias")));
+
+        let point = stored("point");
+        let Object::Instance(instance) = &*point.read().unwrap() else {
+            panic!("expected an instance");
+        };
+        let x = instance.get(Symbol::intern("x"), &point).unwrap();
+        assert_eq!(x.as_number(), Some(7.0));
+    }
+
+    #[test]
+    fn test_names_resolve_by_symbol() {
+        let interpreter = run("let value = 1\n\
+            class Box\n\
+            fn init(value)\n\
+            this.value = value\n\
+            end\n\
+            fn get(scale = 1)\n\
+            return this.value * scale\n\
+            end\n\
+            end\n\
+            fn outer(value)\n\
+            fn inner()\n\
+            let value = value + 100\n\
+            return value\n\
+            end\n\
+            return inner()\n\
+            end\n\
+            let box = Box(value: 7)\n\
+            let field = box.value\n\
+            let scaled = box.get(scale: 3)\n\
+            let shadowed = outer(value + 1)\n\
+            let (first, second) = (value, box.value)\n")
+        .unwrap();
+
+        assert_eq!(value(&interpreter, "value"), "1");
+        assert_eq!(value(&interpreter, "field"), "7");
+        assert_eq!(value(&interpreter, "scaled"), "21");
+        assert_eq!(value(&interpreter, "shadowed"), "102");
+        assert_eq!(value(&interpreter, "second"), "7");
+
+        assert_eq!(Symbol::intern("value"), Symbol::intern(&format!("{}{}", "val", "ue")));
+        assert_eq!(Symbol::intern("value").as_str(), "value");
+        assert_ne!(Symbol::intern("value"), Symbol::intern("values"));
+    }
+
+    #[test]
+    fn test_files_resolve_next_to_including_file_then_search_paths() {
+        let root = directory(
+            "search_paths",
+            &[
+                ("app/main.nova", "include \"helpers.nova\"\nimport \"shared\" as shared\nfrom \"extra\" import extra\nlet total = helper + shared.value + extra\n"),
+                ("app/helpers.nova", "include \"lib/nested.nova\"\nlet helper = nested\n"),
+                ("app/lib/nested.nova", "let nested = 1\n"),
+                ("first/shared.nova", "let value = 10\n"),
+                ("second/shared.nova", "let value = 1000\n"),
+                ("second/extra.nova", "let extra = 100\n"),
+            ],
+        );
+
+        let mut loader = FileSystemLoader::new();
+        loader.add_search_path(root.join("first"));
+        loader.add_search_path(root.join("second"));
+
+        let mut interpreter = AstInterpreter::new();
+        interpreter.set_module_loader(loader.clone());
+        interpreter.set_ast_cache(false);
+        interpreter.load_file(root.join("app/main.nova").to_str().unwrap()).unwrap();
+        assert_eq!(value(&interpreter, "total"), "111");
+
+        let mut interpreter = AstInterpreter::new();
+        interpreter.set_module_loader(loader);
+        let error = interpreter.load_file("missing.nova").unwrap_err().to_string();
+        assert!(error.contains("Cannot find file missing.nova"), "{}", error);
+        assert!(error.contains(&root.join("first").display().to_string()), "{}", error);
+        assert!(error.contains(&root.join("second").display().to_string()), "{}", error);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_include_cycle_names_the_files() {
+        let root = directory(
+            "include_cycle",
+            &[
+                ("main.nova", "include \"lib/a.nova\"\n"),
+                ("lib/a.nova", "include \"../b.nova\"\n"),
+                ("b.nova", "include \"lib/a.nova\"\n"),
+            ],
+        );
+
+        let mut interpreter = AstInterpreter::new();
+        interpreter.set_module_loader(FileSystemLoader::new());
+        interpreter.set_ast_cache(false);
+        let error = interpreter.load_file(root.join("main.nova").to_str().unwrap());
+
+        let (a, b) = (root.join("lib/a.nova"), root.join("b.nova"));
+        let chain = format!("Circular include: {} -> {} -> {}", a.display(), b.display(), a.display());
+        let error = error.unwrap_err().to_string();
+        assert!(error.contains(&chain), "{}", error);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_import_that_fails_to_parse() {
+        let mut loader = MemoryLoader::new();
+        loader.insert("broken.nova", "let = 1\n");
+
+        let mut interpreter = AstInterpreter::new();
+        interpreter.set_module_loader(loader);
+        let statements = generate_parsed_ast("import \"broken\" as broken\n", "").unwrap();
+        let error = interpreter.interpret(statements).unwrap_err().to_string();
+
+        assert!(error.contains("Error importing file: broken.nova\nUnable to parse abstract syntax tree"), "{}", error);
+    }
+
+    #[test]
+    fn test_generator_yielding_none() {
+        let interpreter = run("fn items()\n\
+            yield 1\n\
+            yield none\n\
+            yield 3\n\
+            end\n\
+            let all = tuple(items())\n\
+            let count = 0\n\
+            for item in items()\n\
+            count = count + 1\n\
+            end\n\
+            let generator = items()\n\
+            let (first, second, third, fourth) = (generator.next(), generator.next(), generator.next(), generator.next())\n")
+        .unwrap();
+
+        assert_eq!(value(&interpreter, "all"), "(1, none, 3)");
+        assert_eq!(value(&interpreter, "count"), "3");
+        assert_eq!(value(&interpreter, "second"), "none");
+        assert_eq!(value(&interpreter, "fourth"), "done");
+    }
+
+    #[test]
+    fn test_iterators_over_none() {
+        let interpreter = run("class Items\n\
+            fn init(items)\n\
+            this.items = iter(items)\n\
+            end\n\
+            fn next()\n\
+            return next(this.items)\n\
+            end\n\
+            end\n\
+            class Sequence\n\
+            fn iter()\n\
+            return Items((1, none, 3))\n\
+            end\n\
+            end\n\
+            fn same(item)\n\
+            return item\n\
+            end\n\
+            fn always(item)\n\
+            return true\n\
+            end\n\
+            let count = 0\n\
+            for item in Sequence()\n\
+            count = count + 1\n\
+            end\n\
+            let (a, b, c) = Sequence()\n\
+            let mapped = tuple(map(same, Sequence()))\n\
+            let filtered = tuple(filter(same, (1, none, 3)))\n\
+            let kept = tuple(filter(always, (none, none)))\n\
+            let letters = iter(\"a\")\n\
+            let (letter, end_of_letters) = (next(letters), next(letters))\n")
+        .unwrap();
+
+        assert_eq!(value(&interpreter, "count"), "3");
+        assert_eq!(value(&interpreter, "b"), "none");
+        assert_eq!(value(&interpreter, "mapped"), "(1, none, 3)");
+        assert_eq!(value(&interpreter, "filtered"), "(1, 3)");
+        assert_eq!(value(&interpreter, "kept"), "(none, none)");
+        assert_eq!(value(&interpreter, "end_of_letters"), "done");
+
+        let error = error("class Broken\nfn iter()\nreturn 3\nend\nend\nfor item in Broken()\nend\n");
+        assert!(error.contains("iter() must return an iterator object, not 3"), "{}", error);
+    }
+
+    #[test]
+    fn test_argument_errors() {
+        let connect = "fn connect(host, port, secure = false)\nreturn host\nend\n";
+        let call = |arguments: &str| error(&format!("{}connect({})\n", connect, arguments));
+
+        assert!(call("\"local\"").contains("connect missing required argument port"));
+        assert!(call("\"local\", 80, true, 1").contains("connect takes 2 to 3 positional arguments but 4 were given"));
+        assert!(call("\"local\", 80, timeout: 1").contains("connect got an unexpected keyword argument timeout"));
+        assert!(call("\"local\", 80, host: 1").contains("connect got multiple values for argument host"));
+
+        assert!(error("class Point\nend\nPoint(1)\n").contains("Point takes 0 positional arguments but 1 were given"));
+        assert!(error("next()\n").contains("next takes 1 arguments but 0 were given"));
+    }
+
+    #[test]
+    fn test_async_waits_overlap() {
+        let interpreter = run("let log = \"\"\n\
+            async fn tick(name, times, delay)\n\
+            let count = 0\n\
+            while count < times\n\
+            log = log + name\n\
+            if count >= 0\n\
+            await sleep(delay)\n\
+            end\n\
+            count = count + 1\n\
+            end\n\
+            return count\n\
+            end\n\
+            fn late()\n\
+            log = log + \"!\"\n\
+            return \"late\"\n\
+            end\n\
+            let started = time(\"milli\")\n\
+            let timeout = set_timeout(late, 100)\n\
+            let counts = await gather(tick(\"a\", 3, 40), tick(\"b\", 2, 70))\n\
+            let elapsed = time(\"milli\") - started\n\
+            let timed_out = await timeout\n")
+        .unwrap();
+
+        assert_eq!(value(&interpreter, "counts"), "(3, 2)");
+        assert_eq!(value(&interpreter, "timed_out"), "late");
+
+        // each task logs before it waits, one after the other "a" would wait three times before "b" starts
+        let log = value(&interpreter, "log");
+        assert!(log.starts_with("ab"), "{}", log);
+        let count = |letter| log.chars().filter(|character| *character == letter).count();
+        assert_eq!((count('a'), count('b'), count('!')), (3, 2, 1), "{}", log);
+
+        let elapsed: f64 = value(&interpreter, "elapsed").parse().unwrap();
+        assert!(elapsed >= 120.0, "took {}ms", elapsed);
+    }
+
+    #[test]
+    fn test_unawaited_task_errors() {
+        let failing = "async fn fail(delay)\nawait sleep(delay)\nreturn 1 - \"a\"\nend\n\
+            fn explode()\nreturn 1 - \"a\"\nend\n";
+        let finish = |source: &str| {
+            let mut interpreter = run(&format!("{}{}", failing, source)).unwrap();
+            interpreter.run_event_loop()
+        };
+
+        let error = finish("fail(1)\n").unwrap_err().to_string();
+        assert!(error.contains("task: fail failed without being awaited"), "{}", error);
+        assert!(error.contains("Cannot subtract non numbers"), "{}", error);
+
+        let error = finish("set_timeout(explode, 1)\n").unwrap_err().to_string();
+        assert!(error.contains("task: timeout failed without being awaited"), "{}", error);
+
+        let error = finish("gather(fail(1), sleep(1))\n").unwrap_err().to_string();
+        assert!(error.contains("task: gather failed without being awaited"), "{}", error);
+
+        // a task failing before it is awaited reports its error at the await instead
+        let error = run(&format!("{}let task = fail(1)\nawait sleep(20)\nawait task\n", failing));
+        assert!(error.is_err_and(|error| !error.to_string().contains("without being awaited")));
+        assert!(finish("let task = fail(1)\nlet other = sleep(5)\nawait other\n").is_err());
+        assert!(finish("let task = fail(1)\nlet quiet = sleep(5)\n").is_err());
+        assert!(finish("async fn fine()\nawait sleep(1)\nend\nfine()\nsleep(1)\n").is_ok());
+    }
+
+    #[test]
+    fn test_garbage_collected_while_threads_run() {
+        let mut interpreter = run("class Node\nend\n\
+            fn churn(count)\n\
+            let kept = Node()\n\
+            kept.total = 0\n\
+            while count > 0\n\
+            let a = Node()\n\
+            let b = Node()\n\
+            a.other = b\n\
+            b.other = a\n\
+            kept.total = kept.total + 1\n\
+            count = count - 1\n\
+            end\n\
+            return kept\n\
+            end\n\
+            fn work(gate)\n\
+            let kept = churn(20000)\n\
+            gate.recv()\n\
+            return kept.total\n\
+            end\n\
+            let gate = channel()\n\
+            let worker = spawn(work, gate)\n\
+            let mine = churn(20000)\n")
+        .unwrap();
+
+        // the worker is still running, it waits for the gate
+        let stats = interpreter.heap_stats();
+        assert!(stats.collections >= 1, "{:?}", stats);
+        assert!(stats.total_freed >= 20000, "{:?}", stats);
+
+        let source = "gate.send(1)\nlet total = worker.join() + mine.total\n";
+        interpreter.interpret(generate_parsed_ast(source, "").unwrap()).unwrap();
+        assert_eq!(value(&interpreter, "total"), "40000");
+    }
+
+    #[test]
+    fn test_heap_stats_from_nova() {
+        let interpreter = run("class Node\nend\n\
+            let before = heap_stats()\n\
+            let node = Node()\n\
+            node.other = node\n\
+            node = none\n\
+            let stats = collect_garbage()\n\
+            let counts = (before.collections, stats.collections, stats.skipped, stats.freed)\n\
+            let now = heap_stats()\n\
+            let after = now.total_freed == stats.total_freed\n")
+        .unwrap();
+
+        assert_eq!(value(&interpreter, "counts"), "(0, 1, 0, 1)");
+        assert_eq!(value(&interpreter, "after"), "true");
+    }
+
+    #[test]
+    fn test_packages_resolve_by_name_for_their_dependents() {
+        let root = directory(
+            "packages",
+            &[
+                ("mathlib/nova.toml", "[package]\nname = \"mathlib\"\nversion = \"1.0.0\"\n"),
+                ("mathlib/main.nova", "fn add(a, b)\nreturn a + b\nend\n"),
+                (
+                    "vector/nova.toml",
+                    "[package]\nname = \"vector\"\nversion = \"0.2.0\"\nentry = \"lib.nova\"\n[dependencies]\nmathlib = \"../mathlib\"\n",
+                ),
+                ("vector/lib.nova", "import \"mathlib\" as math\nfn sum(a, b, c)\nreturn math.add(math.add(a, b), c)\nend\n"),
+                ("vector/extra.nova", "import \"mathlib/main\" as math\nfn double(a)\nreturn math.add(a, a)\nend\n"),
+                (
+                    "app/nova.toml",
+                    "[package]\nname = \"app\"\nversion = \"0.1.0\"\n[dependencies]\nvector = { path = \"../vector\", version = \"0.2.0\" }\n",
+                ),
+                ("app/main.nova", "import \"vector\" as vector\nimport \"vector/extra\" as extra\nlet total = vector.sum(1, 2, 3)\nlet doubled = extra.double(4)\n"),
+                ("app/hidden.nova", "import \"mathlib\" as math\n"),
+            ],
+        );
+
+        let resolution = Resolution::resolve(&root.join("app")).unwrap();
+        let mut interpreter = AstInterpreter::new();
+        interpreter.set_module_loader(resolution.loader(FileSystemLoader::new()));
+        interpreter.set_ast_cache(false);
+        interpreter.load_file(resolution.entry().to_str().unwrap()).unwrap();
+        assert_eq!(value(&interpreter, "total"), "6");
+        assert_eq!(value(&interpreter, "doubled"), "8");
+
+        let mut interpreter = AstInterpreter::new();
+        interpreter.set_module_loader(resolution.loader(FileSystemLoader::new()));
+        interpreter.set_ast_cache(false);
+        let error = interpreter.load_file(root.join("app/hidden.nova").to_str().unwrap());
+        let error = error.unwrap_err().to_string();
+        assert!(error.contains("Cannot use package mathlib"), "{}", error);
+        assert!(error.contains("it is not a dependency of app"), "{}", error);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_prelude() {
+        let interpreter = run("fn square(number)\nreturn number * number\nend\n\
+            fn even(number)\nreturn number % 2 == 0\nend\n\
+            fn add(sum, number)\nreturn sum + number\nend\n\
+            let squares = tuple(map(square, range(1, 5)))\n\
+            let evens = tuple(filter(even, range(0, 7)))\n\
+            let sum = reduce(add, squares, 0)\n\
+            let sizes = (len(squares), total(evens), abs(-3), clamp(12, 0, 10))\n\
+            let pairs = tuple(enumerate((\"a\", \"b\")))\n\
+            let checks = (any((false, none, 0)), all((1, false)))\n\
+            fn len(items)\nreturn \"shadowed\"\nend\n\
+            let shadowed = len(squares)\n")
+        .unwrap();
+
+        assert_eq!(value(&interpreter, "squares"), "(1, 4, 9, 16)");
+        assert_eq!(value(&interpreter, "evens"), "(0, 2, 4, 6)");
+        assert_eq!(value(&interpreter, "sum"), "30");
+        assert_eq!(value(&interpreter, "sizes"), "(4, 12, 3, 10)");
+        assert_eq!(value(&interpreter, "pairs"), "((0, a), (1, b))");
+        assert_eq!(value(&interpreter, "checks"), "(true, false)");
+        assert_eq!(value(&interpreter, "shadowed"), "shadowed");
+
+        let mut interpreter = AstInterpreter::new();
+        interpreter.set_prelude(false);
+        let statements = generate_parsed_ast("let number = abs(-3)\n", "").unwrap();
+        assert!(interpreter.interpret(statements).is_err());
+    }
+
+    #[test]
+    fn test_prelude_in_threads_and_their_modules() {
+        let mut loader = MemoryLoader::new();
+        loader.insert("doubles.nova", "fn double(number)\nreturn number * 2\nend\nfn doubled(items)\nreturn tuple(map(double, items))\nend\n");
+
+        let mut interpreter = AstInterpreter::new();
+        interpreter.set_module_loader(loader);
+        let statements = generate_parsed_ast(
+            "fn work(items)\n\
+            import \"doubles\" as doubles\n\
+            return (doubles.doubled(items), len(items))\n\
+            end\n\
+            let thread = spawn(work, (1, 2, 3))\n\
+            let result = thread.join()\n",
+            "",
+        )
+        .unwrap();
+        interpreter.interpret(statements).unwrap();
+
+        assert_eq!(value(&interpreter, "result"), "((2, 4, 6), 3)");
+    }
+
+    #[test]
+    fn test_method_cache_does_not_keep_classes_alive() {
+        let mut interpreter = run("class Node\nend\n\
+            fn fresh(index)\nreturn Node()\nend\n\
+            fn make()\n\
+            let nodes = tuple(map(fresh, range(0, 5000)))\n\
+            class Holder\n\
+            fn count()\nreturn len(nodes)\nend\n\
+            end\n\
+            return Holder()\n\
+            end\n\
+            fn count(holder)\nreturn holder.count()\nend\n\
+            let counted = count(make())\n")
+        .unwrap();
+        assert_eq!(value(&interpreter, "counted"), "5000");
+
+        // `count` keeps the call site, the class and its nodes are garbage
+        let stats = interpreter.collect_garbage();
+        assert!(stats.live < 5000, "{:?}", stats);
     }
 }
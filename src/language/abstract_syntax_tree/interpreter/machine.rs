@@ -0,0 +1,766 @@
+use std::{
+    mem,
+    sync::{Arc, RwLock},
+};
+
+use super::{AstInterpreter, CallFrame};
+use crate::language::{
+    abstract_syntax_tree::{
+        control_flow::{ControlFlow, TailCall},
+        environment::Environment,
+        expression::{call::KeywordArgument, Expression},
+        statement::{
+            assignment::{DestructuringAssign, Get},
+            declaration::TupleDeclaration,
+            ForLoop, IfStatement, MatchStatement, Statement, WhileLoop,
+        },
+    },
+    errors,
+    scanner::{
+        object::{Callable, ClassObject, DefinedCall, Invocation, KeywordArguments, Object, TupleObject, WrappedObject},
+        symbol::Symbol,
+        token::TokenType,
+    },
+};
+
+/// runs that may be nested inside each other, each one is a native function or an iterator
+/// calling back into the interpreter on the native stack
+const MAX_NESTED_RUNS: usize = 1000;
+
+/// expressions visited inside each other on the native stack, operands nested deeper are
+/// scheduled as tasks instead
+const MAX_INLINE_DEPTH: usize = 64;
+
+/// operators nested inside each other that are computed straight away, without tasks or the
+/// value stack, when all of their operands are literals and variables
+const MAX_IMMEDIATE_DEPTH: usize = 8;
+
+/// Work left for the interpreter to do, kept on a stack in place of the native call stack.
+///
+/// Visiting a statement or an expression schedules it: values known straight away are pushed
+/// onto the value stack and whatever has to wait, such as the operator of a binary expression
+/// whose operand calls a function, is pushed as a task. Tasks take their operands from the value
+/// stack and push their results back, so calls of defined functions nest as deep as the stack
+/// memory limit allows.
+#[derive(Debug)]
+pub(super) enum Task {
+    /// an expression waiting for the ones evaluated before it
+    Evaluate(Expression),
+    /// runs the statements of a block from `index` on
+    Statements {
+        statements: Arc<[Statement]>,
+        index: usize,
+    },
+    /// leaves a scope, making this one current again
+    RestoreEnvironment(Arc<RwLock<Environment>>),
+    /// drops the value of an expression statement, printing it in the repl
+    Discard,
+    Binary(TokenType),
+    Unary(TokenType),
+    Assign(Symbol),
+    Set(Symbol),
+    Tuple(usize),
+    Destructure(Arc<DestructuringAssign>),
+    /// looks up a property of the value on the stack, calling it when it is a method call
+    Property(Arc<Get>),
+    /// the method call of a `return object.method(...)`, called as a tail call
+    TailProperty(Arc<Get>),
+    /// calls the callee on the stack with the positional arguments and one keyword argument per
+    /// name above it
+    Call {
+        arguments: usize,
+        keywords: Box<[Symbol]>,
+    },
+    /// the call of a `return f(...)`, made once the returning function has left the stack
+    TailCall {
+        arguments: usize,
+        keywords: Box<[Symbol]>,
+    },
+    Await,
+    Declare {
+        name: Symbol,
+        constant: bool,
+    },
+    DeclareTuple(Arc<TupleDeclaration>),
+    Return,
+    If(Arc<IfStatement>),
+    Match(Arc<MatchStatement>),
+    /// checks the condition of a while loop, `break` and `continue` unwind to it
+    While(Arc<WhileLoop>),
+    /// runs the body of a while loop if the condition on the stack holds
+    WhileBody(Arc<WhileLoop>),
+    /// starts a for loop over the iterable on the stack
+    For(Arc<ForLoop>),
+    /// runs the next iteration of a for loop, `break` and `continue` unwind to it
+    ForNext {
+        for_loop: Arc<ForLoop>,
+        iterator: WrappedObject,
+    },
+    /// a call of a defined function in progress, returning to the caller's scope once the body
+    /// is done, a call of an initializer evaluates to its instance
+    Frame {
+        environment: Arc<RwLock<Environment>>,
+        instance: Option<WrappedObject>,
+    },
+    /// replaces the result of a tail call made by an initializer with its instance
+    Initialized(WrappedObject),
+}
+
+impl AstInterpreter {
+    /// runs `schedule` and the tasks it leaves behind until the stack is back where it started,
+    /// returning how the statements it ran finished. An error leaves the stacks and the current
+    /// scope as they were, so the interpreter can carry on with other work.
+    pub(super) fn run(
+        &mut self,
+        schedule: impl FnOnce(&mut Self) -> Result<(), errors::Error>,
+    ) -> Result<ControlFlow, errors::Error> {
+        if self.nested_runs >= MAX_NESTED_RUNS {
+            return Err(self.recursion_error());
+        }
+
+        let (base, values, calls) = (self.tasks.len(), self.values.len(), self.call_stack.len());
+        let environment = Arc::clone(&self.environment);
+        let outer_base = mem::replace(&mut self.base, base);
+        self.nested_runs += 1;
+
+        let mut result = schedule(self);
+        while result.is_ok() && self.tasks.len() > base {
+            let task = self.tasks.pop().unwrap();
+            result = self.perform(task);
+        }
+
+        self.nested_runs -= 1;
+        self.base = outer_base;
+        let flow = self.escaped.take().unwrap_or(ControlFlow::Normal);
+
+        if let Err(err) = result {
+            self.tasks.truncate(base);
+            self.values.truncate(values);
+            self.call_stack.truncate(calls);
+            self.environment = environment;
+            return Err(err);
+        }
+
+        Ok(flow)
+    }
+
+    /// runs `schedule`, returning the value it leaves on the value stack
+    pub(super) fn run_value(
+        &mut self,
+        schedule: impl FnOnce(&mut Self) -> Result<(), errors::Error>,
+    ) -> Result<WrappedObject, errors::Error> {
+        self.run(schedule)?;
+        Ok(self.pop_value())
+    }
+
+    /// bytes taken by the task, value and call stacks
+    pub(super) fn stack_memory(&self) -> usize {
+        self.tasks.len() * mem::size_of::<Task>()
+            + self.values.len() * mem::size_of::<WrappedObject>()
+            + self.call_stack.len() * mem::size_of::<CallFrame>()
+    }
+
+    /// performs `task` right away when nothing was scheduled since the task stack was `height`
+    /// tasks high, otherwise slots it in below the scheduled tasks so it runs once they are done
+    pub(super) fn then(&mut self, height: usize, task: Task) -> Result<(), errors::Error> {
+        if self.tasks.len() == height {
+            return self.perform(task);
+        }
+
+        self.tasks.insert(height, task);
+        Ok(())
+    }
+
+    /// schedules expressions to be evaluated one after another, leaving their values on the
+    /// value stack in the same order
+    pub(super) fn schedule_in_order<'a>(
+        &mut self,
+        mut expressions: impl DoubleEndedIterator<Item = &'a Expression>,
+    ) -> Result<(), errors::Error> {
+        while let Some(expression) = expressions.next() {
+            let height = self.tasks.len();
+            self.schedule_expression(expression)?;
+
+            if self.tasks.len() > height {
+                // the rest waits for the tasks just scheduled
+                let rest = expressions.rev().map(|expression| Task::Evaluate(expression.clone()));
+                self.tasks.splice(height..height, rest);
+                return Ok(());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// schedules an operand, visiting it right away unless the expressions it is part of are
+    /// already nested deep on the native stack
+    pub(super) fn schedule_expression(&mut self, expression: &Expression) -> Result<(), errors::Error> {
+        if let Some(value) = self.immediate(expression) {
+            self.values.push(value?);
+            return Ok(());
+        }
+
+        if self.inline_depth >= MAX_INLINE_DEPTH {
+            self.tasks.push(Task::Evaluate(expression.clone()));
+            return Ok(());
+        }
+
+        self.inline_depth += 1;
+        let result = expression.accept(self);
+        self.inline_depth -= 1;
+        result
+    }
+
+    /// the value of a literal, a variable or a few operators on them, which is known without
+    /// scheduling anything
+    pub(super) fn immediate(&self, expression: &Expression) -> Option<Result<WrappedObject, errors::Error>> {
+        Self::is_immediate(expression, MAX_IMMEDIATE_DEPTH).then(|| self.compute(expression))
+    }
+
+    fn is_immediate(expression: &Expression, depth: usize) -> bool {
+        match expression {
+            Expression::Literal(_) | Expression::Variable(_) => true,
+            _ if depth == 0 => false,
+            Expression::Binary(binary) => {
+                Self::is_immediate(&binary.left, depth - 1) && Self::is_immediate(&binary.right, depth - 1)
+            }
+            Expression::Unary(unary) => Self::is_immediate(&unary.right, depth - 1),
+            Expression::Grouping(grouping) => Self::is_immediate(&grouping.expression, depth - 1),
+            _ => false,
+        }
+    }
+
+    /// the value of an expression `is_immediate` accepts
+    fn compute(&self, expression: &Expression) -> Result<WrappedObject, errors::Error> {
+        match expression {
+            Expression::Literal(literal) => Ok(literal.object.clone().wrap()),
+            Expression::Variable(variable) => self
+                .environment
+                .read()
+                .map(|environment| environment.get_value(variable.name.name()))
+                .map_err(|err| errors::Error::Runtime(err.to_string())),
+            Expression::Binary(binary) => {
+                let left = self.compute(&binary.left)?;
+                let right = self.compute(&binary.right)?;
+                Self::binary_operation(binary.operator.token_type, &left, &right)
+            }
+            Expression::Unary(unary) => {
+                let right = self.compute(&unary.right)?;
+                Self::unary_operation(unary.operator.token_type, &right)
+            }
+            Expression::Grouping(grouping) => self.compute(&grouping.expression),
+            _ => unreachable!("not an immediate expression"),
+        }
+    }
+
+    /// schedules the callee and arguments of a call
+    pub(super) fn schedule_call(
+        &mut self,
+        callee: &Expression,
+        arguments: &[Expression],
+        keyword_arguments: &[KeywordArgument],
+    ) -> Result<(), errors::Error> {
+        let keyword_values = keyword_arguments.iter().map(|(_, argument)| argument);
+        self.schedule_in_order(std::iter::once(callee).chain(arguments).chain(keyword_values))
+    }
+
+    pub(super) fn keyword_names(keyword_arguments: &[KeywordArgument]) -> Box<[Symbol]> {
+        keyword_arguments.iter().map(|(name, _)| name.name()).collect()
+    }
+
+    /// runs `statements` in `environment`, returning to the current scope afterwards
+    pub(super) fn enter_block(&mut self, statements: &Arc<[Statement]>, environment: Arc<RwLock<Environment>>) {
+        let previous = self.replace_environment(environment);
+        self.tasks.push(Task::RestoreEnvironment(previous));
+
+        if !statements.is_empty() {
+            self.tasks.push(Task::Statements {
+                statements: Arc::clone(statements),
+                index: 0,
+            });
+        }
+    }
+
+    /// unwinds the task stack for a `break`, `continue`, `return` or tail call up to the loop or
+    /// call it leaves, a jump reaching the bottom of the current run is returned by `run`
+    pub(super) fn jump(&mut self, flow: ControlFlow) -> Result<(), errors::Error> {
+        while self.tasks.len() > self.base {
+            match self.tasks.pop().unwrap() {
+                Task::RestoreEnvironment(environment) => self.environment = environment,
+                task @ (Task::While(_) | Task::ForNext { .. })
+                    if matches!(flow, ControlFlow::Break | ControlFlow::Continue) =>
+                {
+                    if matches!(flow, ControlFlow::Continue) {
+                        self.tasks.push(task);
+                    }
+                    return Ok(());
+                }
+                Task::Frame {
+                    environment,
+                    instance,
+                } => return self.leave_frame(environment, instance, flow),
+                _ => {}
+            }
+        }
+
+        self.escaped = Some(flow);
+        Ok(())
+    }
+
+    /// finishes a call of a defined function with the way its body finished
+    fn leave_frame(
+        &mut self,
+        environment: Arc<RwLock<Environment>>,
+        instance: Option<WrappedObject>,
+        flow: ControlFlow,
+    ) -> Result<(), errors::Error> {
+        self.environment = environment;
+        self.leave_call();
+
+        let value = match flow {
+            ControlFlow::Return(value) => value,
+            ControlFlow::TailCall(call) => {
+                if let Some(instance) = instance {
+                    self.tasks.push(Task::Initialized(instance));
+                }
+                return self.begin_call(call.callee, call.arguments, call.keyword_arguments);
+            }
+            _ => Object::None.wrap(),
+        };
+
+        self.values.push(instance.unwrap_or(value));
+        Ok(())
+    }
+
+    /// starts a call, pushing its result when it is known straight away and otherwise scheduling
+    /// the body of the called function
+    pub(super) fn begin_call(
+        &mut self,
+        callee: WrappedObject,
+        arguments: Vec<WrappedObject>,
+        keyword_arguments: Vec<(Symbol, WrappedObject)>,
+    ) -> Result<(), errors::Error> {
+        let callee_binding = callee.read().unwrap();
+        let Object::Callable(callable) = &(*callee_binding) else {
+            return Err(errors::Error::intepret_error("undefined function"));
+        };
+
+        match callable {
+            Callable::DefinedCall(function) => {
+                self.begin_function(function, &arguments, &keyword_arguments, None)
+            }
+            Callable::Class(class) => self.begin_instance(class, &arguments, &keyword_arguments),
+            callable => {
+                let value = callable.call(self, &arguments, &keyword_arguments)?;
+                self.values.push(value);
+                Ok(())
+            }
+        }
+    }
+
+    /// starts a call of a defined function, an initializer evaluating to `instance`
+    pub(super) fn begin_function(
+        &mut self,
+        function: &DefinedCall,
+        arguments: &[WrappedObject],
+        keyword_arguments: &KeywordArguments,
+        instance: Option<WrappedObject>,
+    ) -> Result<(), errors::Error> {
+        let environment = match function.invoke(self, arguments, keyword_arguments)? {
+            Invocation::Finished(value) => {
+                self.values.push(instance.unwrap_or(value));
+                return Ok(());
+            }
+            Invocation::Body(environment) => environment,
+        };
+
+        let declaration = function.declaration();
+        self.enter_call(declaration)?;
+
+        let caller = self.replace_environment(environment);
+        self.tasks.push(Task::Frame {
+            environment: caller,
+            instance,
+        });
+        if !declaration.body.statements.is_empty() {
+            self.tasks.push(Task::Statements {
+                statements: Arc::clone(&declaration.body.statements),
+                index: 0,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// starts creating an instance of a class, running its initializer if it has one
+    pub(super) fn begin_instance(
+        &mut self,
+        class: &ClassObject,
+        arguments: &[WrappedObject],
+        keyword_arguments: &KeywordArguments,
+    ) -> Result<(), errors::Error> {
+        match class.instantiate(self) {
+            (instance, Some(initializer)) => {
+                self.begin_function(&initializer, arguments, keyword_arguments, Some(instance))
+            }
+            (instance, None) => {
+                if let Some((name, _)) = keyword_arguments.first() {
+                    return Err(errors::Error::Runtime(format!(
+                        "{} got an unexpected keyword argument {}",
+                        class.name(),
+                        name
+                    )));
+                }
+                if !arguments.is_empty() {
+                    return Err(errors::Error::Runtime(format!(
+                        "{} takes 0 positional arguments but {} were given",
+                        class.name(),
+                        arguments.len()
+                    )));
+                }
+
+                self.values.push(instance);
+                Ok(())
+            }
+        }
+    }
+
+    fn pop_value(&mut self) -> WrappedObject {
+        self.values.pop().unwrap()
+    }
+
+    fn pop_values(&mut self, count: usize) -> Vec<WrappedObject> {
+        self.values.drain(self.values.len() - count..).collect()
+    }
+
+    /// takes the callee and arguments of a call off the value stack
+    fn pop_call(
+        &mut self,
+        arguments: usize,
+        keywords: &[Symbol],
+    ) -> (WrappedObject, Vec<WrappedObject>, Vec<(Symbol, WrappedObject)>) {
+        let keyword_values = self.pop_values(keywords.len());
+        let arguments = self.pop_values(arguments);
+        let callee = self.pop_value();
+
+        (callee, arguments, keywords.iter().copied().zip(keyword_values).collect())
+    }
+
+    /// runs the next iteration of a for loop, or finishes the loop once the iterator is exhausted
+    fn next_iteration(&mut self, for_loop: Arc<ForLoop>, iterator: WrappedObject) -> Result<(), errors::Error> {
+        let Some(item) = self.next_item(&iterator)? else {
+            return Ok(());
+        };
+
+        let environment = self.loop_environment(&for_loop, item, Arc::clone(&self.environment))?;
+        let previous = self.replace_environment(environment);
+        self.tasks.push(Task::ForNext {
+            for_loop: Arc::clone(&for_loop),
+            iterator,
+        });
+        self.tasks.push(Task::RestoreEnvironment(previous));
+
+        for_loop.body.accept(self)
+    }
+
+    /// looks up a property of the value on the stack and schedules calling it for a method call
+    fn property(&mut self, get: &Get, tail: bool) -> Result<(), errors::Error> {
+        let object = self.pop_value();
+        let property = self.get_property_cached(&object, get.name.name(), &get.cache)?;
+        self.values.push(property);
+
+        let Some(arguments) = &get.arguments else {
+            return Ok(());
+        };
+
+        let height = self.tasks.len();
+        let keyword_values = get.keyword_arguments.iter().map(|(_, argument)| argument);
+        self.schedule_in_order(arguments.iter().chain(keyword_values))?;
+
+        let (arguments, keywords) = (arguments.len(), Self::keyword_names(&get.keyword_arguments));
+        let call = match tail {
+            true => Task::TailCall { arguments, keywords },
+            false => Task::Call { arguments, keywords },
+        };
+        self.then(height, call)
+    }
+
+    fn perform(&mut self, task: Task) -> Result<(), errors::Error> {
+        match task {
+            Task::Evaluate(expression) => expression.accept(self),
+
+            Task::Statements { statements, index } => {
+                if index + 1 < statements.len() {
+                    self.tasks.push(Task::Statements {
+                        statements: Arc::clone(&statements),
+                        index: index + 1,
+                    });
+                }
+                self.schedule_statement(&statements[index])
+            }
+
+            Task::RestoreEnvironment(environment) => {
+                self.environment = environment;
+                Ok(())
+            }
+
+            Task::Discard => {
+                let value = self.pop_value();
+                if self.interactive {
+                    let value = value.read().unwrap();
+                    if !value.is_none() {
+                        println!("{}", value);
+                    }
+                }
+                Ok(())
+            }
+
+            Task::Binary(operator) => {
+                let right = self.pop_value();
+                let left = self.pop_value();
+                let value = Self::binary_operation(operator, &left, &right)?;
+                self.values.push(value);
+                Ok(())
+            }
+
+            Task::Unary(operator) => {
+                let right = self.pop_value();
+                let value = Self::unary_operation(operator, &right)?;
+                self.values.push(value);
+                Ok(())
+            }
+
+            Task::Assign(name) => {
+                let value = self.pop_value();
+                self.environment.write().unwrap().set_value(name, value)?;
+                self.values.push(Object::None.wrap());
+                Ok(())
+            }
+
+            Task::Set(name) => {
+                let value = self.pop_value();
+                let object = self.pop_value();
+
+                if let Object::Instance(instance) = &mut *object.write().unwrap() {
+                    instance.set(name, value.clone());
+                    self.values.push(value);
+                    return Ok(());
+                }
+
+                Err(errors::Error::Runtime(
+                    "Only instances have fields".to_string(),
+                ))
+            }
+
+            Task::Tuple(count) => {
+                let elements = self.pop_values(count);
+                self.values.push(Object::Tuple(TupleObject::new(elements)).wrap());
+                Ok(())
+            }
+
+            Task::Destructure(assign) => {
+                let value = self.pop_value();
+                self.destructure(&assign, value)?;
+                self.values.push(Object::None.wrap());
+                Ok(())
+            }
+
+            Task::Property(get) => self.property(&get, false),
+
+            Task::TailProperty(get) => self.property(&get, true),
+
+            Task::Call { arguments, keywords } => {
+                let (callee, arguments, keyword_arguments) = self.pop_call(arguments, &keywords);
+                self.begin_call(callee, arguments, keyword_arguments)
+            }
+
+            Task::TailCall { arguments, keywords } => {
+                let (callee, arguments, keyword_arguments) = self.pop_call(arguments, &keywords);
+                self.jump(ControlFlow::TailCall(Box::new(TailCall {
+                    callee,
+                    arguments,
+                    keyword_arguments,
+                })))
+            }
+
+            Task::Await => {
+                let awaited = self.pop_value();
+                let value = self.await_value(&awaited)?;
+                self.values.push(value);
+                Ok(())
+            }
+
+            Task::Declare { name, constant } => {
+                let value = Self::copy_if_preferred(self.pop_value());
+                let mut environment = self.environment.write().unwrap();
+                if constant {
+                    environment.declare_constant(name, value)
+                } else {
+                    environment.declare_value(name, value)
+                }
+            }
+
+            Task::DeclareTuple(declaration) => {
+                let value = self.pop_value();
+                let values = self.unpack(value, declaration.names.len())?;
+
+                let mut environment = self.environment.write().unwrap();
+                for (name, value) in declaration.names.iter().zip(values) {
+                    let name = name.name();
+                    let value = Self::copy_if_preferred(value);
+
+                    if declaration.constant {
+                        environment.declare_constant(name, value)?;
+                    } else {
+                        environment.declare_value(name, value)?;
+                    }
+                }
+                Ok(())
+            }
+
+            Task::Return => {
+                let value = self.pop_value();
+                self.jump(ControlFlow::Return(value))
+            }
+
+            Task::If(if_statement) => {
+                let condition = self.pop_value();
+                let truthy = condition.read().unwrap().is_truthy();
+
+                if truthy {
+                    if_statement.then_branch.accept(self)
+                } else if let Some(else_branch) = &if_statement.else_branch {
+                    else_branch.accept(self)
+                } else {
+                    Ok(())
+                }
+            }
+
+            Task::Match(match_statement) => {
+                let value = self.pop_value();
+                let (arm, environment) = self.choose_arm(&match_statement, value)?;
+                self.enter_block(&arm.body.statements, environment);
+                Ok(())
+            }
+
+            Task::While(while_loop) => {
+                let height = self.tasks.len();
+                while_loop.condition.accept(self)?;
+                self.then(height, Task::WhileBody(while_loop))
+            }
+
+            Task::WhileBody(while_loop) => {
+                let condition = self.pop_value();
+                if !condition.read().unwrap().is_truthy() {
+                    return Ok(());
+                }
+
+                self.tasks.push(Task::While(Arc::clone(&while_loop)));
+                while_loop.body.accept(self)
+            }
+
+            Task::For(for_loop) => {
+                let iterable = self.pop_value();
+                let iterator = self.get_iterator(iterable)?;
+                self.next_iteration(for_loop, iterator)
+            }
+
+            Task::ForNext { for_loop, iterator } => self.next_iteration(for_loop, iterator),
+
+            Task::Frame {
+                environment,
+                instance,
+            } => self.leave_frame(environment, instance, ControlFlow::Normal),
+
+            Task::Initialized(instance) => {
+                self.pop_value();
+                self.values.push(instance);
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+
+    use crate::language::{generate_parsed_ast, scanner::symbol::Symbol, AstInterpreter};
+
+    const DEEP: &str = "fn deep(n)\n    if n == 0\n        return 0\n    end\n    return 1 + deep(n - 1)\nend\nlet depth = deep(50000)\n";
+
+    #[test]
+    fn test_recursion_does_not_use_native_stack() {
+        let mut interpreter = AstInterpreter::new();
+        let statements = generate_parsed_ast(DEEP, "").unwrap();
+
+        assert!(interpreter.interpret(statements).is_ok());
+    }
+
+    #[test]
+    fn test_recursion_limited_by_stack_memory() {
+        let mut interpreter = AstInterpreter::new();
+        interpreter.set_max_stack_memory(1024 * 1024);
+        let statements = generate_parsed_ast(DEEP, "").unwrap();
+
+        let error = interpreter.interpret(statements).unwrap_err();
+        assert!(error.to_string().starts_with("maximum recursion depth exceeded"));
+    }
+
+    #[test]
+    fn test_tail_calls_run_in_constant_space() {
+        let source = "fn count(n)\n\
+            if n == 0\n\
+            return \"function\"\n\
+            end\n\
+            return count(n - 1)\n\
+            end\n\
+            class Counter\n\
+            fn count(n)\n\
+            if n == 0\n\
+            return \"method\"\n\
+            end\n\
+            return this.count(n - 1)\n\
+            end\n\
+            end\n\
+            let function = count(100000)\n\
+            let counter = Counter()\n\
+            let method = counter.count(100000)\n";
+
+        // far less than the calls would take if they were not tail calls
+        let mut interpreter = AstInterpreter::new();
+        interpreter.set_max_stack_memory(64 * 1024);
+        assert!(interpreter.interpret(generate_parsed_ast(source, "").unwrap()).is_ok());
+
+        let environment = interpreter.environment.read().unwrap();
+        let value = |name| environment.get_value(Symbol::intern(name)).read().unwrap().to_string();
+        assert_eq!(value("function"), "function");
+        assert_eq!(value("method"), "method");
+    }
+
+    #[test]
+    fn test_deep_expression_does_not_use_native_stack() {
+        let source = format!("let total = 1{}\nlet negated = {}1\n", " + 1".repeat(2000), "-".repeat(150));
+        let statements = generate_parsed_ast(&source, "").unwrap();
+
+        // evaluating the expressions by recursion would overflow this stack, the syntax tree is
+        // shared with the thread and dropped here
+        let shared = statements.clone();
+        let run = thread::Builder::new()
+            .stack_size(256 * 1024)
+            .spawn(move || {
+                let mut interpreter = AstInterpreter::new();
+                interpreter.set_prelude(false);
+                assert!(interpreter.interpret(shared).is_ok());
+
+                let environment = interpreter.environment.read().unwrap();
+                let value = |name| environment.get_value(Symbol::intern(name)).as_number();
+                assert_eq!(value("total"), Some(2001.0));
+                assert_eq!(value("negated"), Some(1.0));
+            })
+            .unwrap();
+
+        run.join().unwrap();
+        drop(statements);
+    }
+}
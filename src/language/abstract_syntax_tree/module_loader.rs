@@ -0,0 +1,164 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::language::errors;
+
+/// Finds and reads the files named by `include` and `import`, set on an interpreter with
+/// `AstInterpreter::set_module_loader`.
+pub trait ModuleLoader: Send + Sync {
+    /// the file `name` refers to when named in the file `from`, `from` is empty for code that
+    /// was not read from a file. Naming the same file twice must resolve to the same path, it
+    /// identifies modules and include cycles
+    fn resolve(&self, name: &Path, from: &Path) -> Result<PathBuf, errors::Error>;
+
+    /// the source of a file returned by `resolve`
+    fn load(&self, path: &Path) -> Result<String, errors::Error>;
+
+    /// whether resolved paths are files on disk, only those have their parsed syntax trees
+    /// saved next to them, see `ast_cache`
+    fn on_disk(&self) -> bool {
+        false
+    }
+}
+
+/// Reads files from disk, looking next to the file naming them and then in the search paths.
+#[derive(Debug, Clone, Default)]
+pub struct FileSystemLoader {
+    search_paths: Vec<PathBuf>,
+}
+
+impl FileSystemLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// adds a directory searched for files not found next to the file naming them, after the
+    /// ones added before
+    pub fn add_search_path(&mut self, directory: impl Into<PathBuf>) {
+        self.search_paths.push(directory.into());
+    }
+}
+
+impl ModuleLoader for FileSystemLoader {
+    fn resolve(&self, name: &Path, from: &Path) -> Result<PathBuf, errors::Error> {
+        let directory = from.parent().unwrap_or(Path::new(""));
+        let mut candidates = std::iter::once(directory.join(name))
+            .chain(self.search_paths.iter().map(|search_path| search_path.join(name)));
+
+        if let Some(file) = candidates.find(|candidate| candidate.is_file()) {
+            return fs::canonicalize(&file).map_err(|err| {
+                errors::Error::Runtime(format!("Error importing file: {}\n{}", file.display(), err))
+            });
+        }
+
+        let searched: Vec<String> = std::iter::once(directory)
+            .chain(self.search_paths.iter().map(PathBuf::as_path))
+            .map(|directory| match directory.as_os_str().is_empty() {
+                true => ".".to_string(),
+                false => directory.display().to_string(),
+            })
+            .collect();
+        Err(errors::Error::Runtime(format!(
+            "Cannot find file {}, searched: {}",
+            name.display(),
+            searched.join(", ")
+        )))
+    }
+
+    fn load(&self, path: &Path) -> Result<String, errors::Error> {
+        fs::read_to_string(path).map_err(|err| {
+            errors::Error::Runtime(format!("Error importing file: {}\n{}", path.display(), err))
+        })
+    }
+
+    fn on_disk(&self) -> bool {
+        true
+    }
+}
+
+/// Serves sources held in memory by path, for hosts bundling their libraries. Names are
+/// resolved next to the file naming them and then from the root, `.` and `..` are followed
+/// without touching the disk.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryLoader {
+    sources: HashMap<PathBuf, String>,
+}
+
+impl MemoryLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, path: impl AsRef<Path>, source: impl Into<String>) {
+        self.sources.insert(normalize(path.as_ref()), source.into());
+    }
+}
+
+impl ModuleLoader for MemoryLoader {
+    fn resolve(&self, name: &Path, from: &Path) -> Result<PathBuf, errors::Error> {
+        let directory = from.parent().unwrap_or(Path::new(""));
+
+        [normalize(&directory.join(name)), normalize(name)]
+            .into_iter()
+            .find(|candidate| self.sources.contains_key(candidate))
+            .ok_or_else(|| errors::Error::Runtime(format!("Cannot find file {}", name.display())))
+    }
+
+    fn load(&self, path: &Path) -> Result<String, errors::Error> {
+        self.sources
+            .get(path)
+            .cloned()
+            .ok_or_else(|| errors::Error::Runtime(format!("Cannot find file {}", path.display())))
+    }
+}
+
+/// `path` with `.` dropped and `..` applied to the component before it
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod test {
+    use super::MemoryLoader;
+    use crate::language::AstInterpreter;
+
+    #[test]
+    fn test_memory_loader_resolves_next_to_including_file() {
+        let mut loader = MemoryLoader::new();
+        loader.insert("main.nova", "include \"lib/tools.nova\"\nimport \"lib/math\" as math\n");
+        loader.insert("lib/tools.nova", "include \"../shared.nova\"\n");
+        loader.insert("lib/math.nova", "fn sum(a, b)\nreturn a + b\nend\n");
+        loader.insert("shared.nova", "let shared = 1\n");
+
+        let mut interpreter = AstInterpreter::new();
+        interpreter.set_module_loader(loader);
+        assert!(interpreter.load_file("main.nova").is_ok());
+        assert!(interpreter.load_file("missing.nova").is_err());
+    }
+
+    #[test]
+    fn test_memory_loader_include_cycle() {
+        let mut loader = MemoryLoader::new();
+        loader.insert("a.nova", "include \"b.nova\"\n");
+        loader.insert("b.nova", "include \"a.nova\"\n");
+
+        let mut interpreter = AstInterpreter::new();
+        interpreter.set_module_loader(loader);
+        let error = interpreter.load_file("a.nova").unwrap_err();
+        assert!(error.to_string().contains("Circular include: a.nova -> b.nova -> a.nova"));
+    }
+}
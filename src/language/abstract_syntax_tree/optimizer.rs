@@ -0,0 +1,336 @@
+use std::sync::Arc;
+
+use super::{
+    expression::{binary::Binary, grouping::Grouping, literal::Literal, unary::Unary, Expression},
+    interpreter::AstInterpreter,
+    statement::{function::FunctionStatement, IfStatement, Statement},
+};
+use crate::language::scanner::{object::Object, token::TokenType};
+
+/// expressions nested deeper than this are not optimized
+const MAX_DEPTH: usize = 128;
+
+/// Rewrites parsed statements into equivalent ones doing less work when run.
+///
+/// Arithmetic and comparisons of number literals and concatenation of string literals are
+/// folded into a single literal, `if` statements with a literal condition are replaced by the
+/// branch that runs, and statements after a `return`, `break` or `continue` are removed.
+/// Anything that could fail at run time, such as adding a string to a number, is left as it is
+/// so errors are reported the same way.
+pub fn optimize(statements: Vec<Statement>) -> Vec<Statement> {
+    optimize_statements(statements)
+}
+
+fn optimize_statements(statements: Vec<Statement>) -> Vec<Statement> {
+    let mut optimized = Vec::with_capacity(statements.len());
+
+    for statement in statements {
+        let statement = optimize_statement(statement);
+        let jumps = matches!(
+            statement,
+            Statement::ReturnStatement(_) | Statement::Break(_) | Statement::Continue(_)
+        );
+
+        if !matches!(statement, Statement::None) {
+            optimized.push(statement);
+        }
+
+        // nothing after a jump in the same block is reachable
+        if jumps {
+            break;
+        }
+    }
+
+    optimized
+}
+
+fn optimize_statement(statement: Statement) -> Statement {
+    match statement {
+        Statement::If(if_statement) => {
+            let IfStatement {
+                condition,
+                then_branch,
+                else_branch,
+                line,
+                filename,
+            } = Arc::unwrap_or_clone(if_statement);
+
+            let condition = optimize_expression(condition);
+            let then_branch = optimize_statement(then_branch);
+            let else_branch = else_branch.map(optimize_statement);
+
+            if let Expression::Literal(literal) = &condition {
+                return match literal.object.is_truthy() {
+                    true => then_branch,
+                    false => else_branch.unwrap_or(Statement::None),
+                };
+            }
+
+            Statement::If(Arc::new(IfStatement {
+                condition,
+                then_branch,
+                else_branch,
+                line,
+                filename,
+            }))
+        }
+        Statement::WhileLoop(while_loop) => {
+            let mut while_loop = Arc::unwrap_or_clone(while_loop);
+            while_loop.condition = optimize_expression(while_loop.condition);
+            while_loop.body = optimize_statement(while_loop.body);
+            Statement::WhileLoop(Arc::new(while_loop))
+        }
+        Statement::ForLoop(for_loop) => {
+            let mut for_loop = Arc::unwrap_or_clone(for_loop);
+            for_loop.iterable = optimize_expression(for_loop.iterable);
+            for_loop.body = optimize_statement(for_loop.body);
+            Statement::ForLoop(Arc::new(for_loop))
+        }
+        Statement::Block(mut block) => {
+            block.statements = optimize_statements(take_statements(&mut block.statements)).into();
+            Statement::Block(block)
+        }
+        Statement::FunctionStatement(function) => Statement::FunctionStatement(optimize_function(function)),
+        Statement::ReturnStatement(value) => Statement::ReturnStatement(value.map(optimize_located)),
+        Statement::Yield(value) => Statement::Yield(value.map(optimize_located)),
+        Statement::VariableDeclaration(mut declaration) => {
+            declaration.initializer = declaration.initializer.map(optimize_expression);
+            Statement::VariableDeclaration(declaration)
+        }
+        Statement::TupleDeclaration(declaration) => {
+            let mut declaration = Arc::unwrap_or_clone(declaration);
+            declaration.initializer = optimize_expression(declaration.initializer);
+            Statement::TupleDeclaration(Arc::new(declaration))
+        }
+        Statement::ExpressionStatement(expression) => {
+            Statement::ExpressionStatement(optimize_located(expression))
+        }
+        Statement::ClassStatement(mut class_statement) => {
+            class_statement.methods = class_statement.methods.into_iter().map(optimize_function).collect();
+            Statement::ClassStatement(class_statement)
+        }
+        Statement::Match(match_statement) => {
+            let mut match_statement = Arc::unwrap_or_clone(match_statement);
+            match_statement.value = optimize_expression(match_statement.value);
+            for arm in &mut match_statement.arms {
+                arm.guard = arm.guard.take().map(optimize_expression);
+                arm.body.statements = optimize_statements(take_statements(&mut arm.body.statements)).into();
+            }
+            Statement::Match(Arc::new(match_statement))
+        }
+        statement @ (Statement::None
+        | Statement::Break(_)
+        | Statement::Continue(_)
+        | Statement::EnumStatement(_)
+        | Statement::Include(_)
+        | Statement::Import(_)) => statement,
+    }
+}
+
+fn optimize_function(function: Arc<FunctionStatement>) -> Arc<FunctionStatement> {
+    let mut function = Arc::unwrap_or_clone(function);
+
+    for parameter in &mut function.parameters {
+        parameter.default = parameter.default.take().map(optimize_expression);
+    }
+    function.body.statements = optimize_statements(take_statements(&mut function.body.statements)).into();
+
+    Arc::new(function)
+}
+
+/// moves the statements of a block into a list, leaving the block empty so the nodes are no
+/// longer shared and can be taken apart without copying
+fn take_statements(statements: &mut Arc<[Statement]>) -> Vec<Statement> {
+    std::mem::replace(statements, Arc::from([])).to_vec()
+}
+
+/// an expression stored along with its line and file
+fn optimize_located((expression, line, filename): (Expression, usize, String)) -> (Expression, usize, String) {
+    (optimize_expression(expression), line, filename)
+}
+
+fn optimize_expression(expression: Expression) -> Expression {
+    optimize_nested(expression, 0)
+}
+
+/// optimizes an expression `depth` levels inside the one being optimized, optimizing recurses
+/// so deeper subtrees are left as they are
+fn optimize_nested(expression: Expression, depth: usize) -> Expression {
+    if depth >= MAX_DEPTH {
+        return expression;
+    }
+
+    let depth = depth + 1;
+    match expression {
+        Expression::Binary(binary) => {
+            let Binary {
+                left,
+                right,
+                operator,
+            } = Arc::unwrap_or_clone(binary);
+            let (left, right) = (optimize_nested(left, depth), optimize_nested(right, depth));
+
+            if let (Expression::Literal(left), Expression::Literal(right)) = (&left, &right) {
+                if let Some(object) = fold_binary(&operator.token_type, &left.object, &right.object) {
+                    return Expression::Literal(Arc::new(Literal::new(object)));
+                }
+            }
+
+            Expression::Binary(Arc::new(Binary::new(left, right, operator)))
+        }
+        Expression::Unary(unary) => {
+            let Unary { operator, right } = Arc::unwrap_or_clone(unary);
+            let right = optimize_nested(right, depth);
+
+            if let (TokenType::Minus, Expression::Literal(literal)) = (&operator.token_type, &right) {
+                if let Object::Number(number) = literal.object {
+                    return Expression::Literal(Arc::new(Literal::new(Object::Number(-number))));
+                }
+            }
+
+            Expression::Unary(Arc::new(Unary::new(right, operator)))
+        }
+        Expression::Grouping(grouping) => match optimize_nested(Arc::unwrap_or_clone(grouping).expression, depth) {
+            literal @ Expression::Literal(_) => literal,
+            expression => Expression::Grouping(Arc::new(Grouping::new(expression))),
+        },
+        Expression::Call(call) => {
+            let mut call = Arc::unwrap_or_clone(call);
+            call.callee = optimize_nested(call.callee, depth);
+            call.arguments = optimize_expressions(call.arguments, depth);
+            call.keyword_arguments = optimize_keyword_arguments(call.keyword_arguments, depth);
+            Expression::Call(Arc::new(call))
+        }
+        Expression::Get(get) => {
+            let mut get = Arc::unwrap_or_clone(get);
+            get.object = optimize_nested(get.object, depth);
+            get.arguments = get.arguments.map(|arguments| optimize_expressions(arguments, depth));
+            get.keyword_arguments = optimize_keyword_arguments(get.keyword_arguments, depth);
+            Expression::Get(Arc::new(get))
+        }
+        Expression::Set(set) => {
+            let mut set = Arc::unwrap_or_clone(set);
+            set.object = optimize_nested(set.object, depth);
+            set.value = optimize_nested(set.value, depth);
+            Expression::Set(Arc::new(set))
+        }
+        Expression::Assign(assign) => {
+            let mut assign = Arc::unwrap_or_clone(assign);
+            assign.value = optimize_nested(assign.value, depth);
+            Expression::Assign(Arc::new(assign))
+        }
+        Expression::Tuple(tuple) => {
+            let mut tuple = Arc::unwrap_or_clone(tuple);
+            tuple.elements = optimize_expressions(tuple.elements, depth);
+            Expression::Tuple(Arc::new(tuple))
+        }
+        Expression::DestructuringAssign(assign) => {
+            let mut assign = Arc::unwrap_or_clone(assign);
+            assign.value = optimize_nested(assign.value, depth);
+            Expression::DestructuringAssign(Arc::new(assign))
+        }
+        Expression::Await(await_expression) => {
+            let mut await_expression = Arc::unwrap_or_clone(await_expression);
+            await_expression.value = optimize_nested(await_expression.value, depth);
+            Expression::Await(Arc::new(await_expression))
+        }
+        expression @ (Expression::Literal(_) | Expression::Variable(_)) => expression,
+    }
+}
+
+fn optimize_expressions(expressions: Vec<Expression>, depth: usize) -> Vec<Expression> {
+    expressions.into_iter().map(|expression| optimize_nested(expression, depth)).collect()
+}
+
+fn optimize_keyword_arguments<T>(arguments: Vec<(T, Expression)>, depth: usize) -> Vec<(T, Expression)> {
+    arguments
+        .into_iter()
+        .map(|(name, value)| (name, optimize_nested(value, depth)))
+        .collect()
+}
+
+/// the literal a binary operation on two literals evaluates to, none when it is not known to
+/// succeed
+fn fold_binary(operator: &TokenType, left: &Object, right: &Object) -> Option<Object> {
+    match (left, right) {
+        (Object::Number(left), Object::Number(right)) => {
+            AstInterpreter::number_operation(operator, *left, *right).map(|value| value.to_object())
+        }
+        (Object::String(left), Object::String(right)) if *operator == TokenType::Plus => {
+            Some(Object::String(format!("{}{}", left, right)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+
+    use super::optimize;
+    use crate::language::{generate_parsed_ast, Expression, Object, Statement};
+
+    fn optimized(source: &str) -> Vec<Statement> {
+        optimize(generate_parsed_ast(source, "").unwrap())
+    }
+
+    fn initializer(statement: &Statement) -> &Expression {
+        match statement {
+            Statement::VariableDeclaration(declaration) => declaration.initializer.as_ref().unwrap(),
+            statement => panic!("expected a declaration, found {:?}", statement),
+        }
+    }
+
+    #[test]
+    fn test_optimizer_folds_constants() {
+        let statements = optimized("let x = (2 + 3) * -4\nlet s = \"no\" + \"va\"\nlet y = x * 2\n");
+
+        assert!(matches!(
+            initializer(&statements[0]),
+            Expression::Literal(literal) if literal.object == Object::Number(-20.0)
+        ));
+        assert!(matches!(
+            initializer(&statements[1]),
+            Expression::Literal(literal) if literal.object == Object::String("nova".to_string())
+        ));
+        assert!(matches!(initializer(&statements[2]), Expression::Binary(_)));
+
+        // left for the interpreter to report
+        let statements = optimized("let x = \"a\" - 1\n");
+        assert!(matches!(initializer(&statements[0]), Expression::Binary(_)));
+    }
+
+    #[test]
+    fn test_optimizer_removes_dead_code() {
+        let statements = optimized("if false\nprintln(1)\nend\nif 1 < 2\nprintln(2)\nelse\nprintln(3)\nend\n");
+        assert_eq!(statements.len(), 1);
+        assert!(matches!(statements[0], Statement::Block(_)));
+
+        let statements = optimized("fn f()\nreturn 1\nprintln(2)\nend\n");
+        let Statement::FunctionStatement(function) = &statements[0] else {
+            panic!("expected a function");
+        };
+        assert_eq!(function.body.statements.len(), 1);
+    }
+
+    #[test]
+    fn test_optimizer_leaves_deep_expressions() {
+        let shallow = optimized(&format!("let x = 1{}\n", " + 1".repeat(100)));
+        assert!(matches!(
+            initializer(&shallow[0]),
+            Expression::Literal(literal) if literal.object == Object::Number(101.0)
+        ));
+
+        // optimizing the whole expression by recursion would overflow this stack, the syntax
+        // tree is shared with the thread and dropped here
+        let statements = generate_parsed_ast(&format!("let x = 1{}\n", " + 1".repeat(2000)), "").unwrap();
+        let shared = statements.clone();
+        let optimize = thread::Builder::new()
+            .stack_size(1024 * 1024)
+            .spawn(move || matches!(initializer(&optimize(shared)[0]), Expression::Binary(_)))
+            .unwrap();
+
+        assert!(optimize.join().unwrap());
+        drop(statements);
+    }
+}
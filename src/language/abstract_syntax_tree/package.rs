@@ -0,0 +1,720 @@
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::language::{errors, FileSystemLoader, ModuleLoader};
+
+/// the file describing a project, in the project's directory
+pub const MANIFEST_FILE: &str = "nova.toml";
+
+/// the file recording how a project's dependencies were resolved, next to the manifest
+pub const LOCK_FILE: &str = "nova.lock";
+
+/// the entry point of packages whose manifest names none
+const DEFAULT_ENTRY: &str = "main.nova";
+
+/// The contents of a `nova.toml`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Manifest {
+    pub name: String,
+    pub version: String,
+    /// the file run for the package, relative to its directory
+    pub entry: String,
+    pub dependencies: Vec<Dependency>,
+}
+
+/// a package the project uses, found in a directory of its own
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dependency {
+    pub name: String,
+    /// the package's directory, relative to the directory of the manifest naming it
+    pub path: PathBuf,
+    /// the version the package must have, any when left out
+    pub version: Option<String>,
+}
+
+/// a value of the manifest format, a subset of TOML
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    String(String),
+    /// an inline table of strings, `{ path = "../lib" }`
+    Table(Vec<(String, String)>),
+}
+
+/// The contents of a `nova.lock`, as written by `Resolution::lockfile`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lockfile {
+    /// the project's own package first, then its dependencies sorted by name
+    pub packages: Vec<LockedPackage>,
+}
+
+/// a package recorded in the lockfile
+#[derive(Debug, Clone, PartialEq)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: String,
+    /// the package's directory, relative to the lockfile
+    pub path: PathBuf,
+    /// the names of the packages it depends on
+    pub dependencies: Vec<String>,
+}
+
+impl Manifest {
+    /// reads the manifest of the project in `directory`
+    pub fn read(directory: &Path) -> Result<Self, errors::Error> {
+        let path = directory.join(MANIFEST_FILE);
+        let source = fs::read_to_string(&path).map_err(|err| {
+            errors::Error::Runtime(format!("Error reading {}\n{}", path.display(), err))
+        })?;
+
+        Self::parse(&source)
+            .map_err(|err| errors::Error::Runtime(format!("Error reading {}\n{}", path.display(), err)))
+    }
+
+    pub fn parse(source: &str) -> Result<Self, errors::Error> {
+        let mut name = None;
+        let mut version = None;
+        let mut entry = None;
+        let mut dependencies = Vec::new();
+        let mut section = String::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let error = |message: String| errors::Error::Runtime(format!("line {}: {}", index + 1, message));
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(header) = line.strip_prefix('[') {
+                section = header
+                    .strip_suffix(']')
+                    .ok_or_else(|| error("expect ']' after section name".to_string()))?
+                    .trim()
+                    .to_string();
+                if section != "package" && section != "dependencies" {
+                    return Err(error(format!("unknown section [{}]", section)));
+                }
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error("expect 'key = value'".to_string()))?;
+            let key = key.trim().to_string();
+            let value = parse_value(value.trim()).map_err(error)?;
+
+            match (section.as_str(), value) {
+                ("package", Value::String(value)) => match key.as_str() {
+                    "name" => name = Some(value),
+                    "version" => version = Some(value),
+                    "entry" => entry = Some(value),
+                    _ => return Err(error(format!("unknown package key {}", key))),
+                },
+                ("dependencies", Value::String(path)) => dependencies.push(Dependency {
+                    name: key,
+                    path: PathBuf::from(path),
+                    version: None,
+                }),
+                ("dependencies", Value::Table(fields)) => {
+                    let mut path = None;
+                    let mut version = None;
+                    for (field, value) in fields {
+                        match field.as_str() {
+                            "path" => path = Some(PathBuf::from(value)),
+                            "version" => version = Some(value),
+                            _ => return Err(error(format!("unknown key {} for dependency {}", field, key))),
+                        }
+                    }
+
+                    let path = path.ok_or_else(|| error(format!("dependency {} needs a path", key)))?;
+                    dependencies.push(Dependency { name: key, path, version });
+                }
+                ("", _) => return Err(error(format!("{} is outside of a section", key))),
+                (_, Value::Table(_)) => return Err(error(format!("expect a string for {}", key))),
+                _ => unreachable!("unknown sections are rejected at their header"),
+            }
+        }
+
+        let missing = |key: &str| errors::Error::Runtime(format!("[package] needs a {}", key));
+        Ok(Self {
+            name: name.ok_or_else(|| missing("name"))?,
+            version: version.ok_or_else(|| missing("version"))?,
+            entry: entry.unwrap_or_else(|| DEFAULT_ENTRY.to_string()),
+            dependencies,
+        })
+    }
+}
+
+/// the line up to a `#` that is not inside a string
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (index, character) in line.char_indices() {
+        match character {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// the items of an inline table or array, split on the commas that are not inside a string
+fn split_items(items: &str) -> Vec<&str> {
+    let mut split = Vec::new();
+    let mut start = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (index, character) in items.char_indices() {
+        match character {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            ',' if !in_string => {
+                split.push(&items[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    split.push(&items[start..]);
+
+    split.into_iter().filter(|item| !item.trim().is_empty()).collect()
+}
+
+impl Lockfile {
+    /// reads the lockfile of the project in `directory`, none when there is none yet
+    pub fn read(directory: &Path) -> Result<Option<Self>, errors::Error> {
+        let path = directory.join(LOCK_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|source| Self::parse(&source).map_err(|err| err.to_string()))
+            .map(Some)
+            .map_err(|err| errors::Error::Runtime(format!("Error reading {}\n{}", path.display(), err)))
+    }
+
+    pub fn parse(source: &str) -> Result<Self, errors::Error> {
+        let mut packages: Vec<LockedPackage> = Vec::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let error = |message: String| errors::Error::Runtime(format!("line {}: {}", index + 1, message));
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if line == "[[package]]" {
+                packages.push(LockedPackage {
+                    name: String::new(),
+                    version: String::new(),
+                    path: PathBuf::new(),
+                    dependencies: Vec::new(),
+                });
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error("expect 'key = value'".to_string()))?;
+            let (key, value) = (key.trim(), value.trim());
+            let package = packages
+                .last_mut()
+                .ok_or_else(|| error(format!("{} is outside of a [[package]]", key)))?;
+
+            match key {
+                "name" => package.name = parse_string(value).map_err(error)?,
+                "version" => package.version = parse_string(value).map_err(error)?,
+                "path" => package.path = PathBuf::from(parse_string(value).map_err(error)?),
+                "dependencies" => package.dependencies = parse_array(value).map_err(error)?,
+                _ => return Err(error(format!("unknown package key {}", key))),
+            }
+        }
+
+        if let Some(package) = packages.iter().find(|package| package.name.is_empty()) {
+            return Err(errors::Error::Runtime(format!(
+                "[[package]] at {} needs a name",
+                package.path.display()
+            )));
+        }
+        Ok(Self { packages })
+    }
+}
+
+fn parse_value(value: &str) -> Result<Value, String> {
+    if let Some(fields) = value.strip_prefix('{') {
+        let fields = fields.strip_suffix('}').ok_or("expect '}' after inline table")?;
+
+        return split_items(fields)
+            .into_iter()
+            .map(|field| {
+                let (key, value) = field.split_once('=').ok_or("expect 'key = value' in inline table")?;
+                Ok((key.trim().to_string(), parse_string(value.trim())?))
+            })
+            .collect::<Result<_, String>>()
+            .map(Value::Table);
+    }
+
+    parse_string(value).map(Value::String)
+}
+
+/// an array of strings, `["mathlib", "vector"]`
+fn parse_array(value: &str) -> Result<Vec<String>, String> {
+    let items = value
+        .strip_prefix('[')
+        .and_then(|items| items.strip_suffix(']'))
+        .ok_or("expect an array in square brackets")?;
+
+    split_items(items)
+        .into_iter()
+        .map(|item| parse_string(item.trim()))
+        .collect()
+}
+
+fn parse_string(value: &str) -> Result<String, String> {
+    let contents = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or_else(|| format!("expect a string in double quotes, found {}", value))?;
+
+    let mut string = String::new();
+    let mut characters = contents.chars();
+    while let Some(character) = characters.next() {
+        if character != '\\' {
+            string.push(character);
+            continue;
+        }
+
+        match characters.next() {
+            Some('"') => string.push('"'),
+            Some('\\') => string.push('\\'),
+            Some('n') => string.push('\n'),
+            Some('t') => string.push('\t'),
+            other => return Err(format!("unknown escape \\{}", other.map_or(String::new(), String::from))),
+        }
+    }
+    Ok(string)
+}
+
+/// a package of a project's dependency graph and the directory it was found in
+#[derive(Debug, Clone, PartialEq)]
+pub struct Package {
+    pub manifest: Manifest,
+    pub directory: PathBuf,
+}
+
+/// The packages of a project, read from its manifest and those of its dependencies.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resolution {
+    pub root: Package,
+    /// every package the root depends on, directly or not, in the order they were found
+    pub dependencies: Vec<Package>,
+}
+
+impl Resolution {
+    /// reads the manifest in `directory` and those of every package it depends on, failing on
+    /// dependency cycles, two packages with the same name and versions that do not match
+    pub fn resolve(directory: &Path) -> Result<Self, errors::Error> {
+        let directory = canonical(directory)?;
+        let root = Package {
+            manifest: Manifest::read(&directory)?,
+            directory,
+        };
+
+        let mut resolution = Self {
+            root: root.clone(),
+            dependencies: Vec::new(),
+        };
+        resolution.visit(&root, &mut vec![root.manifest.name.clone()])?;
+
+        Ok(resolution)
+    }
+
+    /// adds the dependencies of `package` not seen yet, `chain` holds the packages leading to it
+    fn visit(&mut self, package: &Package, chain: &mut Vec<String>) -> Result<(), errors::Error> {
+        for dependency in &package.manifest.dependencies {
+            let directory = canonical(&package.directory.join(&dependency.path))?;
+            let manifest = Manifest::read(&directory)?;
+
+            if manifest.name != dependency.name {
+                return Err(errors::Error::Runtime(format!(
+                    "{} depends on {} but {} holds package {}",
+                    package.manifest.name,
+                    dependency.name,
+                    directory.display(),
+                    manifest.name
+                )));
+            }
+            if let Some(version) = dependency.version.as_ref().filter(|version| **version != manifest.version) {
+                return Err(errors::Error::Runtime(format!(
+                    "{} needs {} version {}, found {}",
+                    package.manifest.name, dependency.name, version, manifest.version
+                )));
+            }
+
+            if let Some(start) = chain.iter().position(|name| *name == dependency.name) {
+                let cycle = chain[start..].join(" -> ");
+                return Err(errors::Error::Runtime(format!("Dependency cycle: {} -> {}", cycle, dependency.name)));
+            }
+
+            let existing = std::iter::once(&self.root)
+                .chain(&self.dependencies)
+                .find(|existing| existing.manifest.name == dependency.name);
+            match existing {
+                Some(existing) if existing.directory == directory => continue,
+                Some(existing) => {
+                    return Err(errors::Error::Runtime(format!(
+                        "Two packages are named {}: {} and {}",
+                        dependency.name,
+                        existing.directory.display(),
+                        directory.display()
+                    )))
+                }
+                None => {}
+            }
+
+            let dependency = Package { manifest, directory };
+            self.dependencies.push(dependency.clone());
+
+            chain.push(dependency.manifest.name.clone());
+            self.visit(&dependency, chain)?;
+            chain.pop();
+        }
+
+        Ok(())
+    }
+
+    /// the file run for the project
+    pub fn entry(&self) -> PathBuf {
+        self.root.directory.join(&self.root.manifest.entry)
+    }
+
+    /// a loader finding `<package>/<path>` in the packages a file's own package depends on,
+    /// other names are left to `files`
+    pub fn loader(&self, files: FileSystemLoader) -> PackageLoader {
+        PackageLoader {
+            packages: std::iter::once(&self.root).chain(&self.dependencies).cloned().collect(),
+            files,
+        }
+    }
+
+    /// fails when a package recorded in `lockfile` was resolved to another version or
+    /// directory, packages added to or removed from the graph since are not an error
+    pub fn check_lockfile(&self, lockfile: &Lockfile) -> Result<(), errors::Error> {
+        for package in &self.dependencies {
+            let Some(locked) = lockfile.packages.iter().find(|locked| locked.name == package.manifest.name) else {
+                continue;
+            };
+
+            let path = relative_path(&package.directory, &self.root.directory);
+            if locked.version != package.manifest.version {
+                return Err(errors::Error::Runtime(format!(
+                    "{} locks {} at version {} but {} holds version {}",
+                    LOCK_FILE,
+                    locked.name,
+                    locked.version,
+                    path.display(),
+                    package.manifest.version
+                )));
+            }
+            if locked.path != path {
+                return Err(errors::Error::Runtime(format!(
+                    "{} locks {} at {} but it was found at {}",
+                    LOCK_FILE,
+                    locked.name,
+                    locked.path.display(),
+                    path.display()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// the contents of the lockfile, packages are sorted by name so it only changes with the graph
+    pub fn lockfile(&self) -> String {
+        let mut dependencies: Vec<&Package> = self.dependencies.iter().collect();
+        dependencies.sort_by(|first, second| first.manifest.name.cmp(&second.manifest.name));
+
+        let mut lockfile = format!("# written by nova_tw from {}, do not edit\n", MANIFEST_FILE);
+        for package in std::iter::once(&self.root).chain(dependencies) {
+            let path = relative_path(&package.directory, &self.root.directory);
+            let mut requires: Vec<String> = package
+                .manifest
+                .dependencies
+                .iter()
+                .map(|dependency| quote(&dependency.name))
+                .collect();
+            requires.sort();
+
+            lockfile.push_str(&format!(
+                "\n[[package]]\nname = {}\nversion = {}\npath = {}\ndependencies = [{}]\n",
+                quote(&package.manifest.name),
+                quote(&package.manifest.version),
+                quote(&path.to_string_lossy()),
+                requires.join(", ")
+            ));
+        }
+        lockfile
+    }
+
+    /// checks the lockfile next to the manifest, if there is one, unless `update` is set and
+    /// then writes it, returns whether it changed
+    pub fn lock(&self, update: bool) -> Result<bool, errors::Error> {
+        if !update {
+            if let Some(lockfile) = Lockfile::read(&self.root.directory)? {
+                self.check_lockfile(&lockfile)?;
+            }
+        }
+        self.write_lockfile()
+    }
+
+    /// writes the lockfile next to the manifest unless it is up to date, returns whether it was
+    pub fn write_lockfile(&self) -> Result<bool, errors::Error> {
+        let path = self.root.directory.join(LOCK_FILE);
+        let lockfile = self.lockfile();
+        if fs::read_to_string(&path).is_ok_and(|existing| existing == lockfile) {
+            return Ok(false);
+        }
+
+        fs::write(&path, lockfile).map_err(|err| {
+            errors::Error::Runtime(format!("Error writing {}\n{}", path.display(), err))
+        })?;
+        Ok(true)
+    }
+}
+
+/// Resolves `<package>/<path>` to a file of a package, and a bare package name to its entry.
+/// Files only see the packages their own package depends on, and itself. Names relative to
+/// the naming file come first and names that are not packages go to a `FileSystemLoader`.
+#[derive(Debug, Clone)]
+pub struct PackageLoader {
+    /// the project's own package first
+    packages: Vec<Package>,
+    files: FileSystemLoader,
+}
+
+impl PackageLoader {
+    /// the package holding `file`, the innermost one when packages are nested, code not read
+    /// from a file belongs to the project
+    fn package_of(&self, file: &Path) -> Option<&Package> {
+        if file.as_os_str().is_empty() {
+            return self.packages.first();
+        }
+
+        self.packages
+            .iter()
+            .filter(|package| file.starts_with(&package.directory))
+            .max_by_key(|package| package.directory.components().count())
+    }
+
+    /// the package `name` starts with and the file named inside of it, the entry for a bare name
+    fn split<'a>(&'a self, name: &Path) -> Option<(&'a Package, PathBuf)> {
+        let mut components = name.components();
+        let first = components.next()?.as_os_str();
+        let rest = components.as_path();
+
+        if rest.as_os_str().is_empty() {
+            // imports name modules without the extension, which is added before resolving
+            let package_name = match name.extension().is_some_and(|extension| extension == "nova") {
+                true => name.file_stem()?,
+                false => first,
+            };
+            let package = self.packages.iter().find(|package| *package_name == *package.manifest.name)?;
+            return Some((package, PathBuf::from(&package.manifest.entry)));
+        }
+
+        let package = self.packages.iter().find(|package| *first == *package.manifest.name)?;
+        Some((package, rest.to_path_buf()))
+    }
+}
+
+impl ModuleLoader for PackageLoader {
+    fn resolve(&self, name: &Path, from: &Path) -> Result<PathBuf, errors::Error> {
+        let next_to = from.parent().unwrap_or(Path::new("")).join(name);
+        let Some((package, path)) = self.split(name).filter(|_| !next_to.is_file()) else {
+            return self.files.resolve(name, from);
+        };
+
+        if let Some(current) = self.package_of(from) {
+            let visible = current.manifest.name == package.manifest.name
+                || current.manifest.dependencies.iter().any(|dependency| dependency.name == package.manifest.name);
+            if !visible {
+                return Err(errors::Error::Runtime(format!(
+                    "Cannot use package {} from {}, it is not a dependency of {}",
+                    package.manifest.name,
+                    from.display(),
+                    current.manifest.name
+                )));
+            }
+        }
+
+        let file = package.directory.join(&path);
+        fs::canonicalize(&file).map_err(|err| {
+            errors::Error::Runtime(format!(
+                "Cannot find file {} in package {}\n{}",
+                path.display(),
+                package.manifest.name,
+                err
+            ))
+        })
+    }
+
+    fn load(&self, path: &Path) -> Result<String, errors::Error> {
+        self.files.load(path)
+    }
+
+    fn on_disk(&self) -> bool {
+        true
+    }
+}
+
+fn canonical(directory: &Path) -> Result<PathBuf, errors::Error> {
+    fs::canonicalize(directory).map_err(|err| {
+        errors::Error::Runtime(format!("Cannot find package directory {}\n{}", directory.display(), err))
+    })
+}
+
+/// `path` as seen from `base`, both absolute
+fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let path: Vec<Component> = path.components().collect();
+    let base: Vec<Component> = base.components().collect();
+    let shared = path.iter().zip(&base).take_while(|(first, second)| first == second).count();
+
+    let relative: PathBuf = std::iter::repeat_n(Component::ParentDir, base.len() - shared)
+        .chain(path[shared..].iter().copied())
+        .collect();
+
+    match relative.as_os_str().is_empty() {
+        true => PathBuf::from("."),
+        false => relative,
+    }
+}
+
+fn quote(string: &str) -> String {
+    format!("\"{}\"", string.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::{Dependency, Lockfile, Manifest, Package, Resolution};
+
+    #[test]
+    fn test_manifest_parse() {
+        let source = "# shared helpers\n[package]\nname = \"app\"\nversion = \"1.2.0\" # bumped\n\n[dependencies]\nmathlib = \"../mathlib\"\nstrings = { path = \"vendor/strings\", version = \"0.3.1\" }\n";
+        let manifest = Manifest::parse(source).unwrap();
+
+        assert_eq!(manifest.name, "app");
+        assert_eq!(manifest.version, "1.2.0");
+        assert_eq!(manifest.entry, "main.nova");
+        assert_eq!(
+            manifest.dependencies,
+            vec![
+                Dependency {
+                    name: "mathlib".to_string(),
+                    path: PathBuf::from("../mathlib"),
+                    version: None,
+                },
+                Dependency {
+                    name: "strings".to_string(),
+                    path: PathBuf::from("vendor/strings"),
+                    version: Some("0.3.1".to_string()),
+                },
+            ]
+        );
+
+        let source = "[package]\nname = \"app\"\nversion = \"1\"\n[dependencies]\nlib = { path = \"vendor/a,b\", version = \"2\" }\n";
+        assert_eq!(Manifest::parse(source).unwrap().dependencies[0].path, PathBuf::from("vendor/a,b"));
+
+        assert!(Manifest::parse("[package]\nname = \"app\"\n").is_err());
+        assert!(Manifest::parse("[package]\nname = \"app\"\nversion = \"1\"\nedition = \"2\"\n").is_err());
+        assert!(Manifest::parse("[package]\nname = \"app\"\nversion = \"1\"\n[dependencies]\nlib = { git = \"url\" }\n").is_err());
+    }
+
+    #[test]
+    fn test_lockfile() {
+        let package = |name: &str, directory: &str, dependencies: Vec<Dependency>| Package {
+            manifest: Manifest {
+                name: name.to_string(),
+                version: "0.1.0".to_string(),
+                entry: "main.nova".to_string(),
+                dependencies,
+            },
+            directory: PathBuf::from(directory),
+        };
+        let dependency = |name: &str| Dependency {
+            name: name.to_string(),
+            path: PathBuf::from(format!("../{}", name)),
+            version: None,
+        };
+
+        let resolution = Resolution {
+            root: package("app", "/work/app", vec![dependency("vector"), dependency("mathlib")]),
+            dependencies: vec![
+                package("vector", "/work/vector", vec![dependency("mathlib")]),
+                package("mathlib", "/work/app/vendor/mathlib", Vec::new()),
+            ],
+        };
+
+        let lockfile = resolution.lockfile();
+        assert!(Lockfile::parse(&lockfile).unwrap().packages[0].dependencies == ["mathlib", "vector"]);
+        assert!(lockfile.contains("name = \"app\"\nversion = \"0.1.0\"\npath = \".\"\ndependencies = [\"mathlib\", \"vector\"]"));
+        assert!(lockfile.find("\"mathlib\"\nversion").unwrap() < lockfile.find("\"vector\"\nversion").unwrap());
+        assert!(lockfile.contains("path = \"vendor/mathlib\""));
+        assert!(lockfile.contains("path = \"../vector\""));
+    }
+
+    #[test]
+    fn test_lockfile_check() {
+        let source = "# written by nova_tw from nova.toml, do not edit\n\n[[package]]\nname = \"app\"\nversion = \"0.1.0\"\npath = \".\"\ndependencies = [\"mathlib\"]\n\n[[package]]\nname = \"mathlib\"\nversion = \"1.0.0\"\npath = \"../mathlib\"\ndependencies = []\n";
+        let lockfile = Lockfile::parse(source).unwrap();
+        assert_eq!(lockfile.packages.len(), 2);
+        assert_eq!(lockfile.packages[1].path, PathBuf::from("../mathlib"));
+        assert!(lockfile.packages[1].dependencies.is_empty());
+        let quoted = Lockfile::parse("[[package]]\nname = \"app\"\ndependencies = [\"a,b\", \"c\\\",d\"]\n").unwrap();
+        assert_eq!(quoted.packages[0].dependencies, ["a,b", "c\",d"]);
+        assert!(Lockfile::parse("name = \"app\"\n").is_err());
+        assert!(Lockfile::parse("[[package]]\nversion = \"1\"\n").is_err());
+
+        let package = |name: &str, version: &str, directory: &str| Package {
+            manifest: Manifest {
+                name: name.to_string(),
+                version: version.to_string(),
+                entry: "main.nova".to_string(),
+                dependencies: Vec::new(),
+            },
+            directory: PathBuf::from(directory),
+        };
+        let resolution = |dependencies: Vec<Package>| Resolution {
+            root: package("app", "0.2.0", "/work/app"),
+            dependencies,
+        };
+
+        let locked = resolution(vec![package("mathlib", "1.0.0", "/work/mathlib")]);
+        assert!(locked.check_lockfile(&lockfile).is_ok());
+
+        let added = resolution(vec![
+            package("mathlib", "1.0.0", "/work/mathlib"),
+            package("vector", "0.1.0", "/work/vector"),
+        ]);
+        assert!(added.check_lockfile(&lockfile).is_ok());
+
+        let bumped = resolution(vec![package("mathlib", "1.1.0", "/work/mathlib")]);
+        let error = bumped.check_lockfile(&lockfile).unwrap_err().to_string();
+        assert!(error.contains("nova.lock locks mathlib at version 1.0.0 but ../mathlib holds version 1.1.0"), "{}", error);
+
+        let moved = resolution(vec![package("mathlib", "1.0.0", "/work/app/vendor/mathlib")]);
+        let error = moved.check_lockfile(&lockfile).unwrap_err().to_string();
+        assert!(error.contains("nova.lock locks mathlib at ../mathlib but it was found at vendor/mathlib"), "{}", error);
+    }
+}
@@ -1,22 +1,31 @@
+use std::{collections::HashMap, sync::Arc};
+
 use crate::language::{
     class::ClassStatement,
+    enum_statement::{EnumStatement, VariantDeclaration},
     errors,
     scanner::{
         object::Object,
+        symbol::Symbol,
         token::{Token, TokenType}, TokenContainer,
-    }, Include,
+    }, Import, ImportBinding, Include,
+    match_statement::{MatchArm, MatchStatement, Pattern},
 };
 
 use super::{
     expression::{
-        binary::Binary, call::Call, grouping::Grouping, literal::Literal, unary::Unary,
-        variable::Variable, Expression,
+        await_expression::Await,
+        binary::Binary,
+        call::{Call, KeywordArgument},
+        grouping::Grouping, literal::Literal, tuple::Tuple,
+        unary::Unary, variable::Variable, Expression,
     },
+    inline_cache::InlineCache,
     statement::{
-        assignment::{Assign, Get, Set},
-        declaration::VariableDeclaration,
-        function::FunctionStatement,
-        Block, IfStatement, Statement, WhileLoop,
+        assignment::{Assign, DestructuringAssign, Get, Set},
+        declaration::{TupleDeclaration, VariableDeclaration},
+        function::{FunctionStatement, Parameter},
+        Block, ForLoop, IfStatement, Statement, WhileLoop,
     },
 };
 
@@ -26,9 +35,35 @@ pub struct AstParser {
     filename: String,
     current: usize,
     error_occurred: bool,
+    /// names declared in each enclosing scope, flagged true when constant
+    scopes: Vec<HashMap<Symbol, bool>>,
+    /// one entry per enclosing function, set once the function body yields
+    generators: Vec<bool>,
+    /// one entry per enclosing function, true for async functions
+    asynchronous: Vec<bool>,
+    /// keywords of the awaits in the statements being parsed, each statement takes back the one
+    /// it suspends at
+    awaits: Vec<Token>,
+    /// loops enclosing the statement being parsed, reset inside function bodies
+    loop_depth: usize,
+    /// expressions and blocks enclosing the one being parsed
+    nesting: usize,
+    /// set once nesting got too deep, the rest of the source is skipped and the enclosing
+    /// blocks failing with it are not reported
+    too_deep: bool,
+    /// errors printed so far
+    reported: usize,
+    /// binary operators in the statement being parsed, the syntax tree of a statement is at
+    /// most this many levels deeper than its nesting
+    operators: usize,
 }
 
 const MAX_PARAMETERS: usize = 8;
+/// deeper nesting is rejected before parsing or evaluating it could exhaust the native stack
+const MAX_NESTING_DEPTH: usize = 200;
+/// chains of binary operators are parsed in a loop, but what they parse to still has to be
+/// walked and dropped
+const MAX_OPERATORS: usize = 2000;
 
 impl AstParser {
     pub fn new(token_container: TokenContainer) -> Self {
@@ -39,6 +74,15 @@ impl AstParser {
             filename,
             current: 0,
             error_occurred: false,
+            scopes: vec![HashMap::new()],
+            generators: Vec::new(),
+            asynchronous: Vec::new(),
+            awaits: Vec::new(),
+            loop_depth: 0,
+            nesting: 0,
+            too_deep: false,
+            reported: 0,
+            operators: 0,
         }
     }
 
@@ -61,42 +105,77 @@ impl AstParser {
     }
 
     fn declaration(&mut self) -> Statement {
+        self.operators = 0;
+        let awaits = self.awaits.len();
         let result = {
             if self.match_tokens(&[TokenType::Class]) {
                 self.class_declaration()
+            } else if self.match_tokens(&[TokenType::Enum]) {
+                self.enum_declaration()
             } else if self.match_tokens(&[TokenType::Fn]) {
-                self.function_declaration("function")
-            } else if self.match_tokens(&[TokenType::Let]) {
-                self.var_declaration()
-            }
-            else if self.check_next(TokenType::ColonEqual) {
-                self.var_declaration()
+                self.function_declaration("function", false)
+            } else if self.match_tokens(&[TokenType::Async]) {
+                self.async_function_declaration()
+            } else if self.match_tokens(&[TokenType::Let])
+                || self.check_next(TokenType::ColonEqual)
+            {
+                self.var_declaration(false)
+            } else if self.match_tokens(&[TokenType::Const]) {
+                self.var_declaration(true)
+            } else if self.match_tokens(&[TokenType::Import]) {
+                self.import_declaration()
+            } else if self.match_tokens(&[TokenType::From]) {
+                self.names_import_declaration()
             } else {
                 self.statement()
             }
         };
+        let result = result.and_then(|statement| self.check_awaits(statement, awaits));
+        self.awaits.truncate(awaits);
+
+        match result {
+            Ok(statement) => statement,
+            Err(_) if self.too_deep => {
+                self.skip_to_end();
+                Statement::None
+            }
+            Err(err) => {
+                self.synchronize();
+                self.report(&err);
 
-        if result.is_err() {
-            self.synchronize();
-            let err = result.unwrap_err();
-            println!("{}", err);
+                Statement::None
+            }
+        }
+    }
 
-            return Statement::None;
+    /// an async function can only suspend at an `await` that is the whole value of a statement,
+    /// any other `await` in it would block the event loop
+    fn check_awaits(&mut self, statement: Statement, start: usize) -> Result<Statement, errors::Error> {
+        if self.asynchronous.last() != Some(&true) {
+            return Ok(statement);
         }
 
-        result.unwrap()
+        let suspends = usize::from(statement.awaited_expression().is_some());
+        match self.awaits.get(start + suspends).cloned() {
+            Some(keyword) => Err(self.error(
+                &keyword,
+                "Can only await the whole value of a statement inside an async function",
+            )),
+            None => Ok(statement),
+        }
     }
 
     fn class_declaration(&mut self) -> Result<Statement, errors::Error> {
         let name = self
             .consume(TokenType::Identifier, "Expect class name")?
             .clone();
+        self.declare_name(&name, false)?;
 
         let mut superclass = None;
 
         if self.match_tokens(&[TokenType::Colon]) {
             let superclass_name = self.consume(TokenType::Identifier, "Expect superclass name")?;
-            let _ = superclass.insert(Expression::Variable(Box::new(Variable::new(
+            let _ = superclass.insert(Expression::Variable(Arc::new(Variable::new(
                 superclass_name.clone(),
             ))));
         }
@@ -105,10 +184,15 @@ impl AstParser {
         let mut methods = Vec::new();
 
         while !self.check(TokenType::End) {
-            if self.match_tokens(&[TokenType::Fn]) {
-                let method = self.function_declaration("method")?;
+            let asynchronous = self.match_tokens(&[TokenType::Async]);
+            if asynchronous {
+                self.consume(TokenType::Fn, "Expect 'fn' after 'async'")?;
+            }
+
+            if asynchronous || self.match_tokens(&[TokenType::Fn]) {
+                let method = self.function_declaration("method", asynchronous)?;
                 if let Statement::FunctionStatement(function) = method {
-                    methods.push(*function);
+                    methods.push(function);
                 }
                 //self.consume(TokenType::NewLine, "Expect newline after end of method")?;
                 continue;
@@ -132,7 +216,77 @@ impl AstParser {
         )))
     }
 
-    fn var_declaration(&mut self) -> Result<Statement, errors::Error> {
+    fn enum_declaration(&mut self) -> Result<Statement, errors::Error> {
+        let name = self
+            .consume(TokenType::Identifier, "Expect enum name")?
+            .clone();
+        self.declare_name(&name, false)?;
+        self.consume(TokenType::NewLine, "Expect newline before enum variants")?;
+
+        let mut variants: Vec<VariantDeclaration> = Vec::new();
+        while !self.check(TokenType::End) && !self.is_at_end() {
+            let variant = self
+                .consume(TokenType::Identifier, "Expect variant name")?
+                .clone();
+
+            if variants.iter().any(|existing| existing.name.object == variant.object) {
+                return Err(self.error(
+                    &variant,
+                    &format!("Variant {} declared more than once", variant.object),
+                ));
+            }
+
+            let mut fields = None;
+            if self.match_tokens(&[TokenType::LeftParen]) {
+                let mut names = Vec::new();
+                if !self.check(TokenType::RightParen) {
+                    loop {
+                        let field = self.consume(TokenType::Identifier, "Expect field name")?.clone();
+                        if names.iter().any(|existing: &Token| existing.object == field.object) {
+                            return Err(self.error(
+                                &field,
+                                &format!("Field {} declared more than once in {}", field.object, variant.object),
+                            ));
+                        }
+                        names.push(field);
+
+                        if !self.match_tokens(&[TokenType::Comma]) {
+                            break;
+                        }
+                    }
+                }
+                self.consume(TokenType::RightParen, "Expect ')' after variant fields")?;
+                fields = Some(names);
+            }
+
+            self.consume(TokenType::NewLine, "Expect newline after enum variant")?;
+            variants.push(VariantDeclaration {
+                name: variant,
+                fields,
+            });
+        }
+
+        self.consume(TokenType::End, "Expect 'end' after enum declaration")?;
+        self.consume(
+            TokenType::NewLine,
+            "Expect newline after end of enum declaration",
+        )?;
+
+        let line = name.line;
+        let filename = self.filename.clone();
+        Ok(Statement::EnumStatement(EnumStatement {
+            name,
+            variants,
+            line,
+            filename,
+        }))
+    }
+
+    fn var_declaration(&mut self, constant: bool) -> Result<Statement, errors::Error> {
+        if self.match_tokens(&[TokenType::LeftParen]) {
+            return self.tuple_declaration(constant);
+        }
+
         let name = self
             .consume(TokenType::Identifier, "Expect variable name")?
             .clone();
@@ -141,7 +295,10 @@ impl AstParser {
 
         if self.match_tokens(&[TokenType::Equal, TokenType::ColonEqual]) {
             initializer = Some(self.expression()?);
+        } else if constant {
+            return Err(self.error(&name, "Expect '=' after constant name"));
         }
+        self.declare_name(&name, constant)?;
 
         self.consume(
             TokenType::NewLine,
@@ -152,14 +309,74 @@ impl AstParser {
         Ok(Statement::VariableDeclaration(VariableDeclaration {
             name,
             initializer,
+            constant,
             line,
             filename
         }))
     }
 
-    fn function_declaration(&mut self, kind: &str) -> Result<Statement, errors::Error> {
+    fn tuple_declaration(&mut self, constant: bool) -> Result<Statement, errors::Error> {
+        let mut names = Vec::new();
+
+        loop {
+            let name = self
+                .consume(TokenType::Identifier, "Expect variable name")?
+                .clone();
+            self.declare_name(&name, constant)?;
+            names.push(name);
+
+            if !self.match_tokens(&[TokenType::Comma]) {
+                break;
+            }
+        }
+
+        self.consume(TokenType::RightParen, "Expect ')' after variable names")?;
+        let line = self.previous().line;
+        if !self.match_tokens(&[TokenType::Equal, TokenType::ColonEqual]) {
+            let previous = self.previous().clone();
+            return Err(self.error(&previous, "Expect '=' after destructuring declaration"));
+        }
+
+        let initializer = self.expression_list()?;
+        self.consume(
+            TokenType::NewLine,
+            "Expected new line after variable declaration",
+        )?;
+
+        let filename = self.filename.clone();
+        Ok(Statement::TupleDeclaration(Arc::new(TupleDeclaration {
+            names,
+            initializer,
+            constant,
+            line,
+            filename,
+        })))
+    }
+
+    fn async_function_declaration(&mut self) -> Result<Statement, errors::Error> {
+        self.consume(TokenType::Fn, "Expect 'fn' after 'async'")?;
+        self.function_declaration("function", true)
+    }
+
+    fn function_declaration(
+        &mut self,
+        kind: &str,
+        asynchronous: bool,
+    ) -> Result<Statement, errors::Error> {
         let name = self.consume(TokenType::Identifier, &format!("Expect {} name", kind))?;
         let name = name.clone();
+        if kind == "function" {
+            self.declare_name(&name, false)?;
+        }
+
+        self.scoped(|parser| parser.function_body(name, asynchronous))
+    }
+
+    fn function_body(
+        &mut self,
+        name: Token,
+        asynchronous: bool,
+    ) -> Result<Statement, errors::Error> {
         self.consume(TokenType::LeftParen, "Expect '(' before parameters")?;
         let mut parameters = Vec::new();
 
@@ -172,8 +389,8 @@ impl AstParser {
                     )));
                 }
 
-                let parameter = self.consume(TokenType::Identifier, "Expect parameter name")?;
-                parameters.push(parameter.clone());
+                let parameter = self.parameter(&parameters)?;
+                parameters.push(parameter);
 
                 if !self.match_tokens(&[TokenType::Comma]) {
                     break;
@@ -186,14 +403,28 @@ impl AstParser {
             TokenType::NewLine,
             "Expect newline after function parameters",
         )?;
-        let body = self.block_statement(&[TokenType::End], true)?;
+        self.generators.push(false);
+        self.asynchronous.push(asynchronous);
+        let loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        let body = self.block_statement(&[TokenType::End], true);
+        self.loop_depth = loop_depth;
+        let generator = self.generators.pop().unwrap_or(false);
+        self.asynchronous.pop();
+        let body = body?;
+
+        if generator && asynchronous {
+            return Err(self.error(&name, "Cannot yield inside an async function"));
+        }
+
         let line = name.line;
         let filename = self.filename.clone();
         if let Statement::Block(body) = body {
-            return Ok(Statement::FunctionStatement(Box::new(FunctionStatement {
+            return Ok(Statement::FunctionStatement(Arc::new(FunctionStatement {
                 name,
                 parameters,
                 body,
+                generator,
+                asynchronous,
                 line,
                 filename
             })));
@@ -203,6 +434,37 @@ impl AstParser {
         Err(self.error(&previous, "Error parsing function"))
     }
 
+    fn parameter(&mut self, previous: &[Parameter]) -> Result<Parameter, errors::Error> {
+        let variadic = self.match_tokens(&[TokenType::Ellipsis]);
+        let name = self
+            .consume(TokenType::Identifier, "Expect parameter name")?
+            .clone();
+        self.declare_name(&name, false)?;
+
+        if previous.last().is_some_and(|parameter| parameter.variadic) {
+            return Err(self.error(&name, "Variadic parameter must be the last parameter"));
+        }
+
+        let mut default = None;
+        if self.match_tokens(&[TokenType::Equal]) {
+            if variadic {
+                return Err(self.error(&name, "Variadic parameter cannot have a default value"));
+            }
+            default = Some(self.or()?);
+        } else if !variadic && previous.iter().any(|parameter| parameter.default.is_some()) {
+            return Err(self.error(
+                &name,
+                "Parameter without a default value cannot follow one with a default value",
+            ));
+        }
+
+        Ok(Parameter {
+            name,
+            default,
+            variadic,
+        })
+    }
+
     fn statement(&mut self) -> Result<Statement, errors::Error> {
         if self.match_tokens(&[TokenType::For]) {
             return self.for_statement();
@@ -216,6 +478,14 @@ impl AstParser {
             return self.return_statement();
         }
 
+        if self.match_tokens(&[TokenType::Yield]) {
+            return self.yield_statement();
+        }
+
+        if self.match_tokens(&[TokenType::Break, TokenType::Continue]) {
+            return self.loop_jump_statement();
+        }
+
         if self.match_tokens(&[TokenType::While]) {
             return self.while_statement();
         }
@@ -224,9 +494,13 @@ impl AstParser {
             return self.include_statement();
         }
 
+        if self.match_tokens(&[TokenType::Match]) {
+            return self.match_statement();
+        }
+
         if self.match_tokens(&[TokenType::Block]) {
             self.consume(TokenType::NewLine, "expect newline before start of block")?;
-            return self.block_statement(&[TokenType::End], true);
+            return self.scoped(|parser| parser.block_statement(&[TokenType::End], true));
         }
 
         self.expression_statement()
@@ -250,8 +524,241 @@ impl AstParser {
         Ok(Statement::Include(Include {files, line, filename}))
     }
 
+    /// `import "path" as name`
+    fn import_declaration(&mut self) -> Result<Statement, errors::Error> {
+        let path = self.module_path()?;
+        self.consume(TokenType::As, "Expect 'as' after module path")?;
+        let name = self
+            .consume(TokenType::Identifier, "Expect module name after 'as'")?
+            .clone();
+        self.declare_name(&name, false)?;
+
+        self.import(path, ImportBinding::Module(name))
+    }
+
+    /// `from "path" import first, second`
+    fn names_import_declaration(&mut self) -> Result<Statement, errors::Error> {
+        let path = self.module_path()?;
+        self.consume(TokenType::Import, "Expect 'import' after module path")?;
+
+        let mut names = Vec::new();
+        loop {
+            let name = self
+                .consume(TokenType::Identifier, "Expect name to import")?
+                .clone();
+            self.declare_name(&name, false)?;
+            names.push(name);
+
+            if !self.match_tokens(&[TokenType::Comma]) {
+                break;
+            }
+        }
+
+        self.import(path, ImportBinding::Names(names))
+    }
+
+    fn module_path(&mut self) -> Result<String, errors::Error> {
+        let path = self.consume(TokenType::String, "Expect module path string")?;
+        Ok(path.object.to_string())
+    }
+
+    fn import(&mut self, path: String, binding: ImportBinding) -> Result<Statement, errors::Error> {
+        self.consume(TokenType::NewLine, "Expect new line after import")?;
+        let line = self.previous().line;
+        let filename = self.filename.clone();
+
+        Ok(Statement::Import(Import {path, binding, line, filename}))
+    }
+
+    fn match_statement(&mut self) -> Result<Statement, errors::Error> {
+        let value = self.expression()?;
+        let line = self
+            .consume(TokenType::NewLine, "Expect new line after match value")?
+            .line;
+
+        let mut arms = Vec::new();
+        while self.match_tokens(&[TokenType::Case]) {
+            arms.push(self.scoped(|parser| parser.match_arm())?);
+        }
+
+        self.consume(TokenType::End, "Expect 'case' or 'end' in match statement")?;
+        self.consume(TokenType::NewLine, "Expect new line after end")?;
+
+        let filename = self.filename.clone();
+        Ok(Statement::Match(Arc::new(MatchStatement {
+            value,
+            arms,
+            line,
+            filename,
+        })))
+    }
+
+    fn match_arm(&mut self) -> Result<MatchArm, errors::Error> {
+        let pattern = self.pattern()?;
+        let mut guard = None;
+        if self.match_tokens(&[TokenType::If]) {
+            guard = Some(self.expression()?);
+        }
+        self.consume(TokenType::NewLine, "Expect new line after case pattern")?;
+
+        let body = self.block_statement(&[TokenType::Case, TokenType::End], false)?;
+        if let Statement::Block(body) = body {
+            return Ok(MatchArm {
+                pattern,
+                guard,
+                body,
+            });
+        }
+
+        let previous = self.previous().clone();
+        Err(self.error(&previous, "Error parsing case body"))
+    }
+
+    fn pattern(&mut self) -> Result<Pattern, errors::Error> {
+        if self.match_tokens(&[TokenType::LeftParen]) {
+            let mut elements = Vec::new();
+            while !self.check(TokenType::RightParen) {
+                elements.push(self.pattern()?);
+                if !self.match_tokens(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+            self.consume(TokenType::RightParen, "Expect ')' after tuple pattern")?;
+            return Ok(Pattern::Tuple(elements));
+        }
+
+        if self.match_tokens(&[TokenType::Identifier]) {
+            let name = self.previous().clone();
+
+            if self.match_tokens(&[TokenType::Dot]) {
+                let variant = self
+                    .consume(TokenType::Identifier, "Expect variant name after '.'")?
+                    .clone();
+                let mut fields = None;
+                if self.match_tokens(&[TokenType::LeftParen]) {
+                    let mut patterns = Vec::new();
+                    while !self.check(TokenType::RightParen) {
+                        patterns.push(self.pattern()?);
+                        if !self.match_tokens(&[TokenType::Comma]) {
+                            break;
+                        }
+                    }
+                    self.consume(TokenType::RightParen, "Expect ')' after variant fields")?;
+                    fields = Some(patterns);
+                }
+
+                return Ok(Pattern::Variant {
+                    enum_name: name,
+                    variant,
+                    fields,
+                });
+            }
+
+            if self.match_tokens(&[TokenType::LeftParen]) {
+                let mut fields = Vec::new();
+                while !self.check(TokenType::RightParen) {
+                    let field = self
+                        .consume(TokenType::Identifier, "Expect field name in pattern")?
+                        .clone();
+                    let pattern = if self.match_tokens(&[TokenType::Colon]) {
+                        self.pattern()?
+                    } else {
+                        self.declare_name(&field, false)?;
+                        Pattern::Binding(field.clone())
+                    };
+                    fields.push((field, pattern));
+
+                    if !self.match_tokens(&[TokenType::Comma]) {
+                        break;
+                    }
+                }
+                self.consume(TokenType::RightParen, "Expect ')' after field patterns")?;
+                return Ok(Pattern::Instance {
+                    class: name,
+                    fields,
+                });
+            }
+
+            if name.object.to_string() == "_" {
+                return Ok(Pattern::Wildcard);
+            }
+
+            self.declare_name(&name, false)?;
+            return Ok(Pattern::Binding(name));
+        }
+
+        let low = self.literal_pattern()?;
+        if self.match_tokens(&[TokenType::DotDot]) {
+            let high = self.literal_pattern()?;
+            return Ok(Pattern::Range(low, high));
+        }
+
+        Ok(Pattern::Literal(low))
+    }
+
+    fn literal_pattern(&mut self) -> Result<Object, errors::Error> {
+        if self.match_tokens(&[TokenType::Minus]) {
+            let number = self.consume(TokenType::Number, "Expect number after '-' in pattern")?;
+            if let Object::Number(number) = number.object {
+                return Ok(Object::Number(-number));
+            }
+        }
+
+        if self.match_tokens(&[TokenType::Number, TokenType::String]) {
+            return Ok(self.previous().object.clone());
+        }
+
+        if self.match_tokens(&[TokenType::True, TokenType::False]) {
+            return Ok(Object::Bool(self.previous().token_type == TokenType::True));
+        }
+
+        if self.match_tokens(&[TokenType::None]) {
+            return Ok(Object::None);
+        }
+
+        let current = self.peek().clone();
+        Err(self.error(&current, "Expect pattern"))
+    }
+
     fn for_statement(&mut self) -> Result<Statement, errors::Error> {
-        todo!()
+        let mut names = Vec::new();
+        let destructure = self.match_tokens(&[TokenType::LeftParen]);
+
+        loop {
+            names.push(self.consume(TokenType::Identifier, "Expect loop variable name")?.clone());
+            if !destructure || !self.match_tokens(&[TokenType::Comma]) {
+                break;
+            }
+        }
+
+        if destructure {
+            self.consume(TokenType::RightParen, "Expect ')' after loop variable names")?;
+        }
+
+        self.consume(TokenType::In, "Expect 'in' after loop variable")?;
+        let iterable = self.expression()?;
+        let line = self
+            .consume(TokenType::NewLine, "Expect new line after for iterable")?
+            .line;
+
+        let body = self.loop_body(|parser| {
+            parser.scoped(|parser| {
+                for name in &names {
+                    parser.declare_name(name, false)?;
+                }
+                parser.block_statement(&[TokenType::End], true)
+            })
+        })?;
+
+        let filename = self.filename.clone();
+        Ok(Statement::ForLoop(Arc::new(ForLoop {
+            names,
+            destructure,
+            iterable,
+            body,
+            line,
+            filename,
+        })))
     }
 
     fn if_statement(&mut self) -> Result<Statement, errors::Error> {
@@ -259,19 +766,22 @@ impl AstParser {
         let current = self.consume(TokenType::NewLine, "Expect new line after while condition")?;
         let line = current.line;
 
-        let then_branch = self.block_statement(&[TokenType::End, TokenType::Else], false)?;
+        let then_branch = self.scoped(|parser| {
+            parser.block_statement(&[TokenType::End, TokenType::Else], false)
+        })?;
         let mut else_branch = None;
 
         if self.match_tokens(&[TokenType::Else]) {
             self.consume(TokenType::NewLine, "Expect new line after while condition")?;
-            else_branch = Some(self.block_statement(&[TokenType::End], true)?)
+            else_branch =
+                Some(self.scoped(|parser| parser.block_statement(&[TokenType::End], true))?)
         } else {
             self.consume(TokenType::End, "Expected end after if block")?;
             self.consume(TokenType::NewLine, "Expect new line after end")?;
         }
 
         let filename = self.filename.clone();
-        Ok(Statement::If(Box::new(IfStatement {
+        Ok(Statement::If(Arc::new(IfStatement {
             condition,
             then_branch,
             else_branch,
@@ -288,7 +798,7 @@ impl AstParser {
         let mut value = None;
 
         if !self.check(TokenType::NewLine) {
-            value = Some((self.expression()?, line, filename));
+            value = Some((self.expression_list()?, line, filename));
         }
 
         self.consume(TokenType::NewLine, "Expect newline after return statement")?;
@@ -296,6 +806,42 @@ impl AstParser {
         Ok(Statement::ReturnStatement(value))
     }
 
+    fn yield_statement(&mut self) -> Result<Statement, errors::Error> {
+        let keyword = self.previous().clone();
+        match self.generators.last_mut() {
+            Some(generator) => *generator = true,
+            None => return Err(self.error(&keyword, "Cannot yield outside a function")),
+        }
+
+        let mut value = None;
+        if !self.check(TokenType::NewLine) {
+            value = Some((self.expression_list()?, keyword.line, self.filename.clone()));
+        }
+
+        self.consume(TokenType::NewLine, "Expect newline after yield statement")?;
+
+        Ok(Statement::Yield(value))
+    }
+
+    fn loop_jump_statement(&mut self) -> Result<Statement, errors::Error> {
+        let keyword = self.previous().clone();
+        let name = match keyword.token_type {
+            TokenType::Break => "break",
+            _ => "continue",
+        };
+
+        if self.loop_depth == 0 {
+            return Err(self.error(&keyword, &format!("Cannot {} outside a loop", name)));
+        }
+
+        self.consume(TokenType::NewLine, &format!("Expect newline after {}", name))?;
+
+        if keyword.token_type == TokenType::Break {
+            return Ok(Statement::Break(keyword));
+        }
+        Ok(Statement::Continue(keyword))
+    }
+
     fn while_statement(&mut self) -> Result<Statement, errors::Error> {
         //self.consume(TokenType::LeftParen, "Expect '(' before condition")?;
         let condition = self.expression()?;
@@ -303,9 +849,11 @@ impl AstParser {
         let current = self.consume(TokenType::NewLine, "Expect new line after while condition")?;
         let line = current.line;
 
-        let body = self.block_statement(&[TokenType::End], true)?;
+        let body = self.loop_body(|parser| {
+            parser.scoped(|parser| parser.block_statement(&[TokenType::End], true))
+        })?;
         let filename = self.filename.clone();
-        Ok(Statement::WhileLoop(Box::new(WhileLoop {
+        Ok(Statement::WhileLoop(Arc::new(WhileLoop {
             condition,
             body,
             line,
@@ -317,6 +865,14 @@ impl AstParser {
         &mut self,
         end_tokens: &[TokenType],
         consume: bool,
+    ) -> Result<Statement, errors::Error> {
+        self.nested(|parser| parser.block_contents(end_tokens, consume))
+    }
+
+    fn block_contents(
+        &mut self,
+        end_tokens: &[TokenType],
+        consume: bool,
     ) -> Result<Statement, errors::Error> {
         let mut statements = Vec::new();
         //self.consume(TokenType::NewLine, "Expect new line before block")?;
@@ -334,17 +890,59 @@ impl AstParser {
         }
 
         let filename = self.filename.clone();
-        Ok(Statement::Block(Block { statements, line, filename }))
+        Ok(Statement::Block(Block { statements: statements.into(), line, filename }))
     }
 
     fn expression_statement(&mut self) -> Result<Statement, errors::Error> {
-        let expression = self.expression()?;
+        let mut expression = self.expression()?;
+        if self.check(TokenType::Comma) {
+            expression = self.destructuring_assignment(expression)?;
+        }
         let filename = self.filename.clone();
 
         let line = self.consume(TokenType::NewLine, "Expect newline after statement")?.line;
         Ok(Statement::ExpressionStatement((expression, line, filename)))
     }
 
+    fn destructuring_assignment(&mut self, first: Expression) -> Result<Expression, errors::Error> {
+        let line = self.previous().line;
+        let mut targets = vec![first];
+
+        while self.match_tokens(&[TokenType::Comma]) {
+            targets.push(self.or()?);
+        }
+
+        let equals = self
+            .consume(TokenType::Equal, "Expect '=' after assignment targets")?
+            .clone();
+
+        for target in &targets {
+            match target {
+                Expression::Variable(variable) => self.check_assignable(&variable.name)?,
+                Expression::Get(get) if get.arguments.is_none() => {}
+                _ => return Err(self.error(&equals, "Invalid Assignment Target")),
+            }
+        }
+
+        let value = self.expression_list()?;
+        let filename = self.filename.clone();
+
+        Ok(Expression::DestructuringAssign(Arc::new(
+            DestructuringAssign {
+                targets,
+                value,
+                line,
+                filename,
+            },
+        )))
+    }
+
+    /// gives up on the rest of the source, a block left unfinished steps back before the end
+    /// and is skipped again
+    fn skip_to_end(&mut self) {
+        self.current = self.tokens.len() - 1;
+    }
+
     fn synchronize(&mut self) {
         self.advance();
 
@@ -355,12 +953,16 @@ impl AstParser {
 
             match self.peek().token_type {
                 TokenType::Class
+                | TokenType::Enum
                 | TokenType::Fn
                 | TokenType::Let
                 | TokenType::For
                 | TokenType::If
                 | TokenType::While
-                | TokenType::Return => return,
+                | TokenType::Match
+                | TokenType::Return
+                | TokenType::Break
+                | TokenType::Continue => return,
 
                 _ => {}
             }
@@ -370,7 +972,22 @@ impl AstParser {
     }
 
     pub fn expression(&mut self) -> Result<Expression, errors::Error> {
-        self.assignment()
+        self.nested(Self::assignment)
+    }
+
+    /// parses `a, b, c` into a tuple, or a single expression when there is no comma
+    fn expression_list(&mut self) -> Result<Expression, errors::Error> {
+        let expression = self.expression()?;
+        if !self.check(TokenType::Comma) {
+            return Ok(expression);
+        }
+
+        let mut elements = vec![expression];
+        while self.match_tokens(&[TokenType::Comma]) {
+            elements.push(self.expression()?);
+        }
+
+        Ok(Expression::Tuple(Arc::new(Tuple::new(elements))))
     }
 
     fn assignment(&mut self) -> Result<Expression, errors::Error> {
@@ -384,11 +1001,12 @@ impl AstParser {
 
             if let Expression::Variable(variable) = &expression {
                 let name = variable.name.clone();
-                return Ok(Expression::Assign(Box::new(Assign { name, value, line, filename})));
+                self.check_assignable(&name)?;
+                return Ok(Expression::Assign(Arc::new(Assign { name, value, line, filename})));
             } else if let Expression::Get(get) = &expression {
-                let get = *get.clone();
+                let get = get.as_ref().clone();
 
-                return Ok(Expression::Set(Box::new(Set {
+                return Ok(Expression::Set(Arc::new(Set {
                     name: get.name,
                     object: get.object,
                     value,
@@ -407,10 +1025,11 @@ impl AstParser {
         let expression = self.and()?;
 
         if self.match_tokens(&[TokenType::Or]) {
+            self.count_operator()?;
             let operator = self.previous().clone();
             let right = self.and()?;
 
-            let binary = Expression::Binary(Box::new(Binary {
+            let binary = Expression::Binary(Arc::new(Binary {
                 left: expression,
                 right,
                 operator,
@@ -425,10 +1044,11 @@ impl AstParser {
         let expression = self.equality()?;
 
         if self.match_tokens(&[TokenType::And]) {
+            self.count_operator()?;
             let operator = self.previous().clone();
             let right = self.equality()?;
 
-            let binary = Expression::Binary(Box::new(Binary {
+            let binary = Expression::Binary(Arc::new(Binary {
                 left: expression,
                 right,
                 operator,
@@ -443,10 +1063,11 @@ impl AstParser {
         let expression = self.comparison()?;
 
         if self.match_tokens(&[TokenType::EqualEqual, TokenType::NotEqual]) {
+            self.count_operator()?;
             let operator = self.previous().clone();
             let right = self.comparison()?;
 
-            let binary = Expression::Binary(Box::new(Binary {
+            let binary = Expression::Binary(Arc::new(Binary {
                 left: expression,
                 right,
                 operator,
@@ -466,10 +1087,11 @@ impl AstParser {
             TokenType::Less,
             TokenType::LessEqual,
         ]) {
+            self.count_operator()?;
             let operator = self.previous().clone();
             let right = self.addition()?;
 
-            let binary = Expression::Binary(Box::new(Binary {
+            let binary = Expression::Binary(Arc::new(Binary {
                 left: expression,
                 right,
                 operator,
@@ -484,10 +1106,11 @@ impl AstParser {
         let mut expression = self.multiplication()?;
 
         while self.match_tokens(&[TokenType::Minus, TokenType::Plus]) {
+            self.count_operator()?;
             let operator = self.previous().to_owned();
             let right = self.multiplication()?;
 
-            expression = Expression::Binary(Box::new(Binary::new(expression, right, operator)))
+            expression = Expression::Binary(Arc::new(Binary::new(expression, right, operator)))
         }
 
         Ok(expression)
@@ -497,10 +1120,11 @@ impl AstParser {
         let mut expression = self.power()?;
 
         while self.match_tokens(&[TokenType::Star, TokenType::Slash, TokenType::Percent]) {
+            self.count_operator()?;
             let operator = self.previous().to_owned();
             let right = self.unary()?;
 
-            expression = Expression::Binary(Box::new(Binary::new(expression, right, operator)))
+            expression = Expression::Binary(Arc::new(Binary::new(expression, right, operator)))
         }
 
         Ok(expression)
@@ -510,10 +1134,11 @@ impl AstParser {
         let mut expression = self.unary()?;
 
         while self.match_tokens(&[TokenType::Caret]) {
+            self.count_operator()?;
             let operator = self.previous().to_owned();
             let right = self.unary()?;
 
-            expression = Expression::Binary(Box::new(Binary::new(expression, right, operator)))
+            expression = Expression::Binary(Arc::new(Binary::new(expression, right, operator)))
         }
 
         Ok(expression)
@@ -522,9 +1147,21 @@ impl AstParser {
     fn unary(&mut self) -> Result<Expression, errors::Error> {
         if self.match_tokens(&[TokenType::Minus]) {
             let operator = self.previous().to_owned();
-            let right = self.unary()?;
+            let right = self.nested(Self::unary)?;
 
-            return Ok(Expression::Unary(Box::new(Unary::new(right, operator))));
+            return Ok(Expression::Unary(Arc::new(Unary::new(right, operator))));
+        }
+
+        if self.match_tokens(&[TokenType::Await]) {
+            let keyword = self.previous().to_owned();
+            if self.asynchronous.last() == Some(&false) {
+                return Err(self.error(&keyword, "Cannot await outside an async function"));
+            }
+
+            self.awaits.push(keyword.clone());
+            let value = self.nested(Self::unary)?;
+
+            return Ok(Expression::Await(Arc::new(Await::new(value, keyword))));
         }
 
         self.call()
@@ -541,16 +1178,21 @@ impl AstParser {
                 .consume(TokenType::Identifier, "Expect name after '.'")?
                 .clone();
             let mut arguments = None;
+            let mut keyword_arguments = Vec::new();
             if self.match_tokens(&[TokenType::LeftParen]) {
-                arguments = Some(self.get_arguments()?);
+                let (positional, keywords) = self.get_arguments()?;
+                arguments = Some(positional);
+                keyword_arguments = keywords;
                 self.consume(TokenType::RightParen, "Expect ')' after arguments")?;
             }
 
             let filename = self.filename.clone();
-            return Ok(Expression::Get(Box::new(Get {
+            return Ok(Expression::Get(Arc::new(Get {
                 object: expression,
                 name,
                 arguments,
+                keyword_arguments,
+                cache: InlineCache::default(),
                 line,
                 filename
             })));
@@ -559,33 +1201,60 @@ impl AstParser {
         Ok(expression)
     }
 
-    fn get_arguments(&mut self) -> Result<Vec<Expression>, errors::Error> {
+    /// parses positional arguments followed by keyword arguments, `(host, port: 8080)`
+    fn get_arguments(&mut self) -> Result<(Vec<Expression>, Vec<KeywordArgument>), errors::Error> {
         let mut arguments = Vec::new();
+        let mut keyword_arguments: Vec<KeywordArgument> = Vec::new();
+
         if !self.check(TokenType::RightParen) {
             loop {
-                if arguments.len() > MAX_PARAMETERS {
+                if arguments.len() + keyword_arguments.len() > MAX_PARAMETERS {
                     let previous = self.previous().clone();
                     return Err(self.error(&previous, "Too many arguments"));
                 }
 
-                arguments.push(self.expression()?);
+                if self.check(TokenType::Identifier) && self.check_next(TokenType::Colon) {
+                    let name = self.advance().clone();
+                    self.advance();
+
+                    let duplicate = keyword_arguments
+                        .iter()
+                        .any(|(keyword, _)| keyword.object == name.object);
+                    if duplicate {
+                        return Err(self.error(
+                            &name,
+                            &format!("Keyword argument {} repeated", name.object),
+                        ));
+                    }
+
+                    keyword_arguments.push((name, self.expression()?));
+                } else if !keyword_arguments.is_empty() {
+                    let current = self.peek().clone();
+                    return Err(self.error(&current, "Positional argument follows keyword argument"));
+                } else {
+                    arguments.push(self.expression()?);
+                }
+
                 if !self.match_tokens(&[TokenType::Comma]) {
                     break;
                 }
             }
         }
 
-        Ok(arguments)
+        Ok((arguments, keyword_arguments))
     }
 
     fn finish_call(&mut self, callee: Expression) -> Result<Expression, errors::Error> {
-        let arguments = self.get_arguments()?;
+        let (arguments, keyword_arguments) = self.get_arguments()?;
 
         let paren = self
             .consume(TokenType::RightParen, "Expect ')' after arguments")?
             .clone();
-        Ok(Expression::Call(Box::new(Call::new(
-            callee, paren, arguments,
+        Ok(Expression::Call(Arc::new(Call::new(
+            callee,
+            paren,
+            arguments,
+            keyword_arguments,
         ))))
     }
 
@@ -594,42 +1263,166 @@ impl AstParser {
         if self.match_tokens(&[TokenType::Identifier]) {
             let token = self.previous().clone();
 
-            return Ok(Expression::Variable(Box::new(Variable::new(token))));
+            return Ok(Expression::Variable(Arc::new(Variable::new(token))));
         }
 
         // Handle literals
         if self.match_tokens(&[TokenType::Number, TokenType::String]) {
             let token = self.previous().clone();
 
-            return Ok(Expression::Literal(Literal::new(token.object)));
+            return Ok(Expression::Literal(Arc::new(Literal::new(token.object))));
         }
 
         // Handle literals
         if self.match_tokens(&[TokenType::None]) {
             //let token = self.previous().clone();
 
-            return Ok(Expression::Literal(Literal::new(Object::None)));
+            return Ok(Expression::Literal(Arc::new(Literal::new(Object::None))));
         }
 
         // Handle booleans
         if self.match_tokens(&[TokenType::True, TokenType::False]) {
             let token = self.previous().clone();
 
-            return Ok(Expression::Literal(Literal::new(Object::Bool(
+            return Ok(Expression::Literal(Arc::new(Literal::new(Object::Bool(
                 token.token_type == TokenType::True,
-            ))));
+            )))));
         }
 
         if self.match_tokens(&[TokenType::LeftParen]) {
+            if self.match_tokens(&[TokenType::RightParen]) {
+                return Ok(Expression::Tuple(Arc::new(Tuple::new(Vec::new()))));
+            }
+
             let expression = self.expression()?;
+            if self.match_tokens(&[TokenType::Comma]) {
+                return self.finish_tuple(expression);
+            }
+
             self.consume(TokenType::RightParen, "Expect ')' after expression")?;
-            return Ok(Expression::Grouping(Box::new(Grouping::new(expression))));
+            return Ok(Expression::Grouping(Arc::new(Grouping::new(expression))));
         }
 
         let current = self.peek().clone();
         Err(self.error(&current, "Expect Expression"))
     }
 
+    /// parses something that can contain itself, failing once nesting gets too deep
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, errors::Error>,
+    ) -> Result<T, errors::Error> {
+        if self.nesting >= MAX_NESTING_DEPTH {
+            let token = self.peek().clone();
+            let error = self.error(&token, "Too many nested expressions and blocks");
+            self.report(&error);
+            self.too_deep = true;
+            self.skip_to_end();
+            return Err(error);
+        }
+
+        self.nesting += 1;
+        let result = parse(self);
+        self.nesting -= 1;
+        result
+    }
+
+    /// counts the binary operator just matched, failing once a statement has too many
+    fn count_operator(&mut self) -> Result<(), errors::Error> {
+        if self.operators >= MAX_OPERATORS {
+            let token = self.previous().clone();
+            return Err(self.error(&token, "Too many operators in one statement"));
+        }
+
+        self.operators += 1;
+        Ok(())
+    }
+
+    /// parses the body of a loop, where `break` and `continue` are allowed
+    fn loop_body<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, errors::Error>,
+    ) -> Result<T, errors::Error> {
+        self.loop_depth += 1;
+        let result = parse(self);
+        self.loop_depth -= 1;
+        result
+    }
+
+    /// runs `parse` inside a new declaration scope
+    fn scoped<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, errors::Error>,
+    ) -> Result<T, errors::Error> {
+        self.scopes.push(HashMap::new());
+        let result = parse(self);
+        self.scopes.pop();
+        result
+    }
+
+    /// records a declaration in the innermost scope, rejecting redeclaration of constants
+    fn declare_name(&mut self, name: &Token, constant: bool) -> Result<(), errors::Error> {
+        let identifier = name.name();
+        let existing = self
+            .scopes
+            .last()
+            .and_then(|scope| scope.get(&identifier).copied());
+
+        match existing {
+            Some(true) => {
+                return Err(self.error(
+                    name,
+                    &format!("Cannot redeclare constant {} in the same scope", identifier),
+                ))
+            }
+            Some(false) if constant => {
+                return Err(self.error(
+                    name,
+                    &format!("Cannot redeclare {} as a constant in the same scope", identifier),
+                ))
+            }
+            _ => {}
+        }
+
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(identifier, constant);
+        }
+        Ok(())
+    }
+
+    /// rejects assignments to names that resolve to a constant declared in this file
+    fn check_assignable(&mut self, name: &Token) -> Result<(), errors::Error> {
+        let identifier = name.name();
+        let constant = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&identifier).copied());
+
+        if constant == Some(true) {
+            return Err(self.error(
+                name,
+                &format!("Cannot assign to constant value: {}", identifier),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn finish_tuple(&mut self, first: Expression) -> Result<Expression, errors::Error> {
+        let mut elements = vec![first];
+
+        while !self.check(TokenType::RightParen) {
+            elements.push(self.expression()?);
+            if !self.match_tokens(&[TokenType::Comma]) {
+                break;
+            }
+        }
+
+        self.consume(TokenType::RightParen, "Expect ')' after tuple elements")?;
+        Ok(Expression::Tuple(Arc::new(Tuple::new(elements))))
+    }
+
     fn match_tokens(&mut self, token_types: &[TokenType]) -> bool {
         for &token_type in token_types {
             if self.check(token_type) {
@@ -675,7 +1468,7 @@ impl AstParser {
         if let Some(token) = next {
             return token.token_type == token_type;
         }
-        return false
+        false
     }
 
     /// Check if we are at the end of file
@@ -698,6 +1491,11 @@ impl AstParser {
         &self.tokens[self.current - 1]
     }
 
+    fn report(&mut self, error: &errors::Error) {
+        self.reported += 1;
+        println!("{}", error);
+    }
+
     fn error(&mut self, token: &Token, message: &str) -> errors::Error {
         self.error_occurred = true;
         errors::Error::Parse(format!("[line: {}] (ParseError) {} ", token.line, message))
@@ -705,4 +1503,180 @@ impl AstParser {
 }
 
 #[cfg(test)]
-mod test {}
+mod test {
+    use std::thread;
+
+    use super::{AstParser, MAX_NESTING_DEPTH, MAX_OPERATORS};
+    use crate::language::{
+        generate_parsed_ast, scanner::Scanner, Import, ImportBinding, Statement, STACK_SIZE,
+    };
+
+    #[test]
+    fn test_parser_constant_reassignment() {
+        let source = "const limit = 3\nlimit = 4\n";
+        assert!(generate_parsed_ast(source, "").is_err());
+    }
+
+    #[test]
+    fn test_parser_constant_shadowing() {
+        let source = "const limit = 3\nblock\nconst limit = 4\nend\n";
+        assert!(generate_parsed_ast(source, "").is_ok());
+
+        let source = "const limit = 3\nconst limit = 4\n";
+        assert!(generate_parsed_ast(source, "").is_err());
+    }
+
+    #[test]
+    fn test_parser_destructuring() {
+        let source = "let (q, r) = divmod(7, 2)\nq, r = r, q\n";
+        assert!(generate_parsed_ast(source, "").is_ok());
+
+        let source = "1, b = 2, 3\n";
+        assert!(generate_parsed_ast(source, "").is_err());
+    }
+
+    #[test]
+    fn test_parser_parameters() {
+        let source = "fn log(level, tag = \"main\", ...parts)\nend\nlog(1, tag: 2)\n";
+        assert!(generate_parsed_ast(source, "").is_ok());
+
+        let source = "fn log(level = 1, tag)\nend\n";
+        assert!(generate_parsed_ast(source, "").is_err());
+
+        let source = "log(tag: 2, 1)\n";
+        assert!(generate_parsed_ast(source, "").is_err());
+    }
+
+    #[test]
+    fn test_parser_enum() {
+        let source = "enum Shape\nEmpty\nCircle(radius)\nRect(width, height)\nend\n";
+        assert!(generate_parsed_ast(source, "").is_ok());
+
+        assert!(generate_parsed_ast("enum Shape\nCircle(r)\nCircle(r)\nend\n", "").is_err());
+        assert!(generate_parsed_ast("enum Shape\nCircle(r)\nRect(r, s)\nend\n", "").is_ok());
+        assert!(generate_parsed_ast("enum Shape\nCircle(r)\nRect(r, r)\nend\n", "").is_err());
+    }
+
+    #[test]
+    fn test_parser_generator() {
+        let statements = generate_parsed_ast("fn count()\nyield 1\nend\n", "").unwrap();
+        assert!(matches!(
+            &statements[0],
+            Statement::FunctionStatement(function) if function.generator
+        ));
+
+        assert!(generate_parsed_ast("yield 1\n", "").is_err());
+    }
+
+    #[test]
+    fn test_parser_imports() {
+        let statements = generate_parsed_ast("import \"mathlib\" as m\nfrom \"mathlib\" import sum, hello\n", "").unwrap();
+        assert!(matches!(
+            &statements[0],
+            Statement::Import(Import { path, binding: ImportBinding::Module(_), .. }) if path == "mathlib"
+        ));
+        assert!(matches!(
+            &statements[1],
+            Statement::Import(Import { binding: ImportBinding::Names(names), .. }) if names.len() == 2
+        ));
+
+        assert!(generate_parsed_ast("import \"mathlib\"\n", "").is_err());
+        assert!(generate_parsed_ast("const sum = 1\nfrom \"mathlib\" import sum\n", "").is_err());
+    }
+
+    #[test]
+    fn test_parser_async_function() {
+        let statements = generate_parsed_ast("async fn load()\nawait sleep(1)\nend\n", "").unwrap();
+        assert!(matches!(
+            &statements[0],
+            Statement::FunctionStatement(function) if function.asynchronous
+        ));
+
+        assert!(generate_parsed_ast("async fn load()\nyield 1\nend\n", "").is_err());
+
+        let suspending = "async fn load(task, object)\n\
+            await task\n\
+            let value = await task\n\
+            value = await task\n\
+            object.value = await task\n\
+            if value\nwhile value\nlet (a, b) = await task\nend\nend\n\
+            return await task\n\
+            end\n";
+        assert!(generate_parsed_ast(suspending, "").is_ok());
+
+        for nested in [
+            "println(await task)",
+            "let value = 1 + await task",
+            "let value = await (await task)",
+            "if await task\nend",
+            "while value\nreturn (await task, 1)\nend",
+        ] {
+            let source = format!("async fn load(task, value)\n{}\nend\n", nested);
+            assert!(generate_parsed_ast(&source, "").is_err(), "{}", nested);
+        }
+
+        assert!(generate_parsed_ast("fn load(task)\nawait task\nend\n", "").is_err());
+        assert!(generate_parsed_ast("async fn load(task)\nfn inner()\nawait task\nend\nend\n", "").is_err());
+        assert!(generate_parsed_ast("let task = sleep(1)\nprintln(await task)\n", "").is_ok());
+    }
+
+    #[test]
+    fn test_parser_for_loop() {
+        let statements = generate_parsed_ast("for (k, v) in pairs\nprintln(k)\nend\n", "").unwrap();
+        assert!(matches!(
+            &statements[0],
+            Statement::ForLoop(for_loop) if for_loop.destructure && for_loop.names.len() == 2
+        ));
+
+        assert!(generate_parsed_ast("for x of items\nend\n", "").is_err());
+    }
+
+    #[test]
+    fn test_parser_loop_jumps() {
+        let statements = generate_parsed_ast("while true\nbreak\nend\n", "").unwrap();
+        assert!(matches!(
+            &statements[0],
+            Statement::WhileLoop(while_loop) if matches!(
+                &while_loop.body,
+                Statement::Block(block) if matches!(block.statements[0], Statement::Break(_))
+            )
+        ));
+
+        assert!(generate_parsed_ast("continue\n", "").is_err());
+        assert!(generate_parsed_ast("while true\nfn f()\nbreak\nend\nend\n", "").is_err());
+    }
+
+    #[test]
+    fn test_parser_nesting_limit() {
+        let nested = |depth: usize| format!("x = {}1{}\n", "(".repeat(depth), ")".repeat(depth));
+        let chained = |operators: usize| format!("x = 1{}\n", "+1".repeat(operators));
+        let half = MAX_OPERATORS / 2 + 1;
+        let grouped = format!("x = (1{})+1{}\n", "*2".repeat(half), "-1".repeat(half));
+
+        // parsing recurses once per level, unoptimised builds need the interpreter's stack size
+        let parse = thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(move || {
+                assert!(generate_parsed_ast(&nested(50), "").is_ok());
+                assert!(generate_parsed_ast(&nested(MAX_NESTING_DEPTH + 1), "").is_err());
+                assert!(generate_parsed_ast(&chained(MAX_OPERATORS), "").is_ok());
+                assert!(generate_parsed_ast(&chained(MAX_OPERATORS + 1), "").is_err());
+                // operators inside parentheses add to the depth of the ones outside
+                assert!(generate_parsed_ast(&grouped, "").is_err());
+                assert!(generate_parsed_ast(&format!("{}{}", chained(half), chained(half)), "").is_ok());
+
+                // the blocks enclosing the one nested too deep fail as well, but are not reported
+                let depth = MAX_NESTING_DEPTH * 15;
+                let source = format!("{}x = 1\n{}", "if true\n".repeat(depth), "end\n".repeat(depth));
+                let mut parser = AstParser::new(Scanner::new().scan_tokens_with_filename(&source, "").unwrap());
+                while !parser.is_at_end() {
+                    parser.declaration();
+                }
+                assert_eq!(parser.reported, 1);
+                assert!(parser.parse_ast().is_err());
+            })
+            .unwrap();
+
+        parse.join().unwrap();
+    }
+}
@@ -2,32 +2,55 @@ pub mod assignment;
 pub mod block;
 pub mod class;
 pub mod declaration;
+pub mod enum_statement;
+pub mod for_loop;
 pub mod function;
 pub mod if_statement;
 pub mod while_loop;
 pub mod include;
+pub mod import;
+pub mod match_statement;
 
 pub use block::Block;
+pub use for_loop::ForLoop;
 pub use if_statement::IfStatement;
 pub use while_loop::WhileLoop;
 pub use include::Include;
+pub use import::{Import, ImportBinding};
+pub use match_statement::MatchStatement;
 
-use self::{class::ClassStatement, declaration::VariableDeclaration, function::FunctionStatement};
+use self::{
+    class::ClassStatement,
+    declaration::{TupleDeclaration, VariableDeclaration},
+    enum_statement::EnumStatement,
+    function::FunctionStatement,
+};
+
+use std::sync::Arc;
 
 use super::{expression::Expression, visitor::StatementVisitor};
+use crate::language::scanner::token::Token;
 
 #[derive(Debug, Clone)]
 pub enum Statement {
     None,
-    If(Box<IfStatement>),
-    WhileLoop(Box<WhileLoop>),
+    If(Arc<IfStatement>),
+    WhileLoop(Arc<WhileLoop>),
+    ForLoop(Arc<ForLoop>),
     Block(Block),
-    FunctionStatement(Box<FunctionStatement>),
+    FunctionStatement(Arc<FunctionStatement>),
     ReturnStatement(Option<(Expression, usize, String)>),
+    Yield(Option<(Expression, usize, String)>),
+    Break(Token),
+    Continue(Token),
     VariableDeclaration(VariableDeclaration),
+    TupleDeclaration(Arc<TupleDeclaration>),
     ExpressionStatement((Expression, usize, String)),
     ClassStatement(ClassStatement),
+    EnumStatement(EnumStatement),
     Include(Include),
+    Import(Import),
+    Match(Arc<MatchStatement>),
 }
 
 impl Statement {
@@ -36,19 +59,91 @@ impl Statement {
             Self::None => visitor.visit_none(),
             Self::If(if_statement) => visitor.visit_if(if_statement),
             Self::WhileLoop(while_loop) => visitor.visit_while(while_loop),
+            Self::ForLoop(for_loop) => visitor.visit_for(for_loop),
             Self::Block(block) => visitor.visit_block(block),
             Self::FunctionStatement(function_statement) => {
                 visitor.visit_function_statement(function_statement)
             }
             Self::ReturnStatement(return_values) => visitor.visit_return(return_values),
+            Self::Yield(yield_value) => visitor.visit_yield(yield_value),
+            Self::Break(keyword) => visitor.visit_break(keyword),
+            Self::Continue(keyword) => visitor.visit_continue(keyword),
             Self::VariableDeclaration(var_declaration) => {
                 visitor.visit_var_declaration(var_declaration)
             }
+            Self::TupleDeclaration(tuple_declaration) => {
+                visitor.visit_tuple_declaration(tuple_declaration)
+            }
             Self::ExpressionStatement(expression_statement) => {
                 visitor.visit_expression_statement(expression_statement)
             }
             Self::ClassStatement(class_statement) => class_statement.accept(visitor),
+            Self::EnumStatement(enum_statement) => enum_statement.accept(visitor),
             Self::Include(include) => include.accept(visitor),
+            Self::Import(import) => import.accept(visitor),
+            Self::Match(match_statement) => visitor.visit_match(match_statement),
+        }
+    }
+}
+
+impl Statement {
+    /// the operand of an `await` making up a whole statement: `await task`, `let x = await task`,
+    /// `x = await task`, `object.field = await task` or `return await task`, the places an async
+    /// function can suspend
+    pub fn awaited_expression(&self) -> Option<&Expression> {
+        let expression = match self {
+            Self::ExpressionStatement((expression, _, _)) => match expression {
+                Expression::Assign(assign) => &assign.value,
+                Expression::DestructuringAssign(assign) => &assign.value,
+                Expression::Set(set) => &set.value,
+                expression => expression,
+            },
+            Self::VariableDeclaration(declaration) => declaration.initializer.as_ref()?,
+            Self::TupleDeclaration(declaration) => &declaration.initializer,
+            Self::ReturnStatement(Some((expression, _, _))) => expression,
+            _ => return None,
+        };
+
+        match expression {
+            Expression::Await(await_expression) => Some(&await_expression.value),
+            _ => None,
+        }
+    }
+
+    /// true when a `yield` is reachable without entering a nested function or class
+    pub fn contains_yield(&self) -> bool {
+        self.contains(&|statement| matches!(statement, Self::Yield(_)))
+    }
+
+    /// true when a statement awaiting a task is reachable without entering a nested function or
+    /// class
+    pub fn contains_await(&self) -> bool {
+        self.contains(&|statement| statement.awaited_expression().is_some())
+    }
+
+    fn contains(&self, found: &impl Fn(&Statement) -> bool) -> bool {
+        if found(self) {
+            return true;
+        }
+
+        match self {
+            Self::Block(block) => block.statements.iter().any(|statement| statement.contains(found)),
+            Self::If(if_statement) => {
+                if_statement.then_branch.contains(found)
+                    || if_statement
+                        .else_branch
+                        .as_ref()
+                        .is_some_and(|statement| statement.contains(found))
+            }
+            Self::WhileLoop(while_loop) => while_loop.body.contains(found),
+            Self::ForLoop(for_loop) => for_loop.body.contains(found),
+            Self::Match(match_statement) => match_statement.arms.iter().any(|arm| {
+                arm.body
+                    .statements
+                    .iter()
+                    .any(|statement| statement.contains(found))
+            }),
+            _ => false,
         }
     }
 }
@@ -1,5 +1,9 @@
 use crate::language::{
-    abstract_syntax_tree::{expression::Expression, visitor::ExpressionVisitor},
+    abstract_syntax_tree::{
+        expression::{call::KeywordArgument, Expression},
+        inline_cache::InlineCache,
+        visitor::ExpressionVisitor,
+    },
     scanner::token::Token,
 };
 
@@ -22,16 +26,12 @@ pub struct Get {
     pub object: Expression,
     pub name: Token,
     pub arguments: Option<Vec<Expression>>,
+    pub keyword_arguments: Vec<KeywordArgument>,
+    pub cache: InlineCache,
     pub line: usize,
     pub filename: String,
 }
 
-impl Get {
-    pub fn accept<T>(&self, visitor: &mut impl ExpressionVisitor<Output = T>) -> T {
-        visitor.visit_get(self)
-    }
-}
-
 #[derive(Debug, Clone)]
 pub struct Set {
     pub object: Expression,
@@ -46,3 +46,12 @@ impl Set {
         visitor.visit_set(self)
     }
 }
+
+/// assigns each element of a tuple to the matching target, `a, b = b, a`
+#[derive(Debug, Clone)]
+pub struct DestructuringAssign {
+    pub targets: Vec<Expression>,
+    pub value: Expression,
+    pub line: usize,
+    pub filename: String,
+}
@@ -1,8 +1,10 @@
+use std::sync::Arc;
+
 use super::Statement;
 
 #[derive(Debug, Clone)]
 pub struct Block {
-    pub statements: Vec<Statement>,
+    pub statements: Arc<[Statement]>,
     pub line: usize,
     pub filename: String,
 }
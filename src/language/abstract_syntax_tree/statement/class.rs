@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use crate::language::{Expression, StatementVisitor, Token};
 
 use super::function::FunctionStatement;
@@ -6,7 +8,7 @@ use super::function::FunctionStatement;
 pub struct ClassStatement {
     pub name: Token,
     pub superclass: Option<Expression>,
-    pub methods: Vec<FunctionStatement>,
+    pub methods: Vec<Arc<FunctionStatement>>,
     pub line: usize,
     pub filename: String,
 }
@@ -15,7 +17,7 @@ impl ClassStatement {
     pub fn new(
         name: Token,
         superclass: Option<Expression>,
-        methods: Vec<FunctionStatement>,
+        methods: Vec<Arc<FunctionStatement>>,
         line: usize,
         filename: String,
         
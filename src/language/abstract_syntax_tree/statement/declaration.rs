@@ -4,6 +4,18 @@ use crate::language::{abstract_syntax_tree::expression::Expression, scanner::tok
 pub struct VariableDeclaration {
     pub name: Token,
     pub initializer: Option<Expression>,
+    /// declared with `const`, the name can not be reassigned in its scope
+    pub constant: bool,
+    pub line: usize,
+    pub filename: String,
+}
+
+/// declares one variable per element of a tuple, `let (q, r) = divmod(x, y)`
+#[derive(Debug, Clone)]
+pub struct TupleDeclaration {
+    pub names: Vec<Token>,
+    pub initializer: Expression,
+    pub constant: bool,
     pub line: usize,
     pub filename: String,
 }
@@ -0,0 +1,22 @@
+use crate::language::{StatementVisitor, Token};
+
+#[derive(Debug, Clone)]
+pub struct EnumStatement {
+    pub name: Token,
+    pub variants: Vec<VariantDeclaration>,
+    pub line: usize,
+    pub filename: String,
+}
+
+/// a variant of an enum, plain `Red` or carrying fields `Circle(radius)`
+#[derive(Debug, Clone)]
+pub struct VariantDeclaration {
+    pub name: Token,
+    pub fields: Option<Vec<Token>>,
+}
+
+impl EnumStatement {
+    pub fn accept<T>(&self, visitor: &mut impl StatementVisitor<Output = T>) -> T {
+        visitor.visit_enum_statement(self)
+    }
+}
@@ -0,0 +1,14 @@
+use crate::language::{abstract_syntax_tree::expression::Expression, Token};
+
+use super::Statement;
+
+/// `for name in iterable`, or `for (a, b) in iterable` to destructure each item
+#[derive(Debug, Clone)]
+pub struct ForLoop {
+    pub names: Vec<Token>,
+    pub destructure: bool,
+    pub iterable: Expression,
+    pub body: Statement,
+    pub line: usize,
+    pub filename: String,
+}
@@ -1,12 +1,26 @@
-use crate::language::scanner::token::Token;
+use crate::language::{abstract_syntax_tree::expression::Expression, scanner::token::Token};
 
 use super::Block;
 
 #[derive(Debug, Clone)]
 pub struct FunctionStatement {
     pub name: Token,
-    pub parameters: Vec<Token>,
+    pub parameters: Vec<Parameter>,
     pub body: Block,
+    /// the body contains `yield`, calling the function returns a generator
+    pub generator: bool,
+    /// declared with `async fn`, calling the function schedules a task
+    pub asynchronous: bool,
     pub line: usize,
     pub filename: String,
 }
+
+/// a function parameter, `name`, `name = default` or `...name`
+#[derive(Debug, Clone)]
+pub struct Parameter {
+    pub name: Token,
+    /// evaluated on every call that does not pass this argument
+    pub default: Option<Expression>,
+    /// collects the remaining positional arguments into a tuple
+    pub variadic: bool,
+}
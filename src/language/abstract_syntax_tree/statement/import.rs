@@ -0,0 +1,25 @@
+use crate::language::{StatementVisitor, Token};
+
+/// the names an import declares in the importing scope
+#[derive(Debug, Clone)]
+pub enum ImportBinding {
+    /// `import "path" as name`, the module object itself
+    Module(Token),
+    /// `from "path" import first, second`, top-level definitions of the module
+    Names(Vec<Token>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Import {
+    /// the module as written, without the `.nova` extension if it was left out
+    pub path: String,
+    pub binding: ImportBinding,
+    pub line: usize,
+    pub filename: String,
+}
+
+impl Import {
+    pub fn accept<T>(&self, visitor: &mut impl StatementVisitor<Output = T>) -> T {
+        visitor.visit_import(self)
+    }
+}
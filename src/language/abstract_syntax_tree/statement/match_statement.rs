@@ -0,0 +1,44 @@
+use crate::language::{Expression, Object, Token};
+
+use super::Block;
+
+#[derive(Debug, Clone)]
+pub struct MatchStatement {
+    pub value: Expression,
+    pub arms: Vec<MatchArm>,
+    pub line: usize,
+    pub filename: String,
+}
+
+/// `case pattern [if guard]` followed by the statements to run when it matches
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expression>,
+    pub body: Block,
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// `_`, matches anything
+    Wildcard,
+    /// numbers, strings, booleans and `none`
+    Literal(Object),
+    /// `low..high`, includes low and excludes high
+    Range(Object, Object),
+    /// a name bound to the matched value
+    Binding(Token),
+    /// `(first, second)`
+    Tuple(Vec<Pattern>),
+    /// `Point(x, y: py)` matches instances of a class or its subclasses by field
+    Instance {
+        class: Token,
+        fields: Vec<(Token, Pattern)>,
+    },
+    /// `Shape.Circle(r)` matches an enum variant, fields are matched in order when given
+    Variant {
+        enum_name: Token,
+        variant: Token,
+        fields: Option<Vec<Pattern>>,
+    },
+}
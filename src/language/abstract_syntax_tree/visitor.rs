@@ -1,15 +1,19 @@
-use crate::language::{class::ClassStatement, Include};
+use std::sync::Arc;
+
+use crate::language::{
+    class::ClassStatement, enum_statement::EnumStatement, Import, Include, MatchStatement, Token,
+};
 
 use super::{
     expression::{
-        binary::Binary, call::Call, grouping::Grouping, literal::Literal, unary::Unary,
-        variable::Variable, Expression,
+        await_expression::Await, binary::Binary, call::Call, grouping::Grouping, literal::Literal, tuple::Tuple,
+        unary::Unary, variable::Variable, Expression,
     },
     statement::{
-        assignment::{Assign, Get, Set},
-        declaration::VariableDeclaration,
+        assignment::{Assign, DestructuringAssign, Get, Set},
+        declaration::{TupleDeclaration, VariableDeclaration},
         function::FunctionStatement,
-        Block, IfStatement, WhileLoop,
+        Block, ForLoop, IfStatement, WhileLoop,
     },
 };
 
@@ -23,21 +27,32 @@ pub trait ExpressionVisitor {
     fn visit_call(&mut self, math_function: &Call) -> Self::Output;
     fn visit_variable(&mut self, variable: &Variable) -> Self::Output;
     fn visit_assign(&mut self, assign: &Assign) -> Self::Output;
-    fn visit_get(&mut self, get: &Get) -> Self::Output;
+    fn visit_get(&mut self, get: &Arc<Get>) -> Self::Output;
     fn visit_set(&mut self, set: &Set) -> Self::Output;
+    fn visit_tuple(&mut self, tuple: &Tuple) -> Self::Output;
+    fn visit_destructuring_assign(&mut self, assign: &Arc<DestructuringAssign>) -> Self::Output;
+    fn visit_await(&mut self, await_expression: &Await) -> Self::Output;
 }
 
 pub trait StatementVisitor {
     type Output;
 
     fn visit_none(&mut self) -> Self::Output;
-    fn visit_if(&mut self, if_statement: &IfStatement) -> Self::Output;
-    fn visit_while(&mut self, while_loop: &WhileLoop) -> Self::Output;
+    fn visit_if(&mut self, if_statement: &Arc<IfStatement>) -> Self::Output;
+    fn visit_while(&mut self, while_loop: &Arc<WhileLoop>) -> Self::Output;
+    fn visit_for(&mut self, for_loop: &Arc<ForLoop>) -> Self::Output;
     fn visit_block(&mut self, block: &Block) -> Self::Output;
-    fn visit_function_statement(&mut self, function_statement: &FunctionStatement) -> Self::Output;
+    fn visit_function_statement(&mut self, function_statement: &Arc<FunctionStatement>) -> Self::Output;
     fn visit_return(&mut self, return_statement: &Option<(Expression, usize, String)>) -> Self::Output;
+    fn visit_yield(&mut self, yield_statement: &Option<(Expression, usize, String)>) -> Self::Output;
+    fn visit_break(&mut self, keyword: &Token) -> Self::Output;
+    fn visit_continue(&mut self, keyword: &Token) -> Self::Output;
     fn visit_var_declaration(&mut self, var_declaration: &VariableDeclaration) -> Self::Output;
+    fn visit_tuple_declaration(&mut self, tuple_declaration: &Arc<TupleDeclaration>) -> Self::Output;
     fn visit_expression_statement(&mut self, expression_statement: &(Expression, usize, String)) -> Self::Output;
     fn visit_class_statement(&mut self, class_statement: &ClassStatement) -> Self::Output;
+    fn visit_enum_statement(&mut self, enum_statement: &EnumStatement) -> Self::Output;
     fn visit_include(&mut self, include: &Include) -> Self::Output;
+    fn visit_import(&mut self, import: &Import) -> Self::Output;
+    fn visit_match(&mut self, match_statement: &Arc<MatchStatement>) -> Self::Output;
 }
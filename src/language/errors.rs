@@ -1,9 +1,4 @@
-use std::{
-    fmt::Display,
-    sync::{Arc, RwLock},
-};
-
-use super::Object;
+use std::fmt::Display;
 
 #[derive(Debug)]
 pub enum Error {
@@ -11,7 +6,6 @@ pub enum Error {
     Scan(String),
     Interpret(String),
     Runtime(String),
-    Return(Arc<RwLock<Object>>),
     Exit(usize),
 }
 
@@ -25,7 +19,6 @@ impl Display for Error {
                 Self::Scan(description) => description.clone(),
                 Self::Interpret(description) => description.clone(),
                 Self::Runtime(description) => description.clone(),
-                Self::Return(_) => "return".to_string(),
                 Self::Exit(code) => format!("{}", code),
             }
         )
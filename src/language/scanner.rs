@@ -1,7 +1,10 @@
 pub mod object;
+pub mod symbol;
 pub mod token;
+pub mod value;
 
 use crate::language::errors;
+use symbol::Symbol;
 use token::{Token, TokenType};
 
 fn simple_token(token_type: TokenType, line: usize) -> Token {
@@ -9,6 +12,7 @@ fn simple_token(token_type: TokenType, line: usize) -> Token {
         token_type,
         object: object::Object::None,
         line,
+        symbol: None,
     }
 }
 
@@ -71,6 +75,7 @@ impl Scanner {
             token_type: TokenType::Eof,
             object: object::Object::None,
             line: self.line,
+            symbol: None,
         });
 
         
@@ -105,7 +110,18 @@ impl Scanner {
 
                 Ok(simple_token(TokenType::Colon, self.line))
             }
-            '.' => Ok(simple_token(TokenType::Dot, self.line)),
+            '.' => {
+                if self.peek() == '.' {
+                    self.advance();
+                    if self.peek() == '.' {
+                        self.advance();
+                        return Ok(simple_token(TokenType::Ellipsis, self.line));
+                    }
+                    return Ok(simple_token(TokenType::DotDot, self.line));
+                }
+
+                Ok(simple_token(TokenType::Dot, self.line))
+            }
             ',' => Ok(simple_token(TokenType::Comma, self.line)),
             '"' => self.scan_string(),
             '&' => {
@@ -203,6 +219,7 @@ impl Scanner {
                 token_type: TokenType::NewLine,
                 object: object::Object::None,
                 line: self.line,
+                symbol: None,
             })
         } else {
             None
@@ -216,8 +233,9 @@ impl Scanner {
         }
 
         let next = self.peek();
-        // if next character is a decimal point consume all remaining digits
-        if next == '.' {
+        // if next character is a decimal point consume all remaining digits,
+        // a second '.' starts a range instead
+        if next == '.' && self.peek_next() != '.' {
             self.advance();
             while !self.is_at_end() && self.peek().is_ascii_digit() {
                 self.advance();
@@ -266,6 +284,7 @@ impl Scanner {
                 token_type: TokenType::Number,
                 object: object::Object::Number(number),
                 line: self.line,
+                symbol: None,
             })
         } else {
             Err(errors::Error::Scan(format!(
@@ -284,27 +303,41 @@ impl Scanner {
 
         match segment {
             "for" => Ok(simple_token(TokenType::For, self.line)),
+            "in" => Ok(simple_token(TokenType::In, self.line)),
             "if" => Ok(simple_token(TokenType::If, self.line)),
             "else" => Ok(simple_token(TokenType::Else, self.line)),
             "while" => Ok(simple_token(TokenType::While, self.line)),
             "fn" => Ok(simple_token(TokenType::Fn, self.line)),
+            "async" => Ok(simple_token(TokenType::Async, self.line)),
+            "await" => Ok(simple_token(TokenType::Await, self.line)),
             "end" => Ok(simple_token(TokenType::End, self.line)),
             "return" => Ok(simple_token(TokenType::Return, self.line)),
+            "yield" => Ok(simple_token(TokenType::Yield, self.line)),
+            "break" => Ok(simple_token(TokenType::Break, self.line)),
+            "continue" => Ok(simple_token(TokenType::Continue, self.line)),
             "true" => Ok(simple_token(TokenType::True, self.line)),
             "false" => Ok(simple_token(TokenType::False, self.line)),
             "and" => Ok(simple_token(TokenType::And, self.line)),
             "or" => Ok(simple_token(TokenType::Or, self.line)),
             "class" => Ok(simple_token(TokenType::Class, self.line)),
+            "enum" => Ok(simple_token(TokenType::Enum, self.line)),
             "let" => Ok(simple_token(TokenType::Let, self.line)),
+            "const" => Ok(simple_token(TokenType::Const, self.line)),
             "block" => Ok(simple_token(TokenType::Block, self.line)),
             "delete" => Ok(simple_token(TokenType::Delete, self.line)),
             "none" => Ok(simple_token(TokenType::None, self.line)),
             "include" => Ok(simple_token(TokenType::Include, self.line)),
+            "import" => Ok(simple_token(TokenType::Import, self.line)),
+            "from" => Ok(simple_token(TokenType::From, self.line)),
+            "as" => Ok(simple_token(TokenType::As, self.line)),
+            "match" => Ok(simple_token(TokenType::Match, self.line)),
+            "case" => Ok(simple_token(TokenType::Case, self.line)),
 
             _ => Ok(Token {
                 token_type: TokenType::Identifier,
                 object: object::Object::String(segment.to_string()),
                 line: self.line,
+                symbol: Some(Symbol::intern(segment)),
             }),
         }
     }
@@ -323,6 +356,7 @@ impl Scanner {
             token_type: TokenType::String,
             object: object::Object::String(string),
             line: self.line,
+            symbol: None,
         })
     }
 
@@ -331,7 +365,11 @@ impl Scanner {
             return '\0';
         }
 
-        self.source.chars().nth(self.current).unwrap()
+        self.rest().next().unwrap()
+    }
+
+    fn peek_next(&self) -> char {
+        self.rest().nth(1).unwrap_or('\0')
     }
 
     fn advance(&mut self) -> char {
@@ -339,8 +377,14 @@ impl Scanner {
             return '\0';
         }
 
-        self.current += 1;
-        self.source.chars().nth(self.current - 1).unwrap()
+        let character = self.rest().next().unwrap();
+        self.current += character.len_utf8();
+        character
+    }
+
+    /// the characters not scanned yet, `current` is a byte offset into the source
+    fn rest(&self) -> std::str::Chars<'_> {
+        self.source[self.current..].chars()
     }
 
     fn is_at_end(&self) -> bool {
@@ -367,6 +411,7 @@ fn is_identifier_rest(character: char) -> bool {
 #[cfg(test)]
 mod scanner_tests {
     use crate::language::scanner::object::Object;
+    use crate::language::scanner::symbol::Symbol;
     use crate::language::scanner::token::Token;
     use crate::language::scanner::token::TokenType;
 
@@ -383,17 +428,20 @@ mod scanner_tests {
                 Token {
                     token_type: TokenType::Number,
                     object: Object::Number(100.0),
-                    line: 1
+                    line: 1,
+                    symbol: None,
                 },
                 Token {
                     token_type: TokenType::NewLine,
                     object: Object::None,
-                    line: 1
+                    line: 1,
+                    symbol: None,
                 },
                 Token {
                     token_type: TokenType::Eof,
                     object: Object::None,
-                    line: 1
+                    line: 1,
+                    symbol: None,
                 }
             ]
         )
@@ -410,17 +458,20 @@ mod scanner_tests {
                 Token {
                     token_type: TokenType::Identifier,
                     object: Object::String("sin".to_string()),
-                    line: 1
+                    line: 1,
+                    symbol: Some(Symbol::intern("sin")),
                 },
                 Token {
                     token_type: TokenType::NewLine,
                     object: Object::None,
-                    line: 1
+                    line: 1,
+                    symbol: None,
                 },
                 Token {
                     token_type: TokenType::Eof,
                     object: Object::None,
-                    line: 1
+                    line: 1,
+                    symbol: None,
                 }
             ]
         )
@@ -437,37 +488,44 @@ mod scanner_tests {
                 Token {
                     token_type: TokenType::For,
                     object: Object::None,
-                    line: 1
+                    line: 1,
+                    symbol: None,
                 },
                 Token {
                     token_type: TokenType::While,
                     object: Object::None,
-                    line: 1
+                    line: 1,
+                    symbol: None,
                 },
                 Token {
                     token_type: TokenType::NewLine,
                     object: Object::None,
-                    line: 2
+                    line: 2,
+                    symbol: None,
                 },
                 Token {
                     token_type: TokenType::Fn,
                     object: Object::None,
-                    line: 2
+                    line: 2,
+                    symbol: None,
                 },
                 Token {
                     token_type: TokenType::End,
                     object: Object::None,
-                    line: 2
+                    line: 2,
+                    symbol: None,
                 },
                 Token {
                     token_type: TokenType::NewLine,
                     object: Object::None,
-                    line: 2
+                    line: 2,
+                    symbol: None,
                 },
                 Token {
                     token_type: TokenType::Eof,
                     object: Object::None,
-                    line: 2
+                    line: 2,
+                    symbol: None,
                 }
             ]
         )
@@ -484,57 +542,68 @@ mod scanner_tests {
                 Token {
                     token_type: TokenType::Number,
                     object: Object::Number(1.0),
-                    line: 1
+                    line: 1,
+                    symbol: None,
                 },
                 Token {
                     token_type: TokenType::Plus,
                     object: Object::None,
-                    line: 1
+                    line: 1,
+                    symbol: None,
                 },
                 Token {
                     token_type: TokenType::Number,
                     object: Object::Number(2.0),
-                    line: 1
+                    line: 1,
+                    symbol: None,
                 },
                 Token {
                     token_type: TokenType::Slash,
                     object: Object::None,
-                    line: 1
+                    line: 1,
+                    symbol: None,
                 },
                 Token {
                     token_type: TokenType::LeftParen,
                     object: Object::None,
-                    line: 1
+                    line: 1,
+                    symbol: None,
                 },
                 Token {
                     token_type: TokenType::Number,
                     object: Object::Number(3.0),
-                    line: 1
+                    line: 1,
+                    symbol: None,
                 },
                 Token {
                     token_type: TokenType::Plus,
                     object: Object::None,
-                    line: 1
+                    line: 1,
+                    symbol: None,
                 },
                 Token {
                     token_type: TokenType::Number,
                     object: Object::Number(1.0),
-                    line: 1
+                    line: 1,
+                    symbol: None,
                 },
                 Token {
                     token_type: TokenType::RightParen,
                     object: Object::None,
-                    line: 1
+                    line: 1,
+                    symbol: None,
                 },
                 Token {
                     token_type: TokenType::NewLine,
                     object: Object::None,
-                    line: 1
+                    line: 1,
+                    symbol: None,
                 },
                 Token {
                     token_type: TokenType::Eof,
                     object: Object::None,
-                    line: 1
+                    line: 1,
+                    symbol: None,
                 }
             ]
         )
@@ -557,12 +626,14 @@ mod scanner_tests {
                 Token {
                     token_type: TokenType::NewLine,
                     object: Object::None,
-                    line: 1
+                    line: 1,
+                    symbol: None,
                 },
                 Token {
                     token_type: TokenType::Eof,
                     object: Object::None,
-                    line: 1
+                    line: 1,
+                    symbol: None,
                 }
             ]
         )
@@ -584,14 +655,70 @@ mod scanner_tests {
                 Token {
                     token_type: TokenType::NewLine,
                     object: Object::None,
-                    line: 1
+                    line: 1,
+                    symbol: None,
                 },
                 Token {
                     token_type: TokenType::Eof,
                     object: Object::None,
-                    line: 1
+                    line: 1,
+                    symbol: None,
                 }
             ]
         )
     }
+
+    #[test]
+    fn test_scanner_range() {
+        let source = "1..2.5";
+        let tokens = Scanner::new().scan_tokens(source).unwrap();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token {
+                    token_type: TokenType::Number,
+                    object: Object::Number(1.0),
+                    line: 1,
+                    symbol: None,
+                },
+                simple_token(TokenType::DotDot, 1),
+                Token {
+                    token_type: TokenType::Number,
+                    object: Object::Number(2.5),
+                    line: 1,
+                    symbol: None,
+                },
+                simple_token(TokenType::NewLine, 1),
+                simple_token(TokenType::Eof, 1),
+            ]
+        )
+    }
+
+    #[test]
+    fn test_scanner_unicode_string() {
+        let source = "\"héllo\" + 1";
+        let tokens = Scanner::new().scan_tokens(source).unwrap();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token {
+                    token_type: TokenType::String,
+                    object: Object::String("héllo".to_string()),
+                    line: 1,
+                    symbol: None,
+                },
+                simple_token(TokenType::Plus, 1),
+                Token {
+                    token_type: TokenType::Number,
+                    object: Object::Number(1.0),
+                    line: 1,
+                    symbol: None,
+                },
+                simple_token(TokenType::NewLine, 1),
+                simple_token(TokenType::Eof, 1),
+            ]
+        )
+    }
 }
@@ -1,41 +1,108 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     fmt::Display,
-    sync::{Arc, RwLock},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Condvar, Mutex, RwLock,
+    },
+    thread::JoinHandle,
 };
 
 use interpreter::AstInterpreter;
 
 use crate::language::{
-    abstract_syntax_tree::{environment::Environment, interpreter},
+    abstract_syntax_tree::{
+        coroutine::Coroutine,
+        environment::Environment,
+        heap::{self, Trace, Tracer},
+        inline_cache::InlineCache,
+        interpreter,
+    },
     errors,
     function::FunctionStatement,
-    Token,
 };
 
-pub type WrappedObject = Arc<RwLock<Object>>;
+use super::{symbol::Symbol, value::Value};
+
+/// the value type used throughout the interpreter, see `Value`
+pub type WrappedObject = Value;
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub enum Object {
     None,
+    /// what `next()` returns once an iterator is exhausted, `done` in scripts
+    Done,
     Bool(bool),
     Number(f64),
     String(String),
     Callable(Callable),
     Instance(Instance),
+    Tuple(TupleObject),
+    Enum(EnumObject),
+    Variant(EnumValue),
+    Generator(GeneratorObject),
+    Iterator(IteratorObject),
+    Task(TaskObject),
+    Thread(ThreadHandle),
+    Channel(ChannelObject),
+    Mutex(MutexObject),
+    Module(ModuleObject),
 }
 
 impl Object {
     pub fn is_truthy(&self) -> bool {
         match self {
-            Self::None => false,
+            Self::None | Self::Done => false,
             Self::Bool(boolean) => *boolean,
             _ => true,
         }
     }
 
+    /// stores none, booleans and numbers inline and everything else on the heap
     pub fn wrap(self) -> WrappedObject {
-        Arc::new(RwLock::new(self))
+        match self {
+            Self::None => return Value::None,
+            Self::Bool(boolean) => return Value::Bool(boolean),
+            Self::Number(number) => return Value::Number(number),
+            _ => {}
+        }
+
+        let holds_references = self.holds_references();
+        let object = Arc::new(RwLock::new(self));
+
+        if holds_references {
+            heap::track(&object);
+        }
+        Value::Heap(object)
+    }
+
+    /// values that may reference other values are tracked by the heap
+    fn holds_references(&self) -> bool {
+        !matches!(
+            self,
+            Self::None | Self::Done | Self::Bool(_) | Self::Number(_) | Self::String(_) | Self::Enum(_)
+        )
+    }
+
+    /// reports the values referenced by this object to the collector
+    pub fn trace(&self, tracer: &mut Tracer) {
+        match self {
+            Self::Callable(callable) => callable.trace(tracer),
+            Self::Instance(instance) => {
+                instance.fields.values().for_each(|field| field.trace(tracer));
+                instance.class.trace(tracer);
+            }
+            Self::Tuple(tuple) => tuple.elements.iter().for_each(|element| element.trace(tracer)),
+            Self::Variant(value) => value.fields.iter().for_each(|(_, field)| field.trace(tracer)),
+            Self::Generator(generator) => tracer.visit(&generator.coroutine),
+            Self::Iterator(iterator) => tracer.visit(&iterator.items),
+            Self::Task(task) => tracer.visit(&task.state),
+            Self::Thread(thread) => tracer.visit(&thread.state),
+            Self::Channel(channel) => tracer.visit(&channel.shared),
+            Self::Mutex(mutex) => tracer.visit(&mutex.shared),
+            Self::Module(module) => tracer.visit(&module.environment),
+            Self::None | Self::Done | Self::Bool(_) | Self::Number(_) | Self::String(_) | Self::Enum(_) => {}
+        }
     }
 
     pub fn is_class(&self) -> bool {
@@ -50,6 +117,14 @@ impl Object {
         matches!(self, Self::None)
     }
 
+    pub fn is_done(&self) -> bool {
+        matches!(self, Self::Done)
+    }
+
+    pub fn is_tuple(&self) -> bool {
+        matches!(self, Self::Tuple(_))
+    }
+
     /// returns true if object can be copied to create another object,
     /// returns false if object should be referenced instead
     pub fn prefers_copy(&self) -> bool {
@@ -61,30 +136,92 @@ impl Display for Object {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let description = match self {
             Self::None => "none".to_string(),
+            Self::Done => "done".to_string(),
             Self::Number(number) => number.to_string(),
             Self::String(string) => string.clone(),
             Self::Bool(boolean) => boolean.to_string(),
             Self::Callable(callable) => callable.to_string(),
             Self::Instance(instance) => instance.to_string(),
+            Self::Tuple(tuple) => tuple.to_string(),
+            Self::Enum(enum_object) => enum_object.to_string(),
+            Self::Variant(value) => value.to_string(),
+            Self::Generator(generator) => generator.to_string(),
+            Self::Iterator(iterator) => iterator.to_string(),
+            Self::Task(task) => task.to_string(),
+            Self::Thread(thread) => thread.to_string(),
+            Self::Channel(channel) => channel.to_string(),
+            Self::Mutex(mutex) => mutex.to_string(),
+            Self::Module(module) => module.to_string(),
         };
 
         write!(f, "{}", description)
     }
 }
 
+/// the number of positional arguments a callable accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Arity {
+    pub min: usize,
+    /// `None` when any number of extra arguments is accepted
+    pub max: Option<usize>,
+}
+
+impl Arity {
+    pub fn exact(count: usize) -> Self {
+        Self {
+            min: count,
+            max: Some(count),
+        }
+    }
+
+    pub fn at_least(min: usize) -> Self {
+        Self { min, max: None }
+    }
+
+    pub fn accepts(&self, count: usize) -> bool {
+        count >= self.min && self.max.is_none_or(|max| count <= max)
+    }
+}
+
+impl Display for Arity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{}", max),
+            Some(max) => write!(f, "{} to {}", self.min, max),
+            None => write!(f, "at least {}", self.min),
+        }
+    }
+}
+
+/// arguments passed by parameter name
+pub type KeywordArguments = [(Symbol, WrappedObject)];
+
 #[derive(Debug, Clone)]
 pub enum Callable {
     NativeCall(NativeCall),
     DefinedCall(DefinedCall),
     Class(ClassObject),
+    Variant(VariantConstructor),
+    NativeMethod(NativeMethod),
 }
 
 impl Callable {
-    pub fn arity(&self) -> i8 {
+    fn trace(&self, tracer: &mut Tracer) {
+        match self {
+            Self::DefinedCall(defined_call) => tracer.visit(&defined_call.closure),
+            Self::Class(class) => class.trace(tracer),
+            Self::NativeMethod(method) => method.receiver.trace(tracer),
+            Self::NativeCall(_) | Self::Variant(_) => {}
+        }
+    }
+
+    pub fn arity(&self) -> Arity {
         match self {
             Self::NativeCall(native_call) => native_call.arity(),
             Self::DefinedCall(defined_call) => defined_call.arity(),
             Self::Class(class_obj) => class_obj.arity(),
+            Self::Variant(constructor) => constructor.arity(),
+            Self::NativeMethod(native_method) => native_method.arity(),
         }
     }
 
@@ -92,11 +229,32 @@ impl Callable {
         &self,
         interpreter: &mut AstInterpreter,
         arguments: &Vec<WrappedObject>,
+        keyword_arguments: &KeywordArguments,
     ) -> Result<WrappedObject, errors::Error> {
         match self {
-            Self::NativeCall(native_call) => native_call.call(interpreter, arguments),
-            Self::DefinedCall(defined_call) => defined_call.call(interpreter, arguments),
-            Self::Class(class) => class.call(interpreter, arguments),
+            Self::NativeCall(native_call) => {
+                if let Some((name, _)) = keyword_arguments.first() {
+                    return Err(errors::Error::Runtime(format!(
+                        "{} got an unexpected keyword argument {}",
+                        native_call.name, name
+                    )));
+                }
+                native_call.call(interpreter, arguments)
+            }
+            Self::DefinedCall(defined_call) => {
+                defined_call.call(interpreter, arguments, keyword_arguments)
+            }
+            Self::Class(class) => class.call(interpreter, arguments, keyword_arguments),
+            Self::Variant(constructor) => constructor.call(arguments, keyword_arguments),
+            Self::NativeMethod(native_method) => {
+                if let Some((name, _)) = keyword_arguments.first() {
+                    return Err(errors::Error::Runtime(format!(
+                        "{} got an unexpected keyword argument {}",
+                        native_method.name, name
+                    )));
+                }
+                native_method.call(interpreter, arguments)
+            }
         }
     }
 }
@@ -122,12 +280,14 @@ impl Display for Callable {
                 Self::Class(class) => class.to_string(),
                 Self::DefinedCall(defined_call) => defined_call.to_string(),
                 Self::NativeCall(native_call) => native_call.to_string(),
+                Self::Variant(constructor) => constructor.to_string(),
+                Self::NativeMethod(native_method) => native_method.to_string(),
             }
         )
     }
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone)]
 pub struct NativeCall {
     pub name: String,
     pub arity: i8,
@@ -152,8 +312,11 @@ impl NativeCall {
             function,
         }
     }
-    pub fn arity(&self) -> i8 {
-        self.arity
+    pub fn arity(&self) -> Arity {
+        match usize::try_from(self.arity) {
+            Ok(count) => Arity::exact(count),
+            Err(_) => Arity::at_least(0),
+        }
     }
 
     pub fn call(
@@ -161,26 +324,107 @@ impl NativeCall {
         interpreter: &mut AstInterpreter,
         arguments: &Vec<WrappedObject>,
     ) -> Result<WrappedObject, errors::Error> {
+        check_arity(&self.name, self.arity(), arguments)?;
         (self.function)(interpreter, arguments)
     }
 }
 
+impl PartialEq for NativeCall {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.arity == other.arity
+    }
+}
+
+impl PartialOrd for NativeCall {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.name.partial_cmp(&other.name)
+    }
+}
+
 impl Display for NativeCall {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "function: {}", self.name)
     }
 }
 
+/// the body of a native method, called with the object it is bound to
+pub type NativeFunction = fn(
+    interpreter: &mut AstInterpreter,
+    receiver: &WrappedObject,
+    arguments: &[WrappedObject],
+) -> Result<WrappedObject, errors::Error>;
+
+/// a native function bound to the object it was looked up on, `generator.next`
+#[derive(Debug, Clone)]
+pub struct NativeMethod {
+    pub name: String,
+    pub arity: i8,
+    pub receiver: WrappedObject,
+    pub function: NativeFunction,
+}
+
+impl NativeMethod {
+    pub fn new(
+        name: String,
+        arity: i8,
+        receiver: WrappedObject,
+        function: NativeFunction,
+    ) -> Self {
+        Self {
+            name,
+            arity,
+            receiver,
+            function,
+        }
+    }
+
+    pub fn arity(&self) -> Arity {
+        match usize::try_from(self.arity) {
+            Ok(count) => Arity::exact(count),
+            Err(_) => Arity::at_least(0),
+        }
+    }
+
+    pub fn call(
+        &self,
+        interpreter: &mut AstInterpreter,
+        arguments: &[WrappedObject],
+    ) -> Result<WrappedObject, errors::Error> {
+        check_arity(&self.name, self.arity(), arguments)?;
+        (self.function)(interpreter, &self.receiver, arguments)
+    }
+}
+
+/// natives take their arguments by position, without checking how many there are
+fn check_arity(name: &str, arity: Arity, arguments: &[WrappedObject]) -> Result<(), errors::Error> {
+    if arity.accepts(arguments.len()) {
+        return Ok(());
+    }
+
+    Err(errors::Error::Runtime(format!(
+        "{} takes {} arguments but {} were given",
+        name,
+        arity,
+        arguments.len()
+    )))
+}
+
+impl Display for NativeMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "method: {}", self.name)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct DefinedCall {
-    declaration: Box<FunctionStatement>,
+    declaration: Arc<FunctionStatement>,
     closure: Arc<RwLock<Environment>>,
     pub initializer: bool,
 }
 
 impl DefinedCall {
     pub fn new(
-        declaration: Box<FunctionStatement>,
+        declaration: Arc<FunctionStatement>,
         closure: Arc<RwLock<Environment>>,
         initializer: bool,
     ) -> Self {
@@ -190,91 +434,245 @@ impl DefinedCall {
             initializer,
         }
     }
-    pub fn arity(&self) -> i8 {
-        self.declaration.parameters.len() as i8
+    pub fn arity(&self) -> Arity {
+        let parameters = &self.declaration.parameters;
+        let required = parameters
+            .iter()
+            .filter(|parameter| parameter.default.is_none() && !parameter.variadic)
+            .count();
+
+        if parameters.iter().any(|parameter| parameter.variadic) {
+            return Arity::at_least(required);
+        }
+
+        Arity {
+            min: required,
+            max: Some(parameters.len()),
+        }
     }
 
     pub fn bind(&self, instance: WrappedObject) -> DefinedCall {
         let mut environment = Environment::with_parent(Arc::clone(&self.closure));
-        environment.declare_value("this", instance);
-        let closure = Arc::new(RwLock::new(environment));
+        environment.declare_value("this", instance).unwrap();
+        let closure = environment.wrap();
 
-        DefinedCall::new(self.declaration.clone(), closure, self.initializer)
+        DefinedCall::new(Arc::clone(&self.declaration), closure, self.initializer)
     }
 
-    pub fn call(
+    /// declares every parameter in `environment` from the positional and keyword arguments,
+    /// evaluating defaults for the ones that were not passed
+    fn bind_arguments(
         &self,
         interpreter: &mut AstInterpreter,
-        arguments: &Vec<WrappedObject>,
-    ) -> Result<WrappedObject, errors::Error> {
-        let mut environment = Environment::with_parent(Arc::clone(&self.closure));
-        for (parameter, value) in &self
-            .declaration
-            .parameters
-            .iter()
-            .zip(arguments)
-            .collect::<Vec<_>>()
-        {
-            environment.declare_value(parameter.object.to_string().as_str(), Arc::clone(*value));
+        environment: &Arc<RwLock<Environment>>,
+        arguments: &[WrappedObject],
+        keyword_arguments: &KeywordArguments,
+    ) -> Result<(), errors::Error> {
+        let function_name = &self.declaration.name.object;
+        let mut positional = arguments.iter();
+
+        for parameter in &self.declaration.parameters {
+            let name = parameter.name.name();
+
+            if parameter.variadic {
+                let rest = positional.by_ref().cloned().collect();
+                let rest = Object::Tuple(TupleObject::new(rest)).wrap();
+                environment.write().unwrap().declare_value(name, rest)?;
+                continue;
+            }
+
+            let keyword = keyword_arguments
+                .iter()
+                .find(|(keyword, _)| *keyword == name)
+                .map(|(_, value)| value.clone());
+
+            let value = match (positional.next(), keyword) {
+                (Some(_), Some(_)) => {
+                    return Err(errors::Error::Runtime(format!(
+                        "{} got multiple values for argument {}",
+                        function_name, name
+                    )))
+                }
+                (Some(value), None) => value.clone(),
+                (None, Some(value)) => value,
+                (None, None) => match &parameter.default {
+                    Some(default) => {
+                        interpreter.evaluate_in(default, Arc::clone(environment))?
+                    }
+                    None => {
+                        return Err(errors::Error::Runtime(format!(
+                            "{} missing required argument {}",
+                            function_name, name
+                        )))
+                    }
+                },
+            };
+
+            environment.write().unwrap().declare_value(name, value)?;
+        }
+
+        if positional.next().is_some() {
+            return Err(errors::Error::Runtime(format!(
+                "{} takes {} positional arguments but {} were given",
+                function_name,
+                self.arity(),
+                arguments.len()
+            )));
+        }
+
+        for (keyword, _) in keyword_arguments {
+            let known = self
+                .declaration
+                .parameters
+                .iter()
+                .any(|parameter| !parameter.variadic && parameter.name.name() == *keyword);
+
+            if !known {
+                return Err(errors::Error::Runtime(format!(
+                    "{} got an unexpected keyword argument {}",
+                    function_name, keyword
+                )));
+            }
         }
 
-        let new_environment = Arc::new(RwLock::new(environment));
+        Ok(())
+    }
+
+    pub fn declaration(&self) -> &Arc<FunctionStatement> {
+        &self.declaration
+    }
+
+    pub fn call(
+        &self,
+        interpreter: &mut AstInterpreter,
+        arguments: &[WrappedObject],
+        keyword_arguments: &KeywordArguments,
+    ) -> Result<WrappedObject, errors::Error> {
+        interpreter.call_function(self, arguments, keyword_arguments)
+    }
 
-        let result = interpreter.execute_block(&self.declaration.body, new_environment);
+    /// binds the arguments and starts the task of an async function or the generator of a
+    /// generator function, the body of any other function is left for the interpreter to run
+    pub fn invoke(
+        &self,
+        interpreter: &mut AstInterpreter,
+        arguments: &[WrappedObject],
+        keyword_arguments: &KeywordArguments,
+    ) -> Result<Invocation, errors::Error> {
+        let environment = Environment::with_parent(Arc::clone(&self.closure));
+        let new_environment = environment.wrap();
+        self.bind_arguments(interpreter, &new_environment, arguments, keyword_arguments)?;
 
-        if result.is_ok() {
-            return Ok(Object::None.wrap());
+        if self.declaration.asynchronous {
+            let coroutine = Coroutine::asynchronous(self.declaration.body.statements.clone(), new_environment);
+            let name = self.declaration.name.object.to_string();
+            let task = Object::Task(TaskObject::new(name, TaskState::Running(Box::new(coroutine)))).wrap();
+            interpreter.event_loop.spawn(task.clone());
+            return Ok(Invocation::Finished(task));
         }
 
-        let error = result.unwrap_err();
-        if let errors::Error::Return(object) = error {
-            return Ok(object);
+        if self.declaration.generator {
+            let coroutine = Coroutine::new(self.declaration.body.statements.clone(), new_environment);
+            let name = self.declaration.name.object.to_string();
+            let generator = Object::Generator(GeneratorObject::new(name, coroutine)).wrap();
+            return Ok(Invocation::Finished(generator));
         }
 
-        Err(error)
+        Ok(Invocation::Body(new_environment))
     }
 }
 
+/// how a call of a defined function started
+pub enum Invocation {
+    /// the call already has its result, the task of an async function or a generator
+    Finished(WrappedObject),
+    /// the body is left for the interpreter to run in this scope holding the arguments
+    Body(Arc<RwLock<Environment>>),
+}
+
 impl Display for DefinedCall {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "function: {}", self.declaration.name.object)
     }
 }
 
+/// identifies a class, every class created gets a new id
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClassId(u64);
+
+impl ClassId {
+    fn next() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ClassObject {
+    id: ClassId,
     name: String,
     superclass: Option<WrappedObject>,
-    methods: HashMap<String, WrappedObject>,
+    methods: HashMap<Symbol, WrappedObject>,
 }
 
 impl ClassObject {
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Some(superclass) = &self.superclass {
+            superclass.trace(tracer);
+        }
+        self.methods.values().for_each(|method| method.trace(tracer));
+    }
+
     pub fn new(
         name: String,
         superclass: Option<WrappedObject>,
-        methods: HashMap<String, WrappedObject>,
+        methods: HashMap<Symbol, WrappedObject>,
     ) -> Self {
         Self {
+            id: ClassId::next(),
             name,
             superclass,
             methods,
         }
     }
 
-    pub fn arity(&self) -> i8 {
-        if let Some(initializer) = self.methods.get("init") {
+    pub fn id(&self) -> ClassId {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn arity(&self) -> Arity {
+        if let Some(initializer) = self.methods.get(&Symbol::intern("init")) {
             let object_binding = initializer.read();
             if let Object::Callable(Callable::DefinedCall(method)) = &*object_binding.unwrap() {
                 return method.arity();
             }
         }
 
-        0
+        Arity::exact(0)
+    }
+
+    /// true when this class or one of its superclasses is `class`
+    pub fn inherits_from(&self, class: ClassId) -> bool {
+        if self.id == class {
+            return true;
+        }
+
+        if let Some(superclass) = &self.superclass {
+            if let Object::Callable(Callable::Class(superclass)) = &*superclass.read().unwrap() {
+                return superclass.inherits_from(class);
+            }
+        }
+
+        false
     }
 
-    pub fn find_method(&self, method_name: &str) -> Option<WrappedObject> {
-        if let Some(method) = self.methods.get(method_name) {
-            return Some(Arc::clone(method));
+    pub fn find_method(&self, method_name: Symbol) -> Option<WrappedObject> {
+        if let Some(method) = self.methods.get(&method_name) {
+            return Some(method.clone());
         }
 
         if let Some(superclass) = &self.superclass {
@@ -290,24 +688,28 @@ impl ClassObject {
     pub fn call(
         &self,
         interpreter: &mut AstInterpreter,
-        arguments: &Vec<WrappedObject>,
+        arguments: &[WrappedObject],
+        keyword_arguments: &KeywordArguments,
     ) -> Result<WrappedObject, errors::Error> {
+        interpreter.call_class(self, arguments, keyword_arguments)
+    }
+
+    /// creates an instance along with the class's initializer bound to it, if it has one
+    pub fn instantiate(&self, interpreter: &mut AstInterpreter) -> (WrappedObject, Option<DefinedCall>) {
         let instance_id = interpreter.id_maker.get_new_id();
         let instance = Instance::new(instance_id, self.clone());
         let instance = Object::Instance(instance).wrap();
 
-        let initializer = self.methods.get("init");
-        if let Some(initializer) = initializer {
-            let initializer = Arc::clone(initializer);
-            let binding = initializer.read().unwrap();
-
-            if let Object::Callable(Callable::DefinedCall(defined_call)) = &*binding {
-                let bound_call = defined_call.bind(Arc::clone(&instance));
-                bound_call.call(interpreter, arguments)?;
+        let initializer = self.methods.get(&Symbol::intern("init")).and_then(|initializer| {
+            match &*initializer.read().unwrap() {
+                Object::Callable(Callable::DefinedCall(defined_call)) => {
+                    Some(defined_call.bind(instance.clone()))
+                }
+                _ => None,
             }
-        }
+        });
 
-        Ok(instance)
+        (instance, initializer)
     }
 }
 
@@ -317,21 +719,21 @@ impl Display for ClassObject {
     }
 }
 
-#[derive(Debug, Default)]
+/// hands out instance ids, clones share the counter so ids stay unique across threads
+#[derive(Debug, Default, Clone)]
 pub struct InstanceIDCreator {
-    current_id: u128,
+    current_id: Arc<Mutex<u128>>,
 }
 
 impl InstanceIDCreator {
     pub fn new() -> Self {
-        Self { current_id: 0 }
+        Self::default()
     }
 
     pub fn get_new_id(&mut self) -> InstanceID {
-        let id = InstanceID {
-            value: self.current_id,
-        };
-        self.current_id += 1;
+        let mut current_id = self.current_id.lock().unwrap();
+        let id = InstanceID { value: *current_id };
+        *current_id += 1;
         id
     }
 }
@@ -351,7 +753,7 @@ impl Display for InstanceID {
 pub struct Instance {
     pub id: InstanceID,
     class: ClassObject,
-    fields: HashMap<String, WrappedObject>,
+    fields: HashMap<Symbol, WrappedObject>,
 }
 
 impl Instance {
@@ -363,30 +765,60 @@ impl Instance {
         }
     }
 
-    pub fn get(&self, name_token: &Token) -> Result<WrappedObject, errors::Error> {
-        let name = name_token.object.to_string();
+    pub fn is_instance_of(&self, class: ClassId) -> bool {
+        self.class.inherits_from(class)
+    }
+
+    /// looks up a field or a method, methods are bound to `receiver` as `this`
+    pub fn get(&self, name: Symbol, receiver: &WrappedObject) -> Result<WrappedObject, errors::Error> {
+        self.get_cached(name, receiver, &InlineCache::default())
+    }
+
+    /// like `get`, but finds methods through `cache` and remembers the method found in it
+    pub fn get_cached(
+        &self,
+        name: Symbol,
+        receiver: &WrappedObject,
+        cache: &InlineCache,
+    ) -> Result<WrappedObject, errors::Error> {
         if let Some(field) = self.fields.get(&name) {
-            return Ok(Arc::clone(field));
+            return Ok(field.clone());
         }
 
-        if let Some(method) = self.class.find_method(&name) {
-            return Ok(Arc::clone(&method));
+        let method = match cache.get(self.class.id()) {
+            Some(method) => method,
+            None => {
+                let Some(method) = self.class.find_method(name) else {
+                    return Err(errors::Error::Runtime(format!(
+                        "Undefined property {}",
+                        name
+                    )));
+                };
+                cache.set(self.class.id(), &method);
+                method
+            }
+        };
+
+        if let Object::Callable(Callable::DefinedCall(defined_call)) = &*method.read().unwrap() {
+            let bound_call = defined_call.bind(receiver.clone());
+            return Ok(Object::Callable(Callable::DefinedCall(bound_call)).wrap());
         }
 
-        Err(errors::Error::Runtime(format!(
-            "Undefined property {}",
-            name
-        )))
+        Ok(method)
+    }
+
+    pub fn has_property(&self, name: Symbol) -> bool {
+        self.fields.contains_key(&name) || self.class.find_method(name).is_some()
     }
 
-    pub fn set(&mut self, name_token: Token, value: WrappedObject) {
+    pub fn set(&mut self, name: Symbol, value: WrappedObject) {
         #[cfg(feature = "debug")]
         println!(
             "(dbg) setting field {} = {}",
-            name_token.object.to_string(),
+            name,
             value.read().unwrap().to_string()
         );
-        self.fields.insert(name_token.object.to_string(), value);
+        self.fields.insert(name, value);
     }
 }
 
@@ -418,3 +850,1082 @@ impl PartialOrd for Instance {
         self.id.partial_cmp(&other.id)
     }
 }
+
+/// an immutable fixed size group of values, `(a, b)`
+#[derive(Debug, Clone, Default)]
+pub struct TupleObject {
+    pub elements: Vec<WrappedObject>,
+}
+
+impl TupleObject {
+    pub fn new(elements: Vec<WrappedObject>) -> Self {
+        Self { elements }
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+}
+
+impl Display for TupleObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let elements = self
+            .elements
+            .iter()
+            .map(|element| element.read().unwrap().to_string())
+            .collect::<Vec<_>>();
+
+        if elements.len() == 1 {
+            return write!(f, "({},)", elements[0]);
+        }
+
+        write!(f, "({})", elements.join(", "))
+    }
+}
+
+impl PartialEq for TupleObject {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .elements
+                .iter()
+                .zip(&other.elements)
+                .all(|(left, right)| *left.read().unwrap() == *right.read().unwrap())
+    }
+}
+
+impl PartialOrd for TupleObject {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        for (left, right) in self.elements.iter().zip(&other.elements) {
+            let ordering = left.read().unwrap().partial_cmp(&*right.read().unwrap())?;
+            if ordering != std::cmp::Ordering::Equal {
+                return Some(ordering);
+            }
+        }
+
+        self.len().partial_cmp(&other.len())
+    }
+}
+
+/// identifies an enum, every enum declared gets a new id
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EnumId(u64);
+
+impl EnumId {
+    fn next() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// an enum type, its variants are reached through `Get`, `Shape.Circle`
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumObject {
+    id: EnumId,
+    pub name: String,
+    /// variant names with their field names, `None` for plain variants
+    variants: Vec<(String, Option<Vec<String>>)>,
+}
+
+impl EnumObject {
+    pub fn new(name: String, variants: Vec<(String, Option<Vec<String>>)>) -> Self {
+        Self {
+            id: EnumId::next(),
+            name,
+            variants,
+        }
+    }
+
+    pub fn id(&self) -> EnumId {
+        self.id
+    }
+
+    /// returns the value of a plain variant or the constructor of a variant with fields
+    pub fn get(&self, name: &str) -> Result<WrappedObject, errors::Error> {
+        let variant = self.variants.iter().find(|(variant, _)| variant == name);
+
+        match variant {
+            Some((variant, None)) => Ok(Object::Variant(EnumValue::new(
+                self.id,
+                self.name.clone(),
+                variant.clone(),
+                Vec::new(),
+            ))
+            .wrap()),
+            Some((variant, Some(fields))) => {
+                let constructor =
+                    VariantConstructor::new(self, variant.clone(), fields.clone());
+                Ok(Object::Callable(Callable::Variant(constructor)).wrap())
+            }
+            None => Err(errors::Error::Runtime(format!(
+                "{} has no variant {}",
+                self.name, name
+            ))),
+        }
+    }
+}
+
+impl PartialOrd for EnumObject {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        (self == other).then_some(std::cmp::Ordering::Equal)
+    }
+}
+
+impl Display for EnumObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "enum : {}", self.name)
+    }
+}
+
+/// builds a variant carrying fields, `Shape.Circle(2)`
+#[derive(Debug, Clone)]
+pub struct VariantConstructor {
+    enum_id: EnumId,
+    enum_name: String,
+    variant: String,
+    fields: Vec<String>,
+}
+
+impl VariantConstructor {
+    pub fn new(enum_object: &EnumObject, variant: String, fields: Vec<String>) -> Self {
+        Self {
+            enum_id: enum_object.id,
+            enum_name: enum_object.name.clone(),
+            variant,
+            fields,
+        }
+    }
+
+    pub fn arity(&self) -> Arity {
+        Arity::exact(self.fields.len())
+    }
+
+    pub fn call(
+        &self,
+        arguments: &[WrappedObject],
+        keyword_arguments: &KeywordArguments,
+    ) -> Result<WrappedObject, errors::Error> {
+        let mut positional = arguments.iter();
+        let mut values = Vec::new();
+
+        for field in &self.fields {
+            let keyword = keyword_arguments
+                .iter()
+                .find(|(keyword, _)| keyword.as_str() == field)
+                .map(|(_, value)| value);
+
+            let value = match (positional.next(), keyword) {
+                (Some(value), None) | (None, Some(value)) => value.clone(),
+                (Some(_), Some(_)) => {
+                    return Err(errors::Error::Runtime(format!(
+                        "{} got multiple values for field {}",
+                        self, field
+                    )))
+                }
+                (None, None) => {
+                    return Err(errors::Error::Runtime(format!(
+                        "{} missing field {}",
+                        self, field
+                    )))
+                }
+            };
+            values.push((field.clone(), value));
+        }
+
+        if positional.next().is_some() {
+            return Err(errors::Error::Runtime(format!(
+                "{} takes {} fields but {} were given",
+                self,
+                self.fields.len(),
+                arguments.len()
+            )));
+        }
+
+        if let Some((keyword, _)) = keyword_arguments
+            .iter()
+            .find(|(keyword, _)| !self.fields.iter().any(|field| keyword.as_str() == field))
+        {
+            return Err(errors::Error::Runtime(format!(
+                "{} has no field {}",
+                self, keyword
+            )));
+        }
+
+        let value = EnumValue::new(
+            self.enum_id,
+            self.enum_name.clone(),
+            self.variant.clone(),
+            values,
+        );
+        Ok(Object::Variant(value).wrap())
+    }
+}
+
+impl Display for VariantConstructor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.enum_name, self.variant)
+    }
+}
+
+/// a value of an enum type, `Shape.Red` or `Shape.Circle(radius: 2)`
+#[derive(Debug, Clone)]
+pub struct EnumValue {
+    pub enum_id: EnumId,
+    pub enum_name: String,
+    pub variant: String,
+    pub fields: Vec<(String, WrappedObject)>,
+}
+
+impl EnumValue {
+    pub fn new(
+        enum_id: EnumId,
+        enum_name: String,
+        variant: String,
+        fields: Vec<(String, WrappedObject)>,
+    ) -> Self {
+        Self {
+            enum_id,
+            enum_name,
+            variant,
+            fields,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<WrappedObject, errors::Error> {
+        if let Some((_, value)) = self.fields.iter().find(|(field, _)| field == name) {
+            return Ok(value.clone());
+        }
+
+        Err(errors::Error::Runtime(format!(
+            "Undefined property {} on {}.{}",
+            name, self.enum_name, self.variant
+        )))
+    }
+}
+
+impl Display for EnumValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.enum_name, self.variant)?;
+        if self.fields.is_empty() {
+            return Ok(());
+        }
+
+        let fields = self
+            .fields
+            .iter()
+            .map(|(name, value)| format!("{}: {}", name, value.read().unwrap()))
+            .collect::<Vec<_>>();
+        write!(f, "({})", fields.join(", "))
+    }
+}
+
+impl PartialEq for EnumValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.enum_id == other.enum_id
+            && self.variant == other.variant
+            && self.fields.len() == other.fields.len()
+            && self
+                .fields
+                .iter()
+                .zip(&other.fields)
+                .all(|((_, left), (_, right))| *left.read().unwrap() == *right.read().unwrap())
+    }
+}
+
+impl PartialOrd for EnumValue {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        (self == other).then_some(std::cmp::Ordering::Equal)
+    }
+}
+
+/// the suspended body of a function containing `yield`, resumed by calling `next()`
+#[derive(Debug, Clone)]
+pub struct GeneratorObject {
+    name: String,
+    coroutine: Arc<Mutex<Coroutine>>,
+}
+
+impl GeneratorObject {
+    pub fn new(name: String, coroutine: Coroutine) -> Self {
+        Self {
+            name,
+            coroutine: Arc::new(Mutex::new(coroutine)),
+        }
+    }
+
+    pub fn get(&self, name: &str, receiver: &WrappedObject) -> Result<WrappedObject, errors::Error> {
+        match name {
+            "next" => {
+                let method = NativeMethod::new(name.to_string(), 0, receiver.clone(), generator_next);
+                Ok(Object::Callable(Callable::NativeMethod(method)).wrap())
+            }
+            "iter" => Ok(iterator_self(receiver)),
+            _ => Err(errors::Error::Runtime(format!(
+                "Undefined property {} on generator",
+                name
+            ))),
+        }
+    }
+
+    /// runs the generator to its next `yield`, returning the yielded value or nothing once it
+    /// has finished
+    pub fn resume(&self, interpreter: &mut AstInterpreter) -> Result<Option<WrappedObject>, errors::Error> {
+        let Ok(mut coroutine) = self.coroutine.try_lock() else {
+            return Err(errors::Error::Runtime(format!(
+                "generator {} is already running",
+                self.name
+            )));
+        };
+
+        coroutine.resume(interpreter)
+    }
+}
+
+fn generator_next(
+    interpreter: &mut AstInterpreter,
+    receiver: &WrappedObject,
+    _arguments: &[WrappedObject],
+) -> Result<WrappedObject, errors::Error> {
+    let generator = match &*receiver.read().unwrap() {
+        Object::Generator(generator) => generator.clone(),
+        _ => return Err(errors::Error::Runtime("next called on a non generator".to_string())),
+    };
+
+    Ok(generator.resume(interpreter)?.unwrap_or_else(|| Object::Done.wrap()))
+}
+
+impl Display for GeneratorObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "generator: {}", self.name)
+    }
+}
+
+impl PartialEq for GeneratorObject {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.coroutine, &other.coroutine)
+    }
+}
+
+impl PartialOrd for GeneratorObject {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        (self == other).then_some(std::cmp::Ordering::Equal)
+    }
+}
+
+/// iterates over a snapshot of values, used for strings and tuples
+#[derive(Debug, Clone)]
+pub struct IteratorObject {
+    name: String,
+    items: Arc<Mutex<std::vec::IntoIter<WrappedObject>>>,
+}
+
+impl IteratorObject {
+    pub fn new(name: String, items: Vec<WrappedObject>) -> Self {
+        Self {
+            name,
+            items: Arc::new(Mutex::new(items.into_iter())),
+        }
+    }
+
+    pub fn next_item(&self) -> Option<WrappedObject> {
+        self.items.lock().unwrap().next()
+    }
+
+    pub fn get(&self, name: &str, receiver: &WrappedObject) -> Result<WrappedObject, errors::Error> {
+        match name {
+            "next" => {
+                let method = NativeMethod::new(name.to_string(), 0, receiver.clone(), iterator_next);
+                Ok(Object::Callable(Callable::NativeMethod(method)).wrap())
+            }
+            "iter" => Ok(iterator_self(receiver)),
+            _ => Err(errors::Error::Runtime(format!(
+                "Undefined property {} on {}",
+                name, self
+            ))),
+        }
+    }
+}
+
+fn iterator_next(
+    _interpreter: &mut AstInterpreter,
+    receiver: &WrappedObject,
+    _arguments: &[WrappedObject],
+) -> Result<WrappedObject, errors::Error> {
+    match &*receiver.read().unwrap() {
+        Object::Iterator(iterator) => Ok(iterator.next_item().unwrap_or_else(|| Object::Done.wrap())),
+        _ => Err(errors::Error::Runtime("next called on a non iterator".to_string())),
+    }
+}
+
+/// `iter()` on an iterator returns the iterator itself
+fn iterator_self(receiver: &WrappedObject) -> WrappedObject {
+    let function = |_interpreter: &mut AstInterpreter,
+                    receiver: &WrappedObject,
+                    _arguments: &[WrappedObject]|
+     -> Result<WrappedObject, errors::Error> { Ok(receiver.clone()) };
+
+    let method = NativeMethod::new("iter".to_string(), 0, receiver.clone(), function);
+    Object::Callable(Callable::NativeMethod(method)).wrap()
+}
+
+impl Display for IteratorObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "iterator: {}", self.name)
+    }
+}
+
+impl PartialEq for IteratorObject {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.items, &other.items)
+    }
+}
+
+impl PartialOrd for IteratorObject {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        (self == other).then_some(std::cmp::Ordering::Equal)
+    }
+}
+
+/// the progress of a task on the interpreter's event loop
+#[derive(Debug)]
+pub enum TaskState {
+    /// the body of an async function, suspended at `await` between steps
+    Running(Box<Coroutine>),
+    /// finished later by a timer
+    Waiting,
+    /// finished once every gathered value has finished
+    Gathering(Vec<WrappedObject>),
+    Done(WrappedObject),
+    Failed(String),
+}
+
+/// a unit of work on the event loop, waited on with `await`
+#[derive(Debug, Clone)]
+pub struct TaskObject {
+    name: String,
+    pub state: Arc<Mutex<TaskState>>,
+    /// set once an `await` or a gathering task waits for the result
+    awaited: Arc<AtomicBool>,
+}
+
+impl TaskObject {
+    pub fn new(name: String, state: TaskState) -> Self {
+        Self {
+            name,
+            state: Arc::new(Mutex::new(state)),
+            awaited: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn mark_awaited(&self) {
+        self.awaited.store(true, Ordering::Relaxed);
+    }
+
+    /// the error of a task that failed without anything waiting for it
+    pub fn unawaited_failure(&self) -> Option<String> {
+        if self.awaited.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        self.outcome()?.err()
+    }
+
+    /// the result of the task, `None` while it is still pending
+    pub fn outcome(&self) -> Option<Result<WrappedObject, String>> {
+        let Ok(state) = self.state.try_lock() else {
+            return None;
+        };
+
+        match &*state {
+            TaskState::Running(_) | TaskState::Waiting => None,
+            TaskState::Done(value) => Some(Ok(value.clone())),
+            TaskState::Failed(message) => Some(Err(message.clone())),
+            TaskState::Gathering(values) => {
+                let mut results = Vec::new();
+                for value in values {
+                    match task_outcome(value)? {
+                        Ok(result) => results.push(result),
+                        Err(message) => return Some(Err(message)),
+                    }
+                }
+
+                Some(Ok(Object::Tuple(TupleObject::new(results)).wrap()))
+            }
+        }
+    }
+
+    pub fn finish(&self, result: Result<WrappedObject, String>) {
+        *self.state.lock().unwrap() = match result {
+            Ok(value) => TaskState::Done(value),
+            Err(message) => TaskState::Failed(message),
+        };
+    }
+}
+
+/// records that something waits for the value, if it is a task
+pub fn mark_awaited(value: &WrappedObject) {
+    if let Object::Task(task) = &*value.read().unwrap() {
+        task.mark_awaited();
+    }
+}
+
+/// the outcome of an awaited value, anything that is not a task is ready immediately
+pub fn task_outcome(value: &WrappedObject) -> Option<Result<WrappedObject, String>> {
+    match &*value.read().unwrap() {
+        Object::Task(task) => task.outcome(),
+        _ => Some(Ok(value.clone())),
+    }
+}
+
+impl Display for TaskObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "task: {}", self.name)
+    }
+}
+
+impl PartialEq for TaskObject {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+impl PartialOrd for TaskObject {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        (self == other).then_some(std::cmp::Ordering::Equal)
+    }
+}
+
+pub type ThreadResult = Result<WrappedObject, errors::Error>;
+
+#[derive(Debug)]
+enum ThreadState {
+    Running(JoinHandle<ThreadResult>),
+    Finished(Result<WrappedObject, String>),
+}
+
+/// a function running on its own thread, `join()` waits for its result
+#[derive(Debug, Clone)]
+pub struct ThreadHandle {
+    state: Arc<Mutex<ThreadState>>,
+}
+
+impl ThreadHandle {
+    pub fn new(handle: JoinHandle<ThreadResult>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ThreadState::Running(handle))),
+        }
+    }
+
+    pub fn get(&self, name: &str, receiver: &WrappedObject) -> Result<WrappedObject, errors::Error> {
+        match name {
+            "join" => {
+                let method = NativeMethod::new(name.to_string(), 0, receiver.clone(), thread_join);
+                Ok(Object::Callable(Callable::NativeMethod(method)).wrap())
+            }
+            _ => Err(errors::Error::Runtime(format!(
+                "Undefined property {} on thread",
+                name
+            ))),
+        }
+    }
+
+    /// waits for the thread to finish, joining again returns the same result
+    pub fn join(&self, interpreter: &AstInterpreter) -> Result<WrappedObject, errors::Error> {
+        let mut state = interpreter.blocking(|| self.state.lock().unwrap());
+
+        if let ThreadState::Running(_) = &*state {
+            let finished = ThreadState::Finished(Err("thread was not joined".to_string()));
+            let ThreadState::Running(handle) = std::mem::replace(&mut *state, finished) else {
+                unreachable!()
+            };
+
+            let result = match interpreter.blocking(|| handle.join()) {
+                Ok(Ok(value)) => Ok(value),
+                Ok(Err(errors::Error::Exit(code))) => Err(format!("thread exited with code {}", code)),
+                Ok(Err(err)) => Err(err.to_string()),
+                Err(_) => Err("thread panicked".to_string()),
+            };
+            *state = ThreadState::Finished(result);
+        }
+
+        match &*state {
+            ThreadState::Finished(Ok(value)) => Ok(value.clone()),
+            ThreadState::Finished(Err(message)) => Err(errors::Error::Runtime(message.clone())),
+            ThreadState::Running(_) => unreachable!(),
+        }
+    }
+}
+
+fn thread_join(
+    interpreter: &mut AstInterpreter,
+    receiver: &WrappedObject,
+    _arguments: &[WrappedObject],
+) -> Result<WrappedObject, errors::Error> {
+    let thread = match &*receiver.read().unwrap() {
+        Object::Thread(thread) => thread.clone(),
+        _ => return Err(errors::Error::Runtime("join called on a non thread".to_string())),
+    };
+
+    thread.join(interpreter)
+}
+
+impl Display for ThreadHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "thread")
+    }
+}
+
+impl PartialEq for ThreadHandle {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+impl PartialOrd for ThreadHandle {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        (self == other).then_some(std::cmp::Ordering::Equal)
+    }
+}
+
+#[derive(Debug, Default)]
+struct ChannelState {
+    queue: VecDeque<WrappedObject>,
+    closed: bool,
+}
+
+/// a queue of messages between threads, `recv()` blocks until a message arrives
+#[derive(Debug, Clone, Default)]
+pub struct ChannelObject {
+    shared: Arc<(Mutex<ChannelState>, Condvar)>,
+}
+
+impl ChannelObject {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, name: &str, receiver: &WrappedObject) -> Result<WrappedObject, errors::Error> {
+        let (arity, function): (i8, NativeFunction) = match name {
+            "send" => (1, channel_send),
+            "recv" => (0, channel_recv),
+            "close" => (0, channel_close),
+            _ => {
+                return Err(errors::Error::Runtime(format!(
+                    "Undefined property {} on channel",
+                    name
+                )))
+            }
+        };
+
+        let method = NativeMethod::new(name.to_string(), arity, receiver.clone(), function);
+        Ok(Object::Callable(Callable::NativeMethod(method)).wrap())
+    }
+
+    pub fn send(&self, value: WrappedObject) -> Result<(), errors::Error> {
+        let (state, available) = &*self.shared;
+        let mut state = state.lock().unwrap();
+
+        if state.closed {
+            return Err(errors::Error::Runtime(
+                "Cannot send on a closed channel".to_string(),
+            ));
+        }
+
+        state.queue.push_back(value);
+        available.notify_one();
+        Ok(())
+    }
+
+    /// waits for the next message, `None` once the channel is closed and drained
+    pub fn recv(&self, interpreter: &AstInterpreter) -> Option<WrappedObject> {
+        let (state, available) = &*self.shared;
+
+        loop {
+            let mut waiting = state.lock().unwrap();
+            if let Some(value) = waiting.queue.pop_front() {
+                return Some(value);
+            }
+
+            if waiting.closed {
+                return None;
+            }
+            drop(waiting);
+
+            // only the queue's length is looked at while the thread counts as stopped
+            interpreter.blocking(|| {
+                let state = state.lock().unwrap();
+                let _ready = available
+                    .wait_while(state, |state| state.queue.is_empty() && !state.closed)
+                    .unwrap();
+            });
+        }
+    }
+
+    pub fn close(&self) {
+        let (state, available) = &*self.shared;
+        state.lock().unwrap().closed = true;
+        available.notify_all();
+    }
+}
+
+fn channel_of(receiver: &WrappedObject) -> Result<ChannelObject, errors::Error> {
+    match &*receiver.read().unwrap() {
+        Object::Channel(channel) => Ok(channel.clone()),
+        _ => Err(errors::Error::Runtime("Expected a channel".to_string())),
+    }
+}
+
+fn channel_send(
+    _interpreter: &mut AstInterpreter,
+    receiver: &WrappedObject,
+    arguments: &[WrappedObject],
+) -> Result<WrappedObject, errors::Error> {
+    channel_of(receiver)?.send(arguments[0].clone())?;
+    Ok(Object::None.wrap())
+}
+
+fn channel_recv(
+    interpreter: &mut AstInterpreter,
+    receiver: &WrappedObject,
+    _arguments: &[WrappedObject],
+) -> Result<WrappedObject, errors::Error> {
+    Ok(channel_of(receiver)?
+        .recv(interpreter)
+        .unwrap_or_else(|| Object::None.wrap()))
+}
+
+fn channel_close(
+    _interpreter: &mut AstInterpreter,
+    receiver: &WrappedObject,
+    _arguments: &[WrappedObject],
+) -> Result<WrappedObject, errors::Error> {
+    channel_of(receiver)?.close();
+    Ok(Object::None.wrap())
+}
+
+impl Display for ChannelObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "channel")
+    }
+}
+
+impl PartialEq for ChannelObject {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.shared, &other.shared)
+    }
+}
+
+impl PartialOrd for ChannelObject {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        (self == other).then_some(std::cmp::Ordering::Equal)
+    }
+}
+
+#[derive(Debug)]
+struct MutexState {
+    value: WrappedObject,
+    locked: bool,
+}
+
+/// guards a value shared between threads, `lock()` blocks while another thread holds it
+#[derive(Debug, Clone)]
+pub struct MutexObject {
+    shared: Arc<(Mutex<MutexState>, Condvar)>,
+}
+
+impl MutexObject {
+    pub fn new(value: WrappedObject) -> Self {
+        let state = MutexState {
+            value,
+            locked: false,
+        };
+
+        Self {
+            shared: Arc::new((Mutex::new(state), Condvar::new())),
+        }
+    }
+
+    pub fn get(&self, name: &str, receiver: &WrappedObject) -> Result<WrappedObject, errors::Error> {
+        let (arity, function): (i8, NativeFunction) = match name {
+            "lock" => (0, mutex_lock),
+            "unlock" => (0, mutex_unlock),
+            "get" => (0, mutex_get),
+            "set" => (1, mutex_set),
+            "update" => (1, mutex_update),
+            _ => {
+                return Err(errors::Error::Runtime(format!(
+                    "Undefined property {} on mutex",
+                    name
+                )))
+            }
+        };
+
+        let method = NativeMethod::new(name.to_string(), arity, receiver.clone(), function);
+        Ok(Object::Callable(Callable::NativeMethod(method)).wrap())
+    }
+
+    pub fn lock(&self, interpreter: &AstInterpreter) {
+        let (state, released) = &*self.shared;
+
+        interpreter.blocking(|| {
+            let state = state.lock().unwrap();
+            let mut state = released.wait_while(state, |state| state.locked).unwrap();
+            state.locked = true;
+        });
+    }
+
+    pub fn unlock(&self) -> Result<(), errors::Error> {
+        let (state, released) = &*self.shared;
+        let mut state = state.lock().unwrap();
+
+        if !state.locked {
+            return Err(errors::Error::Runtime(
+                "Cannot unlock a mutex that is not locked".to_string(),
+            ));
+        }
+
+        state.locked = false;
+        released.notify_one();
+        Ok(())
+    }
+
+    pub fn value(&self) -> WrappedObject {
+        self.shared.0.lock().unwrap().value.clone()
+    }
+
+    pub fn set_value(&self, value: WrappedObject) {
+        self.shared.0.lock().unwrap().value = value;
+    }
+}
+
+fn mutex_of(receiver: &WrappedObject) -> Result<MutexObject, errors::Error> {
+    match &*receiver.read().unwrap() {
+        Object::Mutex(mutex) => Ok(mutex.clone()),
+        _ => Err(errors::Error::Runtime("Expected a mutex".to_string())),
+    }
+}
+
+fn mutex_lock(
+    interpreter: &mut AstInterpreter,
+    receiver: &WrappedObject,
+    _arguments: &[WrappedObject],
+) -> Result<WrappedObject, errors::Error> {
+    mutex_of(receiver)?.lock(interpreter);
+    Ok(Object::None.wrap())
+}
+
+fn mutex_unlock(
+    _interpreter: &mut AstInterpreter,
+    receiver: &WrappedObject,
+    _arguments: &[WrappedObject],
+) -> Result<WrappedObject, errors::Error> {
+    mutex_of(receiver)?.unlock()?;
+    Ok(Object::None.wrap())
+}
+
+fn mutex_get(
+    _interpreter: &mut AstInterpreter,
+    receiver: &WrappedObject,
+    _arguments: &[WrappedObject],
+) -> Result<WrappedObject, errors::Error> {
+    Ok(mutex_of(receiver)?.value())
+}
+
+fn mutex_set(
+    _interpreter: &mut AstInterpreter,
+    receiver: &WrappedObject,
+    arguments: &[WrappedObject],
+) -> Result<WrappedObject, errors::Error> {
+    mutex_of(receiver)?.set_value(arguments[0].clone());
+    Ok(Object::None.wrap())
+}
+
+/// replaces the value with the result of calling a function on it while holding the lock
+fn mutex_update(
+    interpreter: &mut AstInterpreter,
+    receiver: &WrappedObject,
+    arguments: &[WrappedObject],
+) -> Result<WrappedObject, errors::Error> {
+    let mutex = mutex_of(receiver)?;
+    let function = match &*arguments[0].read().unwrap() {
+        Object::Callable(callable) => callable.clone(),
+        _ => return Err(errors::Error::Runtime("update expects a function".to_string())),
+    };
+
+    mutex.lock(interpreter);
+    let result = function.call(interpreter, &vec![mutex.value()], &[]);
+    if let Ok(value) = &result {
+        mutex.set_value(value.clone());
+    }
+    mutex.unlock()?;
+
+    result
+}
+
+impl Display for MutexObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "mutex({})", self.value().read().unwrap())
+    }
+}
+
+impl PartialEq for MutexObject {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.shared, &other.shared)
+    }
+}
+
+impl PartialOrd for MutexObject {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        (self == other).then_some(std::cmp::Ordering::Equal)
+    }
+}
+
+/// the top-level definitions of an imported file, read as properties
+#[derive(Debug, Clone)]
+pub struct ModuleObject {
+    name: String,
+    environment: Arc<RwLock<Environment>>,
+}
+
+impl ModuleObject {
+    pub fn new(name: String, environment: Arc<RwLock<Environment>>) -> Self {
+        Self { name, environment }
+    }
+
+    pub fn get(&self, name: Symbol) -> Result<WrappedObject, errors::Error> {
+        self.environment.read().unwrap().get_local(name).ok_or_else(|| {
+            errors::Error::Runtime(format!("Undefined name {} in module {}", name, self.name))
+        })
+    }
+}
+
+impl Display for ModuleObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "module({})", self.name)
+    }
+}
+
+impl PartialEq for ModuleObject {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.environment, &other.environment)
+    }
+}
+
+impl PartialOrd for ModuleObject {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        (self == other).then_some(std::cmp::Ordering::Equal)
+    }
+}
+
+impl Trace for RwLock<Object> {
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Ok(object) = self.try_read() {
+            object.trace(tracer);
+        }
+    }
+
+    fn release(&self) {
+        if let Ok(mut object) = self.try_write() {
+            *object = Object::None;
+        }
+    }
+}
+
+impl Trace for Mutex<Coroutine> {
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Ok(coroutine) = self.try_lock() {
+            coroutine.trace(tracer);
+        }
+    }
+
+    fn release(&self) {
+        if let Ok(mut coroutine) = self.try_lock() {
+            coroutine.release();
+        }
+    }
+}
+
+impl Trace for Mutex<std::vec::IntoIter<WrappedObject>> {
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Ok(items) = self.try_lock() {
+            items.as_slice().iter().for_each(|item| item.trace(tracer));
+        }
+    }
+
+    fn release(&self) {
+        if let Ok(mut items) = self.try_lock() {
+            *items = Vec::new().into_iter();
+        }
+    }
+}
+
+impl Trace for Mutex<TaskState> {
+    fn trace(&self, tracer: &mut Tracer) {
+        let Ok(state) = self.try_lock() else {
+            return;
+        };
+
+        match &*state {
+            TaskState::Running(coroutine) => coroutine.trace(tracer),
+            TaskState::Gathering(values) => values.iter().for_each(|value| value.trace(tracer)),
+            TaskState::Done(value) => value.trace(tracer),
+            TaskState::Waiting | TaskState::Failed(_) => {}
+        }
+    }
+
+    fn release(&self) {
+        if let Ok(mut state) = self.try_lock() {
+            *state = TaskState::Waiting;
+        }
+    }
+}
+
+impl Trace for Mutex<ThreadState> {
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Ok(state) = self.try_lock() {
+            if let ThreadState::Finished(Ok(value)) = &*state {
+                value.trace(tracer);
+            }
+        }
+    }
+
+    fn release(&self) {
+        if let Ok(mut state) = self.try_lock() {
+            if let ThreadState::Finished(result) = &mut *state {
+                *result = Err("thread result was collected".to_string());
+            }
+        }
+    }
+}
+
+impl Trace for (Mutex<ChannelState>, Condvar) {
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Ok(state) = self.0.try_lock() {
+            state.queue.iter().for_each(|value| value.trace(tracer));
+        }
+    }
+
+    fn release(&self) {
+        if let Ok(mut state) = self.0.try_lock() {
+            state.queue.clear();
+        }
+    }
+}
+
+impl Trace for (Mutex<MutexState>, Condvar) {
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Ok(state) = self.0.try_lock() {
+            state.value.trace(tracer);
+        }
+    }
+
+    fn release(&self) {
+        if let Ok(mut state) = self.0.try_lock() {
+            state.value = Object::None.wrap();
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-use super::object::Object;
+use super::{object::Object, symbol::Symbol};
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
 pub enum TokenType {
@@ -16,26 +16,41 @@ pub enum TokenType {
     RightParen,
     Identifier,
     Dot,
+    DotDot,
+    Ellipsis,
     Comma,
     None,
     Eof,
     Caret,
 
     Fn,
+    Async,
+    Await,
     Class,
+    Enum,
     Let,
+    Const,
     Block,
     Delete,
     If,
     Else,
     For,
+    In,
     While,
     NewLine,
     End,
     Return,
+    Yield,
+    Break,
+    Continue,
     True,
     False,
     Include,
+    Import,
+    From,
+    As,
+    Match,
+    Case,
 
     And,
     Or,
@@ -50,11 +65,84 @@ pub enum TokenType {
     Less,
 }
 
+impl TokenType {
+    /// every token type, in declaration order
+    pub const ALL: [TokenType; 57] = [
+        Self::Number,
+        Self::String,
+        Self::Plus,
+        Self::Minus,
+        Self::Star,
+        Self::Slash,
+        Self::Percent,
+        Self::Colon,
+        Self::LeftParen,
+        Self::RightParen,
+        Self::Identifier,
+        Self::Dot,
+        Self::DotDot,
+        Self::Ellipsis,
+        Self::Comma,
+        Self::None,
+        Self::Eof,
+        Self::Caret,
+        Self::Fn,
+        Self::Async,
+        Self::Await,
+        Self::Class,
+        Self::Enum,
+        Self::Let,
+        Self::Const,
+        Self::Block,
+        Self::Delete,
+        Self::If,
+        Self::Else,
+        Self::For,
+        Self::In,
+        Self::While,
+        Self::NewLine,
+        Self::End,
+        Self::Return,
+        Self::Yield,
+        Self::Break,
+        Self::Continue,
+        Self::True,
+        Self::False,
+        Self::Include,
+        Self::Import,
+        Self::From,
+        Self::As,
+        Self::Match,
+        Self::Case,
+        Self::And,
+        Self::Or,
+        Self::Not,
+        Self::Equal,
+        Self::EqualEqual,
+        Self::ColonEqual,
+        Self::NotEqual,
+        Self::GreaterEqual,
+        Self::LessEqual,
+        Self::Greater,
+        Self::Less,
+    ];
+}
+
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub struct Token {
     pub token_type: TokenType,
     pub object: Object,
     pub line: usize,
+    /// the interned name of an identifier
+    pub symbol: Option<Symbol>,
+}
+
+impl Token {
+    /// the name of an identifier token, interned by the scanner
+    pub fn name(&self) -> Symbol {
+        self.symbol
+            .unwrap_or_else(|| Symbol::intern(&self.object.to_string()))
+    }
 }
 
 impl Display for Token {
@@ -0,0 +1,147 @@
+use std::{
+    fmt::Display,
+    ops::{Deref, DerefMut},
+    sync::{Arc, LockResult, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use crate::language::abstract_syntax_tree::heap::Tracer;
+
+use super::object::Object;
+
+/// A value held by the interpreter.
+///
+/// None, booleans and numbers are stored inline and copied, everything else lives on the heap
+/// behind a shared lock. `read()` and `write()` hand out an `Object` either way, so code working
+/// with values does not need to know where they are stored.
+#[derive(Debug, Clone)]
+pub enum Value {
+    None,
+    Bool(bool),
+    Number(f64),
+    Heap(Arc<RwLock<Object>>),
+}
+
+impl Value {
+    /// the number stored inline, without taking a lock
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Self::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    pub fn is_heap(&self) -> bool {
+        matches!(self, Self::Heap(_))
+    }
+
+    /// a copy of the object behind the value
+    pub fn to_object(&self) -> Object {
+        match self {
+            Self::Heap(object) => object.read().unwrap().clone(),
+            immediate => immediate.immediate(),
+        }
+    }
+
+    // mirrors `RwLock::read`, the guard is as large as an object so the error is as well
+    #[allow(clippy::result_large_err)]
+    pub fn read(&self) -> LockResult<ValueRef<'_>> {
+        match self {
+            Self::Heap(object) => match object.read() {
+                Ok(guard) => Ok(ValueRef::Heap(guard)),
+                Err(error) => Err(PoisonError::new(ValueRef::Heap(error.into_inner()))),
+            },
+            immediate => Ok(ValueRef::Immediate(immediate.immediate())),
+        }
+    }
+
+    /// writing to an inline value only changes the returned copy, values are replaced
+    /// rather than mutated in place
+    #[allow(clippy::result_large_err)]
+    pub fn write(&self) -> LockResult<ValueMut<'_>> {
+        match self {
+            Self::Heap(object) => match object.write() {
+                Ok(guard) => Ok(ValueMut::Heap(guard)),
+                Err(error) => Err(PoisonError::new(ValueMut::Heap(error.into_inner()))),
+            },
+            immediate => Ok(ValueMut::Immediate(immediate.immediate())),
+        }
+    }
+
+    /// whether both values are the same heap object
+    pub fn ptr_eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Self::Heap(left), Self::Heap(right)) => Arc::ptr_eq(left, right),
+            _ => false,
+        }
+    }
+
+    pub(crate) fn trace(&self, tracer: &mut Tracer) {
+        if let Self::Heap(object) = self {
+            tracer.visit(object);
+        }
+    }
+
+    fn immediate(&self) -> Object {
+        match self {
+            Self::None => Object::None,
+            Self::Bool(boolean) => Object::Bool(*boolean),
+            Self::Number(number) => Object::Number(*number),
+            Self::Heap(_) => unreachable!("heap values are not immediate"),
+        }
+    }
+}
+
+impl From<Object> for Value {
+    fn from(object: Object) -> Self {
+        object.wrap()
+    }
+}
+
+/// read access to a value, a copy for inline values and a lock guard for heap ones
+pub enum ValueRef<'a> {
+    Immediate(Object),
+    Heap(RwLockReadGuard<'a, Object>),
+}
+
+impl Deref for ValueRef<'_> {
+    type Target = Object;
+
+    fn deref(&self) -> &Object {
+        match self {
+            Self::Immediate(object) => object,
+            Self::Heap(guard) => guard,
+        }
+    }
+}
+
+impl Display for ValueRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+/// write access to a value, see `Value::write`
+pub enum ValueMut<'a> {
+    Immediate(Object),
+    Heap(RwLockWriteGuard<'a, Object>),
+}
+
+impl Deref for ValueMut<'_> {
+    type Target = Object;
+
+    fn deref(&self) -> &Object {
+        match self {
+            Self::Immediate(object) => object,
+            Self::Heap(guard) => guard,
+        }
+    }
+}
+
+impl DerefMut for ValueMut<'_> {
+    fn deref_mut(&mut self) -> &mut Object {
+        match self {
+            Self::Immediate(object) => object,
+            Self::Heap(guard) => guard,
+        }
+    }
+}
@@ -1,21 +1,149 @@
 use std::{
-    env, fs, io::{self, Write}, process::exit
+    env, ffi::OsString, fs, io::{self, Write}, path::{Path, PathBuf}, process::exit, thread
 };
 
-use nova_tw::language::{generate_parsed_ast, AstInterpreter, errors};
+use nova_tw::language::{
+    errors, generate_parsed_ast, optimize, AstInterpreter, FileSystemLoader, Resolution, Statement,
+    MANIFEST_FILE, STACK_SIZE,
+};
 
 const PROMPT: &str = ">>";
+const USAGE: &str = "usage: nova_tw [--max-stack megabytes] [--search-path directory]... [--optimize] [--dump-ast] [--no-cache] [--no-prelude] [--update-lock] [file | project directory]";
+/// environment variable listing directories searched for included and imported files
+const SEARCH_PATH_VARIABLE: &str = "NOVA_PATH";
+
+/// command line options
+#[derive(Default)]
+struct Options {
+    path: Option<String>,
+    /// memory limit of the interpreter's stacks in megabytes
+    max_stack: Option<usize>,
+    /// directories searched for included and imported files, before those in `NOVA_PATH`
+    search_paths: Vec<String>,
+    /// run the optimizer over parsed code
+    optimize: bool,
+    /// print the syntax tree, a file is not run afterwards
+    dump_ast: bool,
+    /// parse included files every time instead of reusing saved syntax trees
+    no_cache: bool,
+    /// start without the standard prelude
+    no_prelude: bool,
+    /// rewrite a project's lockfile instead of checking the packages it records
+    update_lock: bool,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--max-stack" => {
+                    let megabytes = args.next().ok_or("--max-stack expects a number of megabytes")?;
+                    let megabytes = megabytes
+                        .parse()
+                        .map_err(|_| format!("invalid stack size: {}", megabytes))?;
+                    options.max_stack = Some(megabytes);
+                }
+                "--search-path" => {
+                    let directory = args.next().ok_or("--search-path expects a directory")?;
+                    options.search_paths.push(directory);
+                }
+                "--optimize" => options.optimize = true,
+                "--dump-ast" => options.dump_ast = true,
+                "--no-cache" => options.no_cache = true,
+                "--no-prelude" => options.no_prelude = true,
+                "--update-lock" => options.update_lock = true,
+                _ if arg.starts_with("--") => return Err(format!("unknown option {}\n{}", arg, USAGE)),
+                _ if options.path.is_none() => options.path = Some(arg),
+                _ => return Err(USAGE.to_string()),
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// an interpreter finding files in the packages of `project`, if running one, and in the
+    /// search paths
+    fn interpreter(&self, interactive: bool, project: Option<&Resolution>) -> AstInterpreter {
+        let mut interpreter = if interactive {
+            AstInterpreter::interactive()
+        } else {
+            AstInterpreter::new()
+        };
+
+        if let Some(megabytes) = self.max_stack {
+            interpreter.set_max_stack_memory(megabytes * 1024 * 1024);
+        }
+        let mut loader = FileSystemLoader::new();
+        for directory in self.search_directories(env::var_os(SEARCH_PATH_VARIABLE)) {
+            loader.add_search_path(directory);
+        }
+        match project {
+            Some(project) => interpreter.set_module_loader(project.loader(loader)),
+            None => interpreter.set_module_loader(loader),
+        }
+        interpreter.set_optimize(self.optimize);
+        interpreter.set_ast_cache(!self.no_cache);
+        interpreter.set_prelude(!self.no_prelude);
+        interpreter
+    }
+
+    /// the directories given on the command line and then those listed in `nova_path`, the
+    /// value of `NOVA_PATH`, in the order they are searched
+    fn search_directories(&self, nova_path: Option<OsString>) -> Vec<PathBuf> {
+        let listed = nova_path.iter().flat_map(env::split_paths).collect::<Vec<_>>();
+        self.search_paths.iter().map(PathBuf::from).chain(listed).collect()
+    }
+
+    /// optimizes and prints parsed statements as requested
+    fn prepare(&self, statements: Vec<Statement>) -> Vec<Statement> {
+        let statements = if self.optimize {
+            optimize(statements)
+        } else {
+            statements
+        };
+
+        if self.dump_ast {
+            for statement in &statements {
+                println!("{:#?}", statement);
+            }
+        }
+        statements
+    }
+}
+
 fn main() {
-    let args: Vec<String> = env::args().collect::<Vec<String>>();
-    if args.len() > 1 {
-        run_file(&args[1])
-    } else {
-        repl()
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            exit(2)
+        }
+    };
+
+    // parsing nested code recurses on the native stack, run on a thread with room for it
+    let interpreter_thread = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || match &options.path {
+            Some(path) => match project_directory(Path::new(path)) {
+                Some(directory) => run_project(path, &directory, &options),
+                None => run_file(path, &options, None),
+            },
+            None => repl(&options),
+        })
+        .unwrap_or_else(|err| {
+            eprintln!("Error starting interpreter: {}", err);
+            exit(1)
+        });
+
+    if interpreter_thread.join().is_err() {
+        exit(101)
     }
 }
 
-fn repl() {
-    let mut interpreter = AstInterpreter::interactive();
+fn repl(options: &Options) {
+    let mut interpreter = options.interpreter(true, None);
 
     loop {
         let mut input = String::new();
@@ -37,9 +165,11 @@ fn repl() {
             println!("{}", err);
             continue;
         }
-        let parsed_ast = parsed_ast.unwrap();
+        let parsed_ast = options.prepare(parsed_ast.unwrap());
 
-        let result = interpreter.interpret(parsed_ast);
+        let result = interpreter
+            .interpret(parsed_ast)
+            .and_then(|_| interpreter.run_event_loop());
         //interpreter.print_environment();
         if let Err(err) = result {
             if let errors::Error::Exit(code) = err {
@@ -51,7 +181,47 @@ fn repl() {
     }
 }
 
-fn run_file(path: &str) {
+/// the directory of the project `path` belongs to: a directory given as a project, the one
+/// holding a given manifest, or the nearest one above a file that holds a manifest
+fn project_directory(path: &Path) -> Option<PathBuf> {
+    if path.is_dir() {
+        return Some(path.to_path_buf());
+    }
+
+    let path = fs::canonicalize(path).ok()?;
+    if path.file_name().is_some_and(|name| name == MANIFEST_FILE) {
+        return path.parent().map(Path::to_path_buf);
+    }
+
+    path.ancestors()
+        .skip(1)
+        .find(|directory| directory.join(MANIFEST_FILE).is_file())
+        .map(Path::to_path_buf)
+}
+
+/// resolves the dependencies of the project in `directory`, checks them against its lockfile
+/// and runs `path` with them, or the project's entry point when `path` names the project
+fn run_project(path: &str, directory: &Path, options: &Options) {
+    let resolution = Resolution::resolve(directory).and_then(|resolution| {
+        resolution.lock(options.update_lock)?;
+        Ok(resolution)
+    });
+    let resolution = match resolution {
+        Ok(resolution) => resolution,
+        Err(err) => {
+            println!("{}", err);
+            exit(1)
+        }
+    };
+
+    let names_project = Path::new(path).is_dir() || Path::new(path).ends_with(MANIFEST_FILE);
+    match names_project {
+        true => run_file(&resolution.entry().to_string_lossy(), options, Some(&resolution)),
+        false => run_file(path, options, Some(&resolution)),
+    }
+}
+
+fn run_file(path: &str, options: &Options, project: Option<&Resolution>) {
     let result = fs::read_to_string(path);
 
     if let Err(err) = result {
@@ -67,13 +237,21 @@ fn run_file(path: &str) {
         return;
     }
 
-    let parsed_ast = parsed_ast.unwrap();
+    let parsed_ast = options.prepare(parsed_ast.unwrap());
+    if options.dump_ast {
+        return;
+    }
 
-    let mut interpreter = AstInterpreter::new();
+    let mut interpreter = options.interpreter(false, project);
 
-    let result = interpreter.interpret(parsed_ast);
+    let result = interpreter
+        .interpret(parsed_ast)
+        .and_then(|_| interpreter.run_event_loop());
     #[cfg(feature = "debug")]
-    interpreter.print_environment();
+    {
+        interpreter.print_environment();
+        println!("{:?}", interpreter.collect_garbage());
+    }
 
     if let Err(err) = result {
         if let errors::Error::Exit(code) = err {
@@ -84,3 +262,54 @@ fn run_file(path: &str) {
         println!("{}", err);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs, path::PathBuf};
+
+    use nova_tw::language::generate_parsed_ast;
+
+    use super::{project_directory, Options};
+
+    #[test]
+    fn test_search_path_order() {
+        let arguments = ["--search-path", "cli", "--search-path", "more", "main.nova"];
+        let options = Options::parse(arguments.iter().map(|argument| argument.to_string())).unwrap();
+        let nova_path = env::join_paths(["first", "second"]).unwrap();
+
+        let directories = options.search_directories(Some(nova_path));
+        assert_eq!(directories, ["cli", "more", "first", "second"].map(PathBuf::from));
+        assert_eq!(options.search_directories(None), ["cli", "more"].map(PathBuf::from));
+    }
+
+    #[test]
+    fn test_no_prelude() {
+        let run = |arguments: &[&str]| {
+            let options = Options::parse(arguments.iter().map(|argument| argument.to_string())).unwrap();
+            let mut interpreter = options.interpreter(false, None);
+            interpreter.interpret(generate_parsed_ast("let number = abs(-3)\n", "").unwrap())
+        };
+
+        assert!(run(&["main.nova"]).is_ok());
+        assert!(run(&["--no-prelude", "main.nova"]).is_err());
+    }
+
+    #[test]
+    fn test_project_directory() {
+        let root = env::temp_dir().join(format!("nova_project_directory_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("app/src")).unwrap();
+        fs::write(root.join("app/nova.toml"), "").unwrap();
+        fs::write(root.join("app/src/main.nova"), "").unwrap();
+        fs::write(root.join("loose.nova"), "").unwrap();
+        let root = fs::canonicalize(root).unwrap();
+
+        assert_eq!(project_directory(&root.join("app")), Some(root.join("app")));
+        assert_eq!(project_directory(&root.join("app/nova.toml")), Some(root.join("app")));
+        assert_eq!(project_directory(&root.join("app/src/main.nova")), Some(root.join("app")));
+        assert_eq!(project_directory(&root.join("loose.nova")), None);
+        assert_eq!(project_directory(&root.join("missing.nova")), None);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}